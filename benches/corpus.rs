@@ -0,0 +1,49 @@
+//! Keystroke and batch throughput on a few kilobytes of Vietnamese prose, typed as
+//! Telex and VNI keys.
+//!
+//! The corpus is kept as Vietnamese text in `benches/data/corpus.txt`; the keys are
+//! derived from it with `uvie::reverse`, so edit the text, not the keys.
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use uvie::{InputMethod, UltraFastViEngine};
+
+const CORPUS: &str = include_str!("data/corpus.txt");
+
+fn bench_corpus(c: &mut Criterion) {
+    let mut group = c.benchmark_group("corpus");
+
+    for (method, keys) in [
+        (InputMethod::Telex, uvie::reverse::to_telex(CORPUS)),
+        (InputMethod::Vni, uvie::reverse::to_vni(CORPUS)),
+    ] {
+        // Also a smoke test: the keys type the corpus back
+        assert_eq!(uvie::transform(&keys, method), CORPUS, "{method:?}");
+        group.throughput(Throughput::Elements(keys.chars().count() as u64));
+
+        group.bench_with_input(BenchmarkId::new("feed", method.name()), &keys, |b, keys| {
+            let mut e = UltraFastViEngine::builder().input_method(method).build();
+            b.iter(|| {
+                e.clear();
+                for c in keys.chars() {
+                    black_box(e.feed(c));
+                }
+                black_box(e.commit());
+            })
+        });
+
+        group.bench_with_input(BenchmarkId::new("batch", method.name()), &keys, |b, keys| {
+            let mut e = UltraFastViEngine::builder().input_method(method).build();
+            let mut out = String::with_capacity(CORPUS.len());
+            b.iter(|| {
+                out.clear();
+                e.transform_str(keys, &mut out);
+                black_box(&out);
+            })
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_corpus);
+criterion_main!(benches);
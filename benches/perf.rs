@@ -9,6 +9,12 @@ fn type_seq(engine: &mut UltraFastViEngine, seq: &str) {
     }
 }
 
+fn transform_seq(engine: &mut UltraFastViEngine, out: &mut String, seq: &str) {
+    out.clear();
+    engine.transform_str(seq, out);
+    black_box(&out);
+}
+
 fn type_seq_vi(def: &vi::Definition, out: &mut String, seq: &str) {
     out.clear();
     vi_transform_buffer(def, seq.chars(), out);
@@ -24,6 +30,8 @@ fn bench_uvie_telex(c: &mut Criterion) {
         ("mixed", "clear free pro "),
         ("uow", "huows"),
         ("cluster", "nghees"),
+        // Run-together syllables: most keys are consonants that extend the last render
+        ("long_word", "nghieengtrangthanhcongchung"),
     ];
 
     for (name, seq) in cases {
@@ -60,6 +68,15 @@ fn bench_compare_telex(c: &mut Criterion) {
             })
         });
 
+        group.bench_with_input(BenchmarkId::new("uvie_batch", *name), seq, |b, input| {
+            let mut e = UltraFastViEngine::new();
+            e.set_input_method(InputMethod::Telex);
+            let mut out = String::new();
+            b.iter(|| {
+                transform_seq(&mut e, &mut out, input);
+            })
+        });
+
         group.bench_with_input(BenchmarkId::new("vi", *name), seq, |b, input| {
             let mut out = String::new();
             b.iter(|| {
@@ -91,6 +108,15 @@ fn bench_compare_vni(c: &mut Criterion) {
             })
         });
 
+        group.bench_with_input(BenchmarkId::new("uvie_batch", *name), seq, |b, input| {
+            let mut e = UltraFastViEngine::new();
+            e.set_input_method(InputMethod::Vni);
+            let mut out = String::new();
+            b.iter(|| {
+                transform_seq(&mut e, &mut out, input);
+            })
+        });
+
         group.bench_with_input(BenchmarkId::new("vi", *name), seq, |b, input| {
             let mut out = String::new();
             b.iter(|| {
@@ -126,6 +152,36 @@ fn bench_uvie_vni(c: &mut Criterion) {
     group.finish();
 }
 
+// Hosts that resync the engine after every key (saved per text field, restored on
+// focus) re-render keys that have not changed, which the render cache skips.
+fn bench_rerender(c: &mut Criterion) {
+    let mut group = c.benchmark_group("rerender");
+
+    for (name, seq) in [("simple", "phoos"), ("long", "nghieeengx")] {
+        group.bench_with_input(BenchmarkId::new("feed", name), seq, |b, input| {
+            let mut e = UltraFastViEngine::new();
+            b.iter(|| {
+                type_seq(&mut e, input);
+            })
+        });
+
+        group.bench_with_input(BenchmarkId::new("feed_then_restore", name), seq, |b, input| {
+            let mut e = UltraFastViEngine::new();
+            b.iter(|| {
+                e.clear();
+                for c in input.chars() {
+                    e.feed(c);
+                    let state = e.save_state();
+                    black_box(e.restore_state(state));
+                    black_box(e.preview());
+                }
+            })
+        });
+    }
+
+    group.finish();
+}
+
 // Placeholder for "vi-rs" comparison.
 // Once you provide the crates.io package name + the API to feed characters, we can add:
 // - a dev-dependency to that crate
@@ -136,6 +192,7 @@ criterion_group!(
     bench_uvie_telex,
     bench_uvie_vni,
     bench_compare_telex,
-    bench_compare_vni
+    bench_compare_vni,
+    bench_rerender
 );
 criterion_main!(benches);
@@ -0,0 +1,393 @@
+//! Types Vietnamese in any application: listens to the keyboard system-wide, feeds each
+//! keystroke to the engine and, when the rendering of the word changes, erases what the
+//! application shows with synthetic Backspaces and types the new text.
+//!
+//! ```text
+//! cargo run --example global_ime --features examples-global [-- telex|vni|viqr|auto]
+//! ```
+//!
+//! Ctrl+Shift (pressed and released on their own) turns it on and off. Keystrokes with
+//! Ctrl, Alt or Meta held are shortcuts and are left alone. Reading and sending input
+//! events needs X11 on Linux (not Wayland) and the accessibility permission on macOS.
+
+use std::time::{Duration, Instant};
+
+use enigo::{Direction, Enigo, Keyboard, Settings};
+use rdev::{EventType, Key as RKey};
+use uvie::{Delta, InputMethod, Key, UltraFastViEngine};
+
+/// How long synthetic keys may take to come back through the listener before they are
+/// no longer expected.
+const ECHO_TIMEOUT: Duration = Duration::from_millis(200);
+
+fn main() {
+    let method = match std::env::args().nth(1) {
+        Some(arg) => arg.parse().unwrap_or_else(|err| {
+            eprintln!("{arg:?}: {err}");
+            std::process::exit(2);
+        }),
+        None => InputMethod::Telex,
+    };
+    let mut enigo = Enigo::new(&Settings::default()).unwrap_or_else(|err| {
+        eprintln!("Cannot send key events: {err}");
+        std::process::exit(1);
+    });
+    let mut glue = Glue::new(method);
+    let mut echo = Echo::default();
+    println!("Typing {method}; Ctrl+Shift turns it on and off, Ctrl+C here quits.");
+
+    let listened = rdev::listen(move |event| {
+        let edit = match event.event_type {
+            EventType::KeyPress(key) => {
+                if echo.swallow(key, Instant::now()) {
+                    return;
+                }
+                glue.key_press(key, event.name.as_deref())
+            }
+            EventType::KeyRelease(key) => {
+                if glue.key_release(key) {
+                    println!("{}", if glue.enabled { "on" } else { "off" });
+                }
+                None
+            }
+            // A click may move the caret away from the word
+            EventType::ButtonPress(_) => {
+                glue.caret_moved();
+                None
+            }
+            _ => None,
+        };
+        if let Some(edit) = edit {
+            echo.expect(&edit, Instant::now());
+            apply(&mut enigo, &edit);
+        }
+    });
+    if let Err(err) = listened {
+        eprintln!("Cannot listen to key events: {err:?}");
+        std::process::exit(1);
+    }
+}
+
+fn apply(enigo: &mut Enigo, edit: &Edit) {
+    for _ in 0..edit.backspaces {
+        let _ = enigo.key(enigo::Key::Backspace, Direction::Click);
+    }
+    if !edit.text.is_empty() {
+        let _ = enigo.text(&edit.text);
+    }
+}
+
+/// Keys to send so that the application shows what the engine rendered.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Edit {
+    backspaces: usize,
+    text: String,
+}
+
+/// The engine key for a key press, from the text the OS layout says it types; `None`
+/// for keys that change nothing (lock keys).
+fn translate(key: RKey, name: Option<&str>) -> Option<Key> {
+    Some(match key {
+        RKey::Backspace => Key::Backspace,
+        RKey::Escape => Key::Escape,
+        RKey::Return | RKey::KpReturn => Key::Enter,
+        RKey::Tab => Key::Tab,
+        RKey::CapsLock | RKey::NumLock | RKey::ScrollLock => return None,
+        _ => {
+            let mut chars = name.unwrap_or_default().chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) if !c.is_control() => Key::Char(c),
+                _ => Key::Other,
+            }
+        }
+    })
+}
+
+/// Modifier keys held down.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+struct Modifiers {
+    ctrl: bool,
+    shift: bool,
+    alt: bool,
+    meta: bool,
+}
+
+impl Modifiers {
+    /// Records `key` going down or up; false when it is not a modifier. AltGr counts as
+    /// one but not as a shortcut: layouts use it to type letters.
+    fn update(&mut self, key: RKey, down: bool) -> bool {
+        let held = match key {
+            RKey::ControlLeft | RKey::ControlRight => &mut self.ctrl,
+            RKey::ShiftLeft | RKey::ShiftRight => &mut self.shift,
+            RKey::Alt => &mut self.alt,
+            RKey::MetaLeft | RKey::MetaRight => &mut self.meta,
+            RKey::AltGr => return true,
+            _ => return false,
+        };
+        *held = down;
+        true
+    }
+
+    fn shortcut(self) -> bool {
+        self.ctrl || self.alt || self.meta
+    }
+
+    fn toggle_chord(self) -> bool {
+        self.ctrl && self.shift && !self.alt && !self.meta
+    }
+}
+
+/// What the application shows after the key `typed` reached it, turned into the render
+/// `delta` describes: the application holds the previous render followed by `typed`.
+fn correction(delta: Delta<'_>, typed: char) -> Option<Edit> {
+    if delta.backspaces == 0
+        && let Some(rest) = delta.insert.strip_prefix(typed)
+    {
+        return (!rest.is_empty()).then(|| Edit {
+            backspaces: 0,
+            text: rest.to_owned(),
+        });
+    }
+    Some(Edit {
+        backspaces: delta.backspaces + 1,
+        text: delta.insert.to_owned(),
+    })
+}
+
+/// The edit turning `shown` into `wanted`, keeping their common prefix.
+fn diff(shown: &str, wanted: &str) -> Option<Edit> {
+    let common = shown
+        .char_indices()
+        .zip(wanted.chars())
+        .find(|((_, a), b)| a != b)
+        .map_or(shown.len().min(wanted.len()), |((i, _), _)| i);
+    let (shown, wanted) = (&shown[common..], &wanted[common..]);
+    (!shown.is_empty() || !wanted.is_empty()).then(|| Edit {
+        backspaces: shown.chars().count(),
+        text: wanted.to_owned(),
+    })
+}
+
+/// Key handling, independent of how events are read and sent.
+struct Glue {
+    engine: UltraFastViEngine,
+    enabled: bool,
+    mods: Modifiers,
+    // Ctrl+Shift are down and nothing else was pressed: releasing them toggles
+    toggle_armed: bool,
+}
+
+impl Glue {
+    fn new(method: InputMethod) -> Self {
+        Self {
+            engine: UltraFastViEngine::builder().input_method(method).build(),
+            enabled: true,
+            mods: Modifiers::default(),
+            toggle_armed: false,
+        }
+    }
+
+    /// Handles a key going down, which the application has already received, and
+    /// returns the edit that makes it show the engine's render.
+    fn key_press(&mut self, key: RKey, name: Option<&str>) -> Option<Edit> {
+        if self.mods.update(key, true) {
+            self.toggle_armed = self.mods.toggle_chord();
+            return None;
+        }
+        self.toggle_armed = false;
+        if !self.enabled {
+            return None;
+        }
+        if self.mods.shortcut() {
+            // Ctrl+A, Alt+Tab, ...: the text or the focus may have changed
+            self.caret_moved();
+            return None;
+        }
+        match translate(key, name)? {
+            Key::Char(c) => correction(self.engine.feed_delta(c), c),
+            key @ (Key::Backspace | Key::Escape) if !self.engine.raw().is_empty() => {
+                let mut shown = self.engine.preview().to_owned();
+                if key == Key::Backspace {
+                    // The application deleted the last char itself
+                    shown.pop();
+                }
+                let wanted = match self.engine.process(key) {
+                    uvie::FeedResult::Preview(wanted) => wanted,
+                    _ => return None,
+                };
+                diff(&shown, wanted)
+            }
+            // The key is past the word in the application already, so the word stays
+            // as it was last shown
+            key => {
+                self.engine.process(key);
+                None
+            }
+        }
+    }
+
+    /// Handles a key going up; true when it toggled the engine on or off.
+    fn key_release(&mut self, key: RKey) -> bool {
+        let armed = self.toggle_armed;
+        if !self.mods.update(key, false) || !armed {
+            return false;
+        }
+        self.toggle_armed = false;
+        self.enabled = !self.enabled;
+        self.engine.clear();
+        true
+    }
+
+    fn caret_moved(&mut self) {
+        self.engine.process(Key::Other);
+    }
+}
+
+/// Synthetic key presses on their way back through the listener, which must not be fed
+/// to the engine again.
+#[derive(Debug, Default)]
+struct Echo {
+    pending: usize,
+    until: Option<Instant>,
+}
+
+impl Echo {
+    fn expect(&mut self, edit: &Edit, now: Instant) {
+        self.pending += edit.backspaces + edit.text.chars().count();
+        self.until = Some(now + ECHO_TIMEOUT);
+    }
+
+    /// Whether the press of `key` at `now` is one of ours.
+    fn swallow(&mut self, key: RKey, now: Instant) -> bool {
+        if self.until.is_some_and(|until| now > until) {
+            self.pending = 0;
+            self.until = None;
+        }
+        if self.pending == 0 || matches!(key, RKey::ControlLeft | RKey::ControlRight | RKey::Alt | RKey::MetaLeft | RKey::MetaRight)
+        {
+            return false;
+        }
+        self.pending -= 1;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn edit(backspaces: usize, text: &str) -> Option<Edit> {
+        Some(Edit {
+            backspaces,
+            text: text.to_owned(),
+        })
+    }
+
+    /// Presses each char's key as an unshifted letter and collects the edits sent.
+    fn type_keys(glue: &mut Glue, keys: &str) -> Vec<Option<Edit>> {
+        keys.chars().map(|c| glue.key_press(RKey::KeyA, Some(&c.to_string()))).collect()
+    }
+
+    #[test]
+    fn translates_keys() {
+        assert_eq!(translate(RKey::KeyA, Some("a")), Some(Key::Char('a')));
+        assert_eq!(translate(RKey::KeyA, Some("A")), Some(Key::Char('A')));
+        assert_eq!(translate(RKey::Space, Some(" ")), Some(Key::Char(' ')));
+        assert_eq!(translate(RKey::Backspace, Some("\u{8}")), Some(Key::Backspace));
+        assert_eq!(translate(RKey::Return, Some("\r")), Some(Key::Enter));
+        assert_eq!(translate(RKey::Tab, None), Some(Key::Tab));
+        assert_eq!(translate(RKey::Escape, None), Some(Key::Escape));
+        assert_eq!(translate(RKey::LeftArrow, None), Some(Key::Other));
+        assert_eq!(translate(RKey::Delete, Some("\u{7f}")), Some(Key::Other));
+        assert_eq!(translate(RKey::CapsLock, None), None);
+    }
+
+    #[test]
+    fn corrections_account_for_the_key_already_typed() {
+        let delta = |backspaces, insert| Delta { backspaces, insert };
+        // Echoed as typed: nothing to do
+        assert_eq!(correction(delta(0, "v"), 'v'), None);
+        // `vieet`: the app shows `viee`, the engine wants `viêt`
+        assert_eq!(correction(delta(1, "êt"), 't'), edit(2, "êt"));
+        // `aas`: a tone on the last letter
+        assert_eq!(correction(delta(1, "ấ"), 's'), edit(2, "ấ"));
+        // A key typed as another letter: Telex `w` alone is `ư`
+        assert_eq!(correction(delta(0, "ư"), 'w'), edit(1, "ư"));
+        // A key typed as more than itself
+        assert_eq!(correction(delta(0, "ab"), 'a'), edit(0, "b"));
+    }
+
+    #[test]
+    fn diffs_keep_the_common_prefix() {
+        assert_eq!(diff("viê", "việ"), edit(1, "ệ"));
+        assert_eq!(diff("á", ""), edit(1, ""));
+        assert_eq!(diff("đ", "dd"), edit(1, "dd"));
+        assert_eq!(diff("ab", "ab"), None);
+    }
+
+    #[test]
+    fn types_a_word() {
+        let mut glue = Glue::new(InputMethod::Telex);
+        let edits = type_keys(&mut glue, "vieetj ");
+        assert_eq!(edits, [None, None, None, edit(2, "ê"), None, edit(3, "ệt"), None]);
+    }
+
+    #[test]
+    fn backspace_and_escape_rewrite_the_word() {
+        let mut glue = Glue::new(InputMethod::Telex);
+        type_keys(&mut glue, "as");
+        // The app deleted `á`; the engine only dropped the tone
+        assert_eq!(glue.key_press(RKey::Backspace, None), edit(0, "a"));
+        type_keys(&mut glue, "s");
+        assert_eq!(glue.key_press(RKey::Escape, None), edit(1, "as"));
+        // No word: backspace is the application's business
+        type_keys(&mut glue, " ");
+        assert_eq!(glue.key_press(RKey::Backspace, None), None);
+    }
+
+    #[test]
+    fn shortcuts_and_the_caret_end_the_word() {
+        let mut glue = Glue::new(InputMethod::Telex);
+        type_keys(&mut glue, "ho");
+        glue.key_press(RKey::ControlLeft, None);
+        assert_eq!(glue.key_press(RKey::KeyA, Some("a")), None);
+        glue.key_release(RKey::ControlLeft);
+        // A fresh word: no tone reaches back to `ho`
+        assert_eq!(type_keys(&mut glue, "f"), [None]);
+        assert_eq!(glue.engine.raw(), "f");
+        glue.key_press(RKey::LeftArrow, None);
+        assert_eq!(glue.engine.raw(), "");
+    }
+
+    #[test]
+    fn ctrl_shift_toggles() {
+        let mut glue = Glue::new(InputMethod::Telex);
+        glue.key_press(RKey::ControlLeft, None);
+        glue.key_press(RKey::ShiftLeft, None);
+        assert!(glue.key_release(RKey::ShiftLeft));
+        assert!(!glue.key_release(RKey::ControlLeft));
+        assert!(!glue.enabled);
+        assert_eq!(type_keys(&mut glue, "aa"), [None, None]);
+
+        // Ctrl+Shift+T is a shortcut, not the toggle
+        glue.key_press(RKey::ControlLeft, None);
+        glue.key_press(RKey::ShiftLeft, None);
+        glue.key_press(RKey::KeyT, Some("T"));
+        assert!(!glue.key_release(RKey::ShiftLeft));
+        glue.key_release(RKey::ControlLeft);
+        assert!(!glue.enabled);
+    }
+
+    #[test]
+    fn echoes_are_swallowed_once() {
+        let now = Instant::now();
+        let mut echo = Echo::default();
+        echo.expect(&Edit { backspaces: 1, text: "ê".into() }, now);
+        assert!(echo.swallow(RKey::Backspace, now));
+        assert!(echo.swallow(RKey::KeyA, now));
+        assert!(!echo.swallow(RKey::KeyA, now));
+
+        echo.expect(&Edit { backspaces: 2, text: String::new() }, now);
+        assert!(!echo.swallow(RKey::Backspace, now + ECHO_TIMEOUT * 2));
+    }
+}
@@ -0,0 +1,39 @@
+//! Feeds arbitrary text, a char at a time, into Telex and VNI engines: nothing may
+//! panic and every output must be UTF-8.
+//!
+//! `cargo +nightly fuzz run feed`
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use uvie::{EngineConfig, InputMethod, UltraFastViEngine};
+
+fuzz_target!(|data: &[u8]| {
+    let text = String::from_utf8_lossy(data);
+    for method in [InputMethod::Telex, InputMethod::Vni] {
+        let config = EngineConfig {
+            input_method: method,
+            ..EngineConfig::default()
+        };
+        feed(&mut UltraFastViEngine::with_config(config.clone()), &text);
+        // Buffers smaller than a word, so the capacity limits are hit too
+        feed(&mut UltraFastViEngine::<8, 12>::sized(config), &text);
+        check(&uvie::transform(&text, method));
+    }
+});
+
+fn feed<const RAW: usize, const OUT: usize>(engine: &mut UltraFastViEngine<RAW, OUT>, text: &str) {
+    for key in text.chars() {
+        // DEL stands in for backspace, so words are unwound as well as typed
+        if key == '\u{7f}' {
+            check(engine.backspace());
+        } else {
+            check(engine.feed(key));
+        }
+    }
+    check(engine.commit());
+}
+
+fn check(out: &str) {
+    assert!(std::str::from_utf8(out.as_bytes()).is_ok(), "invalid UTF-8: {:?}", out.as_bytes());
+}
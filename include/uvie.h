@@ -0,0 +1,34 @@
+/* C interface of the uvie Vietnamese input engine (Rust crate built with `--features ffi`). */
+#ifndef UVIE_H
+#define UVIE_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define UVIE_METHOD_TELEX 0
+#define UVIE_METHOD_VNI 1
+#define UVIE_METHOD_VIQR 2
+#define UVIE_METHOD_AUTO 3
+
+typedef struct UltraFastViEngine UltraFastViEngine;
+
+UltraFastViEngine *uvie_engine_new(void);
+void uvie_engine_free(UltraFastViEngine *engine);
+/* Returns 0, or -1 for an unknown method. */
+int uvie_engine_set_method(UltraFastViEngine *engine, int method);
+/* Writes the NUL-terminated UTF-8 render to `out` and returns its length without the NUL.
+ * A return value >= out_cap means `out` was too small: the key was still consumed, fetch
+ * the render with uvie_engine_rendered() and a buffer of at least the returned size + 1. */
+size_t uvie_engine_feed(UltraFastViEngine *engine, uint32_t codepoint, char *out, size_t out_cap);
+size_t uvie_engine_rendered(const UltraFastViEngine *engine, char *out, size_t out_cap);
+void uvie_engine_clear(UltraFastViEngine *engine);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* UVIE_H */
@@ -1,40 +1,70 @@
+/// Keys of the word being composed; `N` bytes in the heapless build.
 #[cfg(feature = "heapless")]
-pub type RawBuffer = heapless::String<32>;
+pub type RawBuffer<const N: usize = 32> = heapless::String<N>;
 
+/// Rendered output; `N` bytes in the heapless build.
 #[cfg(feature = "heapless")]
-pub type OutBuffer = heapless::String<128>;
+pub type OutBuffer<const N: usize = 128> = heapless::String<N>;
 
+/// Keys of the word being composed; grows as needed, `N` is the initial capacity.
 #[cfg(not(feature = "heapless"))]
-pub type RawBuffer = String;
+pub type RawBuffer<const N: usize = 32> = alloc::string::String;
 
+/// Rendered output; grows as needed, `N` is the initial capacity.
 #[cfg(not(feature = "heapless"))]
-pub type OutBuffer = String;
+pub type OutBuffer<const N: usize = 128> = alloc::string::String;
 
-#[cfg(all(not(feature = "std"), not(feature = "heapless")))]
+#[cfg(all(not(feature = "alloc"), not(feature = "heapless")))]
 compile_error!(
-    "no_std build requires `heapless` feature (use --no-default-features --features heapless)"
+    "no_std build requires the `alloc` or `heapless` feature (use --no-default-features --features alloc, or heapless)"
 );
 
 #[cfg(feature = "heapless")]
 #[inline(always)]
-pub fn new_raw_buffer() -> RawBuffer {
+pub fn new_raw_buffer<const N: usize>() -> RawBuffer<N> {
     RawBuffer::new()
 }
 
 #[cfg(feature = "heapless")]
 #[inline(always)]
-pub fn new_out_buffer() -> OutBuffer {
+pub fn new_out_buffer<const N: usize>() -> OutBuffer<N> {
     OutBuffer::new()
 }
 
 #[cfg(not(feature = "heapless"))]
 #[inline(always)]
-pub fn new_raw_buffer() -> RawBuffer {
-    String::with_capacity(32)
+pub fn new_raw_buffer<const N: usize>() -> RawBuffer<N> {
+    RawBuffer::with_capacity(N)
 }
 
 #[cfg(not(feature = "heapless"))]
 #[inline(always)]
-pub fn new_out_buffer() -> OutBuffer {
-    String::with_capacity(128)
+pub fn new_out_buffer<const N: usize>() -> OutBuffer<N> {
+    OutBuffer::with_capacity(N)
+}
+
+#[cfg(feature = "heapless")]
+#[inline(always)]
+pub fn push_char<const N: usize>(buf: &mut heapless::String<N>, c: char) -> bool {
+    buf.push(c).is_ok()
+}
+
+#[cfg(feature = "heapless")]
+#[inline(always)]
+pub fn push_str<const N: usize>(buf: &mut heapless::String<N>, s: &str) -> bool {
+    buf.push_str(s).is_ok()
+}
+
+#[cfg(not(feature = "heapless"))]
+#[inline(always)]
+pub fn push_char(buf: &mut alloc::string::String, c: char) -> bool {
+    buf.push(c);
+    true
+}
+
+#[cfg(not(feature = "heapless"))]
+#[inline(always)]
+pub fn push_str(buf: &mut alloc::string::String, s: &str) -> bool {
+    buf.push_str(s);
+    true
 }
@@ -0,0 +1,359 @@
+use crate::modes::InputMethod;
+use crate::tone::ToneStyle;
+
+/// Behavior toggles of an engine; see [`UltraFastViEngine::with_config`](crate::UltraFastViEngine::with_config).
+///
+/// With the `serde` feature, missing fields take their default so saved configs keep
+/// loading after new options are added.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct EngineConfig {
+    pub input_method: InputMethod,
+    pub tone_style: ToneStyle,
+    /// Lets a trailing `w` reach back to the vowel it modifies (Telex `tuw` → `tư`).
+    pub w_bubbling: bool,
+    /// Lets a `w` that modifies nothing type `ư` (Telex `tw` → `tư`); off, a word with
+    /// such a `w` stays as typed (`wow`).
+    pub w_shorthand: bool,
+    /// Commit the typed keys instead of a render that is not a Vietnamese syllable.
+    pub restore_english_on_commit: bool,
+    /// How far a word must look like Vietnamese to be converted.
+    pub validation: ValidationLevel,
+    /// Leave a word as typed while composing when its keys look English (see
+    /// [`looks_english`](crate::english::looks_english)).
+    pub english_detection: bool,
+    /// Punctuation that ends a word, on top of whitespace.
+    pub delimiters: Delimiters,
+    /// Unicode normalization form of the output.
+    pub output_form: OutputForm,
+    /// What huyền, hỏi or ngã on a word ending in p, t, c or ch does.
+    pub stop_final_tone: StopFinalTone,
+    /// Telex `[` `]` type ơ ư and `{` `}` type Ơ Ư, as in Unikey's extended Telex; off,
+    /// they are ordinary punctuation.
+    pub extended_telex: bool,
+    /// Whether Telex keys may be typed out of place (see [`TelexVariant`]).
+    pub telex_variant: TelexVariant,
+    /// Typed at the start of a word, makes the rest of it literal and is itself dropped
+    /// (`\vieejt` → `vieejt`). Typed twice, it is typed once.
+    pub escape_char: Option<char>,
+    /// Rewrites keys before they are composed, for non-QWERTY layouts (see [`KeyRemap`]).
+    pub key_remap: KeyRemap,
+    /// Capitalizes the first letter of the first word and of each word after `.`, `!` or
+    /// `?` and a space, unless the `.` ends a token with digits (`Q.1.`).
+    pub auto_capitalize: bool,
+    /// Whether `...` or `…` ends a sentence for
+    /// [`auto_capitalize`](Self::auto_capitalize).
+    pub ellipsis_ends_sentence: bool,
+    /// Spelling of a bare `i`/`y` ending a committed syllable (see [`YOrthography`]).
+    pub y_orthography: YOrthography,
+    /// Shorthand expansions as (typed keys, text) pairs.
+    #[cfg(feature = "std")]
+    pub macros: Vec<(String, String)>,
+}
+
+impl Default for EngineConfig {
+    fn default() -> Self {
+        Self {
+            input_method: InputMethod::Telex,
+            tone_style: ToneStyle::Modern,
+            w_bubbling: true,
+            w_shorthand: true,
+            restore_english_on_commit: true,
+            english_detection: false,
+            validation: ValidationLevel::Basic,
+            delimiters: Delimiters::default(),
+            output_form: OutputForm::Nfc,
+            stop_final_tone: StopFinalTone::Raw,
+            extended_telex: false,
+            telex_variant: TelexVariant::FreeStyle,
+            escape_char: Some('\\'),
+            key_remap: KeyRemap::IDENTITY,
+            auto_capitalize: false,
+            ellipsis_ends_sentence: false,
+            y_orthography: YOrthography::AsTyped,
+            #[cfg(feature = "std")]
+            macros: Vec::new(),
+        }
+    }
+}
+
+/// How Vietnamese letters are encoded in the output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum OutputForm {
+    /// Precomposed letters: `ệ` is U+1EC7.
+    #[default]
+    Nfc,
+    /// Base letter followed by combining marks, in canonical order: `ệ` is `e` U+0323 U+0302.
+    Nfd,
+}
+
+/// Handling of a tone Vietnamese does not allow before a stop final (`p`, `t`, `c`,
+/// `ch`), which only takes sắc or nặng.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum StopFinalTone {
+    /// Show the keys as typed: `hocf` stays `hocf`.
+    #[default]
+    Raw,
+    /// Leave the tone off: `hocf` → `hoc`.
+    Ignore,
+}
+
+/// How strictly a word is checked before its modifiers and tones are applied.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum ValidationLevel {
+    /// Always convert, even into something that is not Vietnamese: `clears` → `cleá`.
+    Off,
+    /// Leave words with impossible letter clusters as typed (`clears`), and commit
+    /// those that are not syllables as typed if
+    /// [`restore_english_on_commit`](EngineConfig::restore_english_on_commit) is on.
+    #[default]
+    Basic,
+    /// As `Basic`, but committing takes a whole syllable by
+    /// [`is_valid_syllable`](crate::validate::is_valid_syllable), so unfinished words
+    /// (`vieet`) and chat spellings (`kos`) are committed as typed.
+    Strict,
+}
+
+/// Spelling of a syllable that is a bare `i` or `y` after l, k, m, t, h, s or qu,
+/// where style guides differ (`lý`/`lí`, `kỹ`/`kĩ`, `quý`/`quí`). Applied when a word is
+/// committed; the tone stays on the vowel. Other syllables (`in`, `yêu`, `tuy`) are
+/// never respelled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum YOrthography {
+    /// Keep the vowel that was typed.
+    #[default]
+    AsTyped,
+    /// `lí` → `lý`, `quí` → `quý`.
+    PreferY,
+    /// `lý` → `lí`, `quý` → `quí`.
+    PreferI,
+}
+
+/// Set of ASCII word delimiters, one bit per code point. Non-ASCII chars are ignored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Delimiters(u128);
+
+impl Delimiters {
+    /// `. , ; : ! ? ( ) " '`
+    pub const DEFAULT: &'static [char] = &['.', ',', ';', ':', '!', '?', '(', ')', '"', '\''];
+
+    pub fn new(delims: &[char]) -> Self {
+        Self(
+            delims
+                .iter()
+                .filter(|c| c.is_ascii())
+                .fold(0, |mask, &c| mask | (1u128 << c as u32)),
+        )
+    }
+
+    #[inline(always)]
+    pub fn contains(self, c: char) -> bool {
+        c.is_ascii() && self.0 & (1u128 << c as u32) != 0
+    }
+
+    pub fn iter(self) -> impl Iterator<Item = char> {
+        (0u8..128).map(char::from).filter(move |&c| self.contains(c))
+    }
+}
+
+impl Default for Delimiters {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT)
+    }
+}
+
+// Stored as the string of its chars, e.g. `".,;"`.
+#[cfg(feature = "serde")]
+impl serde::Serialize for Delimiters {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut buf = [0u8; 128];
+        let mut len = 0;
+        for c in self.iter() {
+            buf[len] = c as u8;
+            len += 1;
+        }
+        // Only ASCII bytes were written
+        serializer.serialize_str(core::str::from_utf8(&buf[..len]).unwrap_or_default())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Delimiters {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct Visitor;
+
+        impl serde::de::Visitor<'_> for Visitor {
+            type Value = Delimiters;
+
+            fn expecting(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+                f.write_str("a string of ASCII delimiter characters")
+            }
+
+            fn visit_str<E: serde::de::Error>(self, v: &str) -> Result<Delimiters, E> {
+                match v.chars().find(|c| !c.is_ascii()) {
+                    Some(c) => Err(E::custom(format_args!("delimiter {c:?} is not ASCII"))),
+                    None => Ok(v.chars().fold(Delimiters(0), |d, c| Delimiters(d.0 | (1u128 << c as u32)))),
+                }
+            }
+        }
+
+        deserializer.deserialize_str(Visitor)
+    }
+}
+
+/// Table rewriting ASCII keys before they are composed, for keyboards that do not
+/// type Telex or VNI keys where QWERTY has them. Only composition sees the rewritten
+/// key: a word left as typed shows the keys the keyboard sent. Non-ASCII chars are
+/// never remapped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyRemap([u8; 128]);
+
+impl KeyRemap {
+    /// Every key composes as typed.
+    pub const IDENTITY: Self = Self::from_pairs(&[]);
+
+    /// Keys of a Dvorak layout compose as the QWERTY letters in their place: typing
+    /// `phos` with the QWERTY keys sends `ldro`, which is `phó`. Dvorak's `'` `,` `.`
+    /// `;` sit where QWERTY has letters and type those letters.
+    pub const DVORAK: Self = Self::from_pairs(&[
+        (b'\'', b'q'), (b',', b'w'), (b'.', b'e'), (b'p', b'r'), (b'y', b't'), (b'f', b'y'), (b'g', b'u'),
+        (b'c', b'i'), (b'r', b'o'), (b'l', b'p'), (b'o', b's'), (b'e', b'd'), (b'u', b'f'), (b'i', b'g'),
+        (b'd', b'h'), (b'h', b'j'), (b't', b'k'), (b'n', b'l'), (b';', b'z'), (b'q', b'x'), (b'j', b'c'),
+        (b'k', b'v'), (b'x', b'b'), (b'b', b'n'),
+        (b'"', b'Q'), (b'<', b'W'), (b'>', b'E'), (b':', b'Z'),
+    ]);
+
+    /// Letters of an AZERTY layout compose as the QWERTY letters in their place (`a`
+    /// and `q`, `z` and `w` swap); `m` and the punctuation keys are left alone.
+    pub const AZERTY: Self = Self::from_pairs(&[(b'a', b'q'), (b'q', b'a'), (b'z', b'w'), (b'w', b'z')]);
+
+    /// Maps each typed key to the key it composes as; pairs with a non-ASCII char are
+    /// ignored. A letter pair maps the capitals too.
+    pub fn new(pairs: &[(char, char)]) -> Self {
+        let mut remap = Self::IDENTITY;
+        pairs.iter().for_each(|&(typed, key)| remap.set(typed, key));
+        remap
+    }
+
+    const fn from_pairs(pairs: &[(u8, u8)]) -> Self {
+        let mut table = [0u8; 128];
+        let mut i = 0;
+        while i < 128 {
+            table[i] = i as u8;
+            i += 1;
+        }
+        let mut i = 0;
+        while i < pairs.len() {
+            let (typed, key) = pairs[i];
+            table[typed as usize] = key;
+            if typed.is_ascii_lowercase() && key.is_ascii_lowercase() {
+                table[typed.to_ascii_uppercase() as usize] = key.to_ascii_uppercase();
+            }
+            i += 1;
+        }
+        Self(table)
+    }
+
+    /// Makes `typed` compose as `key`, and a capital `typed` as a capital `key` when
+    /// both are letters. Ignored unless both are ASCII.
+    pub fn set(&mut self, typed: char, key: char) {
+        if !typed.is_ascii() || !key.is_ascii() {
+            return;
+        }
+        self.0[typed as usize] = key as u8;
+        if typed.is_ascii_lowercase() && key.is_ascii_lowercase() {
+            self.0[typed.to_ascii_uppercase() as usize] = key.to_ascii_uppercase() as u8;
+        }
+    }
+
+    /// The key `c` composes as.
+    #[inline(always)]
+    pub fn get(&self, c: char) -> char {
+        if c.is_ascii() { self.0[c as usize] as char } else { c }
+    }
+
+    pub fn is_identity(&self) -> bool {
+        *self == Self::IDENTITY
+    }
+
+    /// The keys this remaps, as (typed, composed) pairs.
+    pub fn iter(&self) -> impl Iterator<Item = (char, char)> + '_ {
+        (0u8..128).filter(|&c| self.0[c as usize] != c).map(|c| (char::from(c), char::from(self.0[c as usize])))
+    }
+}
+
+impl Default for KeyRemap {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+// Stored as the keys it changes, each typed key followed by its composed key, e.g.
+// `"aqqazwwzAQQAZWWZ"`.
+#[cfg(feature = "serde")]
+impl serde::Serialize for KeyRemap {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut buf = [0u8; 256];
+        let mut len = 0;
+        for (typed, key) in self.iter() {
+            buf[len] = typed as u8;
+            buf[len + 1] = key as u8;
+            len += 2;
+        }
+        // Only ASCII bytes were written
+        serializer.serialize_str(core::str::from_utf8(&buf[..len]).unwrap_or_default())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for KeyRemap {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct Visitor;
+
+        impl serde::de::Visitor<'_> for Visitor {
+            type Value = KeyRemap;
+
+            fn expecting(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+                f.write_str("a string of ASCII typed/composed key pairs")
+            }
+
+            fn visit_str<E: serde::de::Error>(self, v: &str) -> Result<KeyRemap, E> {
+                if let Some(c) = v.chars().find(|c| !c.is_ascii()) {
+                    return Err(E::custom(format_args!("key {c:?} is not ASCII")));
+                }
+                let bytes = v.as_bytes();
+                if !bytes.len().is_multiple_of(2) {
+                    return Err(E::custom(format_args!("key {:?} has no composed key", bytes[bytes.len() - 1] as char)));
+                }
+                let mut remap = KeyRemap::IDENTITY;
+                bytes.chunks(2).for_each(|pair| remap.0[pair[0] as usize] = pair[1]);
+                Ok(remap)
+            }
+        }
+
+        deserializer.deserialize_str(Visitor)
+    }
+}
+
+/// How strictly Telex keys must follow the letters they change.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum TelexVariant {
+    /// Modifiers only act right after their letter and tones only after the vowels:
+    /// `nana` and `truongw` stay as typed, `naa` is still `nâ`.
+    Strict,
+    /// A late modifier finds its letter anywhere in the word (`nana` → `nân`, `truongw`
+    /// → `trương`) and a tone may come before the vowels end (`hsoa` → `hoá`).
+    #[default]
+    FreeStyle,
+}
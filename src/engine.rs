@@ -1,6 +1,12 @@
-use crate::buffers::{OutBuffer, RawBuffer, new_out_buffer, new_raw_buffer};
-use crate::modes::{IS_TONE_KEY, InputMethod, Mode, mode_for};
-use crate::tone::{is_vowel_unicode, map_vowel_with_tone};
+use crate::config::{Delimiters, EngineConfig, KeyRemap, OutputForm, StopFinalTone, TelexVariant, ValidationLevel, YOrthography};
+use crate::buffers::{OutBuffer, RawBuffer, new_out_buffer, new_raw_buffer, push_char, push_str};
+use crate::modes::{IS_MODIFIER, IS_TONE_KEY, IS_VOWEL, InputMethod, Mode, mode_for};
+#[cfg(feature = "std")]
+use crate::tone::{BaseWord, to_nfd};
+#[cfg(feature = "dictionary")]
+use crate::tone::to_lower_vi;
+use crate::tone::{ToneStyle, is_vowel_unicode, map_vowel_with_tone, to_upper_vi, tone_position, tone_row_base, tone_vowel_index, write_decomposed};
+use crate::validate::{is_valid_coda, is_valid_letters};
 
 /// Bitmask lookup table for invalid Vietnamese consonant pairs.
 /// Index = (c1 - b'a') * 26 + (c2 - b'a'), value = true if pair is invalid.
@@ -20,66 +26,1528 @@ static INVALID_PAIR_TABLE: [bool; 676] = {
     mark!(b'p', b't'); mark!(b'p', b'c'); mark!(b'p', b'g'); mark!(b'p', b'q');
     mark!(b'p', b's'); mark!(b'p', b'k'); mark!(b'p', b'd'); mark!(b'p', b'f');
     mark!(b'p', b'b');
+    mark!(b's', b'm'); mark!(b's', b'n'); mark!(b's', b'c');
+    mark!(b't', b'w'); mark!(b's', b'w'); mark!(b'w', b'h');
     t
 };
 
-pub struct UltraFastViEngine {
-    raw_buffer: RawBuffer,
-    out_buffer: OutBuffer,
+/// English onsets checked on the first keys, before `r` or `w` turn into marks
+/// (`three` would otherwise read as `th` + hỏi, `swift` as `sư…`).
+const ENGLISH_ONSETS: [&[u8; 3]; 12] = [
+    b"str", b"scr", b"spl", b"spr", b"squ", b"thr", b"shr", b"sch", b"swe", b"swi", b"twe", b"twi",
+];
+
+fn starts_english(keys: &[u8]) -> bool {
+    let key = |i: usize| keys.get(i).map(u8::to_ascii_lowercase);
+    match (key(0), key(1), key(2)) {
+        (Some(b'w'), Some(b'h'), _) => true,
+        (Some(a), Some(b), Some(c)) => ENGLISH_ONSETS.contains(&&[a, b, c]),
+        _ => false,
+    }
+}
+
+/// Whether the lowercase letters `word` are `đ` alone or followed only by capitals
+/// (`ĐHQG`, `đHQG`): no syllable, but still what the user meant.
+fn is_d_abbreviation(word: &[char], upper: u32) -> bool {
+    word.first() == Some(&'đ')
+        && word[1..].iter().enumerate().all(|(i, c)| c.is_ascii_alphabetic() && upper & (1 << (i + 1)) != 0)
+}
+
+/// Layout an [`InputMethod::Auto`] word is typed in: VNI when a digit follows a vowel
+/// (`pho61`) or `d` (`d9`) and no Telex key shows (a tone letter after a vowel, `aa`,
+/// `ee`, `oo`, `dd` or `w`); Telex otherwise, ties included.
+fn detect_method(keys: &[u8]) -> InputMethod {
+    let (mut vni, mut telex) = (false, false);
+    let mut seen_vowel = false;
+    let mut prev = 0u8;
+    for &k in keys {
+        let k = k.to_ascii_lowercase();
+        match k {
+            b'a' | b'e' | b'o' => {
+                telex |= prev == k;
+                seen_vowel = true;
+            }
+            b'i' | b'u' | b'y' => seen_vowel = true,
+            b'0'..=b'9' => vni |= seen_vowel || k == b'9' && prev == b'd',
+            b's' | b'f' | b'r' | b'x' | b'j' => telex |= seen_vowel,
+            b'd' => telex |= prev == b'd',
+            b'w' => telex = true,
+            _ => {}
+        }
+        prev = k;
+    }
+    if vni && !telex { InputMethod::Vni } else { InputMethod::Telex }
+}
+
+/// How the engine currently treats the word being composed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Decision {
+    /// No word is being composed.
+    Idle,
+    /// The word is being converted and differs from the raw keys.
+    Composing,
+    /// The word is valid so far and will react to tone/modifier keys, but currently
+    /// renders exactly as typed (e.g. `nhan`).
+    ComposingUnchanged,
+    /// The engine gave up on the word and shows the raw keys (e.g. `clear`).
+    Fallback,
+}
+
+/// What a single keystroke did to the displayed composition.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeystrokeEffect {
+    /// The key was swallowed without changing the display (e.g. `z` with no tone to remove).
+    NoEffect,
+    /// The key was echoed as-is at the end of the word (a consonant, a Telex digit).
+    Appended,
+    /// The key rewrote part of the word (a tone, a modifier, a fallback to raw).
+    Recomposed,
+    /// The key committed the word.
+    Committed,
+}
+
+impl KeystrokeEffect {
+    /// True when the key was not simply echoed, so a host must not replay it itself.
+    pub fn consumed(self) -> bool {
+        matches!(self, KeystrokeEffect::NoEffect | KeystrokeEffect::Recomposed)
+    }
+}
+
+/// Rendered output plus the decision behind it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InputResult<'a> {
+    pub output: &'a str,
+    pub decision: Decision,
+    pub effect: KeystrokeEffect,
+}
+
+/// Minimal edit turning the previously displayed composition into the new one:
+/// delete `backspaces` chars, then type `insert`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Delta<'a> {
+    pub backspaces: usize,
+    pub insert: &'a str,
+}
+
+/// A word render not yet written anywhere.
+enum Rendered<const N: usize> {
+    /// The raw keys, verbatim.
+    Raw,
+    /// Expansion of the macro at this index of `config.macros`.
+    #[cfg(feature = "std")]
+    Macro(usize),
+    /// Lowercase letters, uppercased where `upper` has the slot's bit set.
+    Chars { buf: [char; N], len: usize, upper: u32 },
+}
+
+/// Respells the bare `i` or `y` ending `word` (lowercase letters) after l, k, m, t, h,
+/// s or qu as `form` prefers, keeping its tone: `lí` → `lý`.
+fn respell_i_y(word: &mut [char], form: YOrthography) {
+    let to = match form {
+        YOrthography::AsTyped => return,
+        YOrthography::PreferY => 'y',
+        YOrthography::PreferI => 'i',
+    };
+    let [onset @ .., vowel] = word else {
+        return;
+    };
+    if !matches!(onset, ['l' | 'k' | 'm' | 't' | 'h' | 's'] | ['q', 'u']) {
+        return;
+    }
+    if let Some((row, tone)) = tone_vowel_index(*vowel)
+        && matches!(tone_row_base(row), 'i' | 'y')
+    {
+        *vowel = map_vowel_with_tone(to, tone);
+    }
+}
+
+/// Where the text fed so far stands in its sentence, for `auto_capitalize`.
+#[derive(Clone, Copy)]
+struct Sentence {
+    // The next word starts a sentence.
+    start: bool,
+    // Sentence stop ending the text since the last space.
+    stop: Stop,
+    // The text since the last space has a digit: a `.` there ends an abbreviation.
+    digits: bool,
+}
+
+#[derive(Clone, Copy)]
+enum Stop {
+    None,
+    Period,
+    Ellipsis,
+    // `!` or `?`
+    Mark,
+}
+
+impl Sentence {
+    const START: Self = Self {
+        start: true,
+        stop: Stop::None,
+        digits: false,
+    };
+
+    fn track(&mut self, c: char, ellipsis_ends: bool) {
+        match c {
+            c if c.is_whitespace() => {
+                self.start |= match self.stop {
+                    Stop::None => false,
+                    Stop::Period => !self.digits,
+                    Stop::Ellipsis => ellipsis_ends,
+                    Stop::Mark => true,
+                };
+                self.stop = Stop::None;
+                self.digits = false;
+            }
+            '.' => {
+                self.stop = match self.stop {
+                    Stop::None => Stop::Period,
+                    Stop::Mark => Stop::Mark,
+                    Stop::Period | Stop::Ellipsis => Stop::Ellipsis,
+                }
+            }
+            '…' => self.stop = Stop::Ellipsis,
+            '!' | '?' => self.stop = Stop::Mark,
+            // Quotes and brackets keep both the end of a sentence and the start of the next
+            '"' | '\'' | '(' | ')' | '[' | ']' | '“' | '”' | '«' | '»' => {}
+            _ => {
+                self.start = false;
+                self.stop = Stop::None;
+                self.digits |= c.is_ascii_digit();
+            }
+        }
+    }
+}
+
+/// Letters of a word as resolved before validation and tone placement, kept so a plain
+/// consonant typed next can extend them instead of the word being composed again.
+#[derive(Clone)]
+struct Resolved<const N: usize> {
+    // Keys the letters were resolved from; none when `keys_len` is 0.
+    keys: [u8; N],
+    keys_len: usize,
+    chars: [char; N],
+    len: usize,
+    vowel_mask: u32,
+    upper_mask: u32,
+    last_tone_char: u8,
+    tone_cancelled: bool,
+    // Last key left after the first pass, which the next key may combine with.
+    last_key: u8,
+}
+
+impl<const N: usize> Resolved<N> {
+    const EMPTY: Self = Self {
+        keys: [0; N],
+        keys_len: 0,
+        chars: ['\0'; N],
+        len: 0,
+        vowel_mask: 0,
+        upper_mask: 0,
+        last_tone_char: 0,
+        tone_cancelled: false,
+        last_key: 0,
+    };
+}
+
+/// What a single keystroke did, from the host's point of view.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FeedResult<'a> {
+    /// Live render of the word being composed.
+    Preview(&'a str),
+    /// `word` is final; insert it followed by `delimiter`.
+    Committed { word: &'a str, delimiter: char },
+    /// The engine did not consume the key; insert it as typed.
+    PassThrough(char),
+    /// `word` is final; insert it, then let the host handle `key` (see
+    /// [`process`](UltraFastViEngine::process)).
+    CommittedThen { word: &'a str, key: Key },
+    /// The engine did not consume the named key; let the host handle it.
+    Forward(Key),
+}
+
+/// A key event for [`UltraFastViEngine::process`]: a typed char or one of the editing
+/// keys a text field reacts to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Key {
+    Char(char),
+    Backspace,
+    Escape,
+    Enter,
+    Tab,
+    /// Any other key: arrows, Home, a mouse click moving the caret.
+    Other,
+}
+
+/// A fixed-size buffer that overflowed, from [`UltraFastViEngine::last_error`].
+///
+/// Only the buffers of the `heapless` build have a capacity to overflow.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EngineError {
+    /// A key did not fit in the raw buffer and was dropped; the word is shown as typed.
+    RawOverflow,
+    /// Output did not fit in the output buffer and was cut short.
+    OutOverflow,
+}
+
+impl core::fmt::Display for EngineError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str(match self {
+            EngineError::RawOverflow => "raw key buffer overflowed",
+            EngineError::OutOverflow => "output buffer overflowed",
+        })
+    }
+}
+
+impl core::error::Error for EngineError {}
+
+/// Snapshot of the word being composed, from [`UltraFastViEngine::save_state`].
+///
+/// Tone cancellation and every other render detail are derived from the raw keys, so
+/// restoring reproduces the exact render of the engine that typed them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EngineState<const RAW: usize = 32> {
+    raw: RawBuffer<RAW>,
+    // Only there when a key was remapped
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "no_keys::<RAW>"))]
+    typed: RawBuffer<RAW>,
+    raw_locked: bool,
     input_method: InputMethod,
-    mode: &'static Mode,
+}
+
+#[cfg(feature = "serde")]
+fn no_keys<const RAW: usize>(keys: &RawBuffer<RAW>) -> bool {
+    keys.is_empty()
+}
+
+impl<const RAW: usize> EngineState<RAW> {
+    pub fn raw(&self) -> &str {
+        &self.raw
+    }
+
+    pub fn input_method(&self) -> InputMethod {
+        self.input_method
+    }
+}
+
+/// Keystroke-to-Vietnamese composer for a single text field.
+///
+/// The engine is plain owned data: it is `Send`, so keep one per thread or per client
+/// and move it wherever that client is served. It has no interior mutability and is not
+/// meant to be shared behind a lock between concurrent typists; clone a configured
+/// engine instead.
+///
+/// `RAW` and `OUT` are the byte capacities of the key and output buffers in the
+/// `heapless` build (e.g. `UltraFastViEngine::<16, 48>::sized(config)` for a small
+/// keyboard firmware). Words longer than `RAW` keys, or 32, are shown as typed; with
+/// `std` the buffers grow and that limit is all `RAW` changes.
+#[derive(Clone)]
+pub struct UltraFastViEngine<const RAW: usize = 32, const OUT: usize = 128> {
+    raw_buffer: RawBuffer<RAW>,
+    // Keys as the keyboard sent them, byte for byte with `raw_buffer`, once `key_remap`
+    // changed one in the word; empty while they are the keys in `raw_buffer`.
+    typed_buffer: RawBuffer<RAW>,
+    // Keys of the word committed by the last delimiter, for `reopen_last_word`.
+    last_word: RawBuffer<RAW>,
+    last_typed: RawBuffer<RAW>,
+    out_buffer: OutBuffer<OUT>,
+    // Preview shown before the last `feed_delta` call, used to diff against.
+    prev_buffer: OutBuffer<OUT>,
+    // Text of the segment passed to the running `feed_str` call.
+    seg_buffer: OutBuffer<OUT>,
+    // Keys and lock state `out_buffer` was last rendered from by `render_str`; empty
+    // once anything else has written it or the config changed.
+    render_key: RawBuffer<RAW>,
+    render_locked: bool,
+    // Resolved letters of the last word composed, for `extend_resolved`.
+    resolved: Resolved<RAW>,
+    // Set when the last render fell back to the raw keys.
+    fallback: bool,
+    // The current word was reverted to raw and keeps rendering literally until committed.
+    raw_locked: bool,
+    // First overflow since the last `clear`.
+    error: Option<EngineError>,
+    // Position in the sentence for `auto_capitalize`, and where it was before the word
+    // in `last_word`.
+    sentence: Sentence,
+    last_sentence: Sentence,
+    config: EngineConfig,
+    // Layout derived from `config`, or a custom one from `set_custom_mode`.
+    mode: Mode,
+    // Sorted lowercase words that may be converted, from `set_dictionary`.
+    #[cfg(feature = "dictionary")]
+    dictionary: Option<&'static [&'static str]>,
+}
+
+const _: () = {
+    const fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<UltraFastViEngine>();
+};
+
+impl<const RAW: usize, const OUT: usize> core::fmt::Debug for UltraFastViEngine<RAW, OUT> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("UltraFastViEngine")
+            .field("raw", &self.raw_buffer.as_str())
+            .field("rendered", &self.out_buffer.as_str())
+            .field("input_method", &self.config.input_method)
+            .field("tone_style", &self.config.tone_style)
+            .field("fallback", &self.fallback)
+            .field("raw_locked", &self.raw_locked)
+            .field("restore_english_on_commit", &self.config.restore_english_on_commit)
+            .field("validation", &self.config.validation)
+            .field("error", &self.error)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Configures an engine up front; see [`UltraFastViEngine::builder`].
+///
+/// ```
+/// use uvie::{InputMethod, ToneStyle, UltraFastViEngine};
+///
+/// let mut e = UltraFastViEngine::builder()
+///     .input_method(InputMethod::Vni)
+///     .tone_style(ToneStyle::Traditional)
+///     .build();
+/// assert_eq!(e.feed_str("hoa2"), "hòa");
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct EngineBuilder {
+    engine: UltraFastViEngine,
+}
+
+impl EngineBuilder {
+    pub fn input_method(mut self, method: InputMethod) -> Self {
+        self.engine.set_input_method(method);
+        self
+    }
+
+    pub fn tone_style(mut self, style: ToneStyle) -> Self {
+        self.engine.set_tone_style(style);
+        self
+    }
+
+    pub fn delimiters(mut self, delims: &[char]) -> Self {
+        self.engine.set_delimiters(delims);
+        self
+    }
+
+    pub fn output_form(mut self, form: OutputForm) -> Self {
+        self.engine.set_output_form(form);
+        self
+    }
+
+    pub fn w_shorthand(mut self, on: bool) -> Self {
+        self.engine.set_w_shorthand(on);
+        self
+    }
+
+    pub fn auto_capitalize(mut self, on: bool) -> Self {
+        self.engine.set_auto_capitalize(on);
+        self
+    }
+
+    pub fn y_orthography(mut self, form: YOrthography) -> Self {
+        self.engine.set_y_orthography(form);
+        self
+    }
+
+    pub fn telex_variant(mut self, variant: TelexVariant) -> Self {
+        self.engine.set_telex_variant(variant);
+        self
+    }
+
+    pub fn escape_char(mut self, escape: Option<char>) -> Self {
+        self.engine.set_escape_char(escape);
+        self
+    }
+
+    pub fn stop_final_tone(mut self, rule: StopFinalTone) -> Self {
+        self.engine.set_stop_final_tone(rule);
+        self
+    }
+
+    pub fn english_detection(mut self, on: bool) -> Self {
+        self.engine.set_english_detection(on);
+        self
+    }
+
+    pub fn extended_telex(mut self, on: bool) -> Self {
+        self.engine.set_extended_telex(on);
+        self
+    }
+
+    pub fn key_remap(mut self, remap: KeyRemap) -> Self {
+        self.engine.set_key_remap(remap);
+        self
+    }
+
+    /// Whether words that are not Vietnamese syllables are committed as typed
+    /// (see [`UltraFastViEngine::restore_english_on_commit`]). On by default.
+    pub fn restore_english_on_commit(mut self, on: bool) -> Self {
+        self.engine.restore_english_on_commit(on);
+        self
+    }
+
+    pub fn validation(mut self, level: ValidationLevel) -> Self {
+        self.engine.set_validation_level(level);
+        self
+    }
+
+    #[cfg(feature = "dictionary")]
+    pub fn dictionary(mut self, words: &'static [&'static str]) -> Self {
+        self.engine.set_dictionary(words);
+        self
+    }
+
+    pub fn build(self) -> UltraFastViEngine {
+        self.engine
+    }
+}
+
+impl Default for UltraFastViEngine {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl UltraFastViEngine {
+    /// Punctuation that ends a word unless [`set_delimiters`](Self::set_delimiters) says otherwise.
+    pub const DEFAULT_DELIMITERS: &'static [char] = Delimiters::DEFAULT;
+
+    /// Longest word, in keys, that is composed; longer ones (URLs, identifiers) are
+    /// shown exactly as typed.
+    pub const MAX_WORD_KEYS: usize = 32;
+
     pub fn new() -> Self {
-        let input_method = InputMethod::Telex;
+        Self::with_config(EngineConfig::default())
+    }
+
+    pub fn with_config(config: EngineConfig) -> Self {
+        Self::sized(config)
+    }
+
+    pub fn builder() -> EngineBuilder {
+        EngineBuilder::default()
+    }
+}
+
+impl<const RAW: usize, const OUT: usize> UltraFastViEngine<RAW, OUT> {
+    // `MAX_WORD_KEYS`, or fewer when the raw buffer is smaller; bounds the scratch
+    // arrays of `compose` and its 32-bit slot masks.
+    const WORD_KEYS: usize = if RAW < 32 { RAW } else { 32 };
+
+    /// Like [`with_config`](UltraFastViEngine::with_config), for an engine with other
+    /// buffer capacities than the default.
+    pub fn sized(config: EngineConfig) -> Self {
         Self {
-            raw_buffer: new_raw_buffer(),
-            out_buffer: new_out_buffer(),
-            input_method,
-            mode: mode_for(input_method),
+            raw_buffer: new_raw_buffer::<RAW>(),
+            typed_buffer: RawBuffer::new(),
+            last_word: new_raw_buffer::<RAW>(),
+            last_typed: RawBuffer::new(),
+            out_buffer: new_out_buffer::<OUT>(),
+            prev_buffer: new_out_buffer::<OUT>(),
+            seg_buffer: new_out_buffer::<OUT>(),
+            render_key: new_raw_buffer::<RAW>(),
+            render_locked: false,
+            resolved: Resolved::EMPTY,
+            fallback: false,
+            raw_locked: false,
+            error: None,
+            sentence: Sentence::START,
+            last_sentence: Sentence::START,
+            mode: derive_mode(config.input_method, &config),
+            config,
+            #[cfg(feature = "dictionary")]
+            dictionary: None,
         }
     }
 
+    pub fn config(&self) -> &EngineConfig {
+        &self.config
+    }
+
+    /// Applies `config` and re-renders the word being composed under it. Changing the
+    /// input method or w-bubbling rebuilds the layout from the built-in one, replacing
+    /// any [`set_custom_mode`](Self::set_custom_mode) layout.
+    pub fn set_config(&mut self, config: EngineConfig) -> &str {
+        if config.input_method != self.config.input_method || config.w_bubbling != self.config.w_bubbling {
+            self.mode = derive_mode(config.input_method, &config);
+        }
+        self.config = config;
+        self.config_changed();
+        self.render_str()
+    }
+
     pub fn clear(&mut self) {
         self.raw_buffer.clear();
+        self.typed_buffer.clear();
+        self.last_word.clear();
+        self.last_typed.clear();
         self.out_buffer.clear();
+        self.prev_buffer.clear();
+        self.seg_buffer.clear();
+        self.render_key.clear();
+        self.fallback = false;
+        self.raw_locked = false;
+        self.error = None;
+        self.sentence = Sentence::START;
+        self.last_sentence = Sentence::START;
+    }
+
+    /// The first buffer overflow since the engine was created or last [`clear`](Self::clear)ed.
+    ///
+    /// Only the fixed-size buffers of the `heapless` build overflow: keys are dropped
+    /// or output is cut short. Check this after feeding and `clear` to recover.
+    pub fn last_error(&self) -> Option<EngineError> {
+        self.error
+    }
+
+    #[inline(always)]
+    fn overflowed(&mut self, error: EngineError) {
+        self.error.get_or_insert(error);
+    }
+
+    /// Switches layouts and returns the word being composed re-rendered from its keys
+    /// under the new one (Telex `pho`, then VNI `61` → `phố`).
+    ///
+    /// With [`InputMethod::Auto`] each word is read as VNI when a digit follows one of
+    /// its vowels (`pho61` → `phố`) and as Telex otherwise, including words with both
+    /// kinds of keys (`tie6s`).
+    pub fn set_input_method(&mut self, method: InputMethod) -> &str {
+        self.config.input_method = method;
+        self.mode = derive_mode(self.config.input_method, &self.config);
+        self.config_changed();
+        self.render_str()
     }
 
-    pub fn set_input_method(&mut self, method: InputMethod) {
-        self.input_method = method;
-        self.mode = mode_for(method);
+    /// Switches to a custom layout built with [`ModeBuilder`](crate::modes::ModeBuilder).
+    pub fn set_custom_mode(&mut self, mode: Mode) {
+        self.config.input_method = mode.method;
+        self.mode = mode;
+        self.config_changed();
     }
 
     pub fn input_method(&self) -> InputMethod {
-        self.input_method
+        self.config.input_method
+    }
+
+    /// Chooses between new-style (`hoà`, default) and old-style (`hòa`) tone placement.
+    pub fn set_tone_style(&mut self, style: ToneStyle) {
+        self.config.tone_style = style;
+        self.config_changed();
+    }
+
+    pub fn tone_style(&self) -> ToneStyle {
+        self.config.tone_style
+    }
+
+    /// Selects precomposed (NFC, the default) or decomposed (NFD) output, from the
+    /// next render on.
+    pub fn set_output_form(&mut self, form: OutputForm) {
+        self.config.output_form = form;
+        self.config_changed();
+    }
+
+    /// Chooses whether huyền, hỏi or ngã on a word ending in p, t, c or ch shows the
+    /// keys as typed (the default) or is left off.
+    pub fn set_stop_final_tone(&mut self, rule: StopFinalTone) {
+        self.config.stop_final_tone = rule;
+        self.config_changed();
+    }
+
+    /// Key that, starting a word, passes the rest of it through unchanged (`\` by
+    /// default, `None` for none). The key itself is not typed unless pressed twice.
+    pub fn set_escape_char(&mut self, escape: Option<char>) {
+        self.config.escape_char = escape;
+    }
+
+    /// Chooses free-style Telex (the default), where modifiers and tones may be typed
+    /// late, or strict Telex, where they must follow their letters.
+    pub fn set_telex_variant(&mut self, variant: TelexVariant) {
+        self.config.telex_variant = variant;
+        self.config_changed();
+    }
+
+    /// Whether a lone Telex `w` types `ư` (the default) or leaves the word as typed, for
+    /// `www` or `wow`. Pairs like `uw` and `ow` work either way.
+    pub fn set_w_shorthand(&mut self, on: bool) {
+        self.config.w_shorthand = on;
+        self.config_changed();
+    }
+
+    /// Lets Telex `[` `]` `{` `}` type ơ ư Ơ Ư (off by default).
+    pub fn set_extended_telex(&mut self, on: bool) {
+        self.config.extended_telex = on;
+        self.config_changed();
+    }
+
+    /// Chooses how strictly words are checked before conversion; `Off` converts
+    /// everything, e.g. for transliteration or generating test input.
+    pub fn set_validation_level(&mut self, level: ValidationLevel) {
+        self.config.validation = level;
+        self.config_changed();
+    }
+
+    /// Shows a word as typed while it is composed once its keys look English, e.g.
+    /// `for` or `just` (off by default). A tone key pressed twice to type it
+    /// (`cass`) is caught too.
+    pub fn set_english_detection(&mut self, on: bool) {
+        self.config.english_detection = on;
+        self.config_changed();
     }
 
-    pub fn feed(&mut self, key: char) -> &str {
+    /// When on (the default), a word whose render is not a valid Vietnamese syllable is
+    /// committed as the keys that were typed, e.g. `expression` instead of a toned mess.
+    pub fn restore_english_on_commit(&mut self, on: bool) {
+        self.config.restore_english_on_commit = on;
+    }
+
+    /// Capitalizes words that start a sentence (see
+    /// [`EngineConfig::auto_capitalize`]), counting from the next key; the engine starts
+    /// a sentence when created or [`clear`](Self::clear)ed.
+    pub fn set_auto_capitalize(&mut self, on: bool) {
+        self.config.auto_capitalize = on;
+        self.invalidate_render();
+    }
+
+    /// How a bare `i`/`y` after l, k, m, t, h, s or qu is spelled in committed words.
+    pub fn set_y_orthography(&mut self, form: YOrthography) {
+        self.config.y_orthography = form;
+    }
+
+    /// Expands the word typed as `raw` into `expansion` when it is committed, e.g.
+    /// `vn` → `Việt Nam`. The expansion is emitted as-is; an uppercase first key
+    /// uppercases its first letter. Replaces any macro with the same keys.
+    #[cfg(feature = "std")]
+    pub fn add_macro(&mut self, raw: &str, expansion: &str) {
+        self.remove_macro(raw);
+        self.config.macros.push((raw.to_string(), expansion.to_string()));
+    }
+
+    /// Returns whether a macro for `raw` existed.
+    #[cfg(feature = "std")]
+    pub fn remove_macro(&mut self, raw: &str) -> bool {
+        let before = self.config.macros.len();
+        self.config.macros.retain(|(k, _)| !k.eq_ignore_ascii_case(raw));
+        self.config.macros.len() != before
+    }
+
+    #[cfg(feature = "std")]
+    fn macro_index(&self) -> Option<usize> {
+        if self.raw_locked || self.config.macros.is_empty() {
+            return None;
+        }
+        let raw = self.raw_buffer.as_str();
+        self.config.macros.iter().position(|(k, _)| k.eq_ignore_ascii_case(raw))
+    }
+
+    /// Only converts words found in `words` (lowercase, NFC, sorted like `sort()`); others
+    /// are committed as typed (`hax` stays `hax` unless `hã` is listed). Lookups are a
+    /// binary search at commit, without allocating.
+    #[cfg(feature = "dictionary")]
+    pub fn set_dictionary(&mut self, words: &'static [&'static str]) {
+        debug_assert!(words.is_sorted(), "dictionary words must be sorted");
+        self.dictionary = Some(words);
+    }
+
+    /// Goes back to converting every word that passes the syllable check.
+    #[cfg(feature = "dictionary")]
+    pub fn clear_dictionary(&mut self) {
+        self.dictionary = None;
+    }
+
+    /// Replaces the keys that end a word. Whitespace always does; non-ASCII entries are
+    /// ignored. Leave `'` or `-` out to keep them inside words.
+    pub fn set_delimiters(&mut self, delims: &[char]) {
+        self.config.delimiters = Delimiters::new(delims);
+    }
+
+    /// Rewrites the keys fed from now on before they are composed (see [`KeyRemap`]).
+    pub fn set_key_remap(&mut self, remap: KeyRemap) {
+        self.config.key_remap = remap;
+    }
+
+    #[inline(always)]
+    fn is_delimiter(&self, key: char) -> bool {
         if key.is_whitespace() {
-            self.render_str();
-            self.raw_buffer.clear();
-            let _ = self.out_buffer.push(key);
+            return true;
+        }
+        if !self.config.delimiters.contains(key) || self.extended_telex_keys(key).is_some() {
+            return false;
+        }
+        // Punctuation that doubles as a mark key (VIQR) acts as a mark mid-word.
+        self.raw_buffer.is_empty()
+            || (self.mode.classify[key as usize] & (IS_TONE_KEY | IS_MODIFIER) == 0
+                && (self.mode.mark_escape == 0 || key as u8 != self.mode.mark_escape))
+    }
+
+    /// Feeds one key and returns the render of the word being composed, or the
+    /// committed word and its delimiter. With NFC output a word of ASCII keys renders
+    /// to at most 3 bytes per key.
+    ///
+    /// Does not allocate once the buffers have grown to the longest word typed, which
+    /// with the default capacities is any word of up to 32 keys.
+    pub fn feed(&mut self, typed: char) -> &str {
+        let key = self.config.key_remap.get(typed);
+        if self.is_delimiter(key) {
+            self.render_commit();
+            self.end_word();
+            if !push_char(&mut self.out_buffer, typed) {
+                self.overflowed(EngineError::OutOverflow);
+            }
+            self.track_sentence(typed);
             return &self.out_buffer;
         }
-        let _ = self.raw_buffer.push(key.to_ascii_lowercase());
+        if self.starts_escape(key) {
+            self.raw_locked = true;
+            return self.render_str();
+        }
+        self.push_typed(key, typed);
         self.render_str()
     }
 
-    fn render_str(&mut self) -> &str {
+    /// Whether `key` is the escape char starting a word (a second one is typed).
+    #[inline(always)]
+    fn starts_escape(&self, key: char) -> bool {
+        self.raw_buffer.is_empty() && !self.raw_locked && self.config.escape_char == Some(key)
+    }
+
+    /// Keys an extended Telex shortcut stands for (`[` → `ow`), when those are on.
+    #[inline(always)]
+    fn extended_telex_keys(&self, key: char) -> Option<&'static str> {
+        if !self.config.extended_telex || self.mode.method != InputMethod::Telex {
+            return None;
+        }
+        match key {
+            '[' => Some("ow"),
+            ']' => Some("uw"),
+            '{' => Some("OW"),
+            '}' => Some("UW"),
+            _ => None,
+        }
+    }
+
+    /// Adds `key`, remapped from `typed`, to the raw keys, keeping `typed` for when the
+    /// word is shown as typed.
+    fn push_typed(&mut self, key: char, typed: char) {
+        if key == typed && self.typed_buffer.is_empty() {
+            return self.push_key(key);
+        }
+        if self.typed_buffer.is_empty() {
+            self.typed_buffer.clone_from(&self.raw_buffer);
+        }
+        let len = self.raw_buffer.len();
+        self.push_key(key);
+        // Keys an extended Telex shortcut or a pasted letter stands for are kept as such
+        let pushed = match key != typed && self.raw_buffer.len() == len + 1 {
+            true => push_char(&mut self.typed_buffer, typed),
+            false => push_str(&mut self.typed_buffer, &self.raw_buffer[len..]),
+        };
+        debug_assert!(pushed, "typed keys fit wherever the raw keys did");
+    }
+
+    /// The keys of the word as the keyboard sent them.
+    #[inline(always)]
+    fn typed(&self) -> &str {
+        if self.typed_buffer.is_empty() { &self.raw_buffer } else { &self.typed_buffer }
+    }
+
+    /// Adds `key` to the raw keys. A precomposed Vietnamese letter (pasted text) is
+    /// added as the keys typing it in the current mode, so keys fed after it still
+    /// apply (`việt` then `z` → `viêt`), and so is a combining mark of decomposed (NFD)
+    /// text; any other non-ASCII char leaves the word as typed.
+    fn push_key(&mut self, key: char) {
+        if let Some(keys) = self.extended_telex_keys(key) {
+            keys.chars().for_each(|k| self.push_key(k));
+            return;
+        }
+        if key.is_ascii() {
+            // A full fixed-size buffer (heapless) drops the key; the word stays as typed
+            // rather than composing without it
+            if !push_char(&mut self.raw_buffer, key) {
+                self.raw_locked = true;
+                self.overflowed(EngineError::RawOverflow);
+            }
+            return;
+        }
+        let mut keys = [0u8; 3];
+        match self.keys_for(key, &mut keys).or_else(|| self.keys_for_mark(key, &mut keys)) {
+            Some(n) => {
+                for &k in &keys[..n] {
+                    if !push_char(&mut self.raw_buffer, k as char) {
+                        self.raw_locked = true;
+                        self.overflowed(EngineError::RawOverflow);
+                    }
+                }
+            }
+            None => {
+                if !push_char(&mut self.raw_buffer, key) {
+                    self.overflowed(EngineError::RawOverflow);
+                }
+                self.raw_locked = true;
+            }
+        }
+    }
+
+    /// Writes the key adding the combining mark `c` to the letter before it, as in
+    /// decomposed `e` U+0302 U+0323 for `ệ`.
+    fn keys_for_mark(&self, c: char, keys: &mut [u8; 3]) -> Option<usize> {
+        let raw = self.raw_buffer.as_bytes();
+        if raw.is_empty() {
+            return None;
+        }
+        keys[0] = match c {
+            '\u{301}' => self.mode.tone_key(1)?,
+            '\u{300}' => self.mode.tone_key(2)?,
+            '\u{309}' => self.mode.tone_key(3)?,
+            '\u{303}' => self.mode.tone_key(4)?,
+            '\u{323}' => self.mode.tone_key(5)?,
+            '\u{302}' | '\u{306}' | '\u{31b}' => {
+                // Canonical order puts a dot below before the circumflex or breve
+                let last = *raw
+                    .iter()
+                    .rev()
+                    .find(|&&k| self.mode.classify[k.to_ascii_lowercase() as usize] & IS_TONE_KEY == 0)?;
+                let base = last.to_ascii_lowercase();
+                let letter = match (base, c) {
+                    (b'a', '\u{302}') => 'â',
+                    (b'e', '\u{302}') => 'ê',
+                    (b'o', '\u{302}') => 'ô',
+                    (b'a', '\u{306}') => 'ă',
+                    (b'o', '\u{31b}') => 'ơ',
+                    (b'u', '\u{31b}') => 'ư',
+                    _ => return None,
+                };
+                let key = self.mode.modifier_key(base, letter)?;
+                if last.is_ascii_uppercase() { key.to_ascii_uppercase() } else { key }
+            }
+            _ => return None,
+        };
+        Some(1)
+    }
+
+    /// Writes the keys typing the Vietnamese letter `c` to `keys`, returning how many.
+    fn keys_for(&self, c: char, keys: &mut [u8; 3]) -> Option<usize> {
+        let (base, modifier, tone) = self.mode.letter_keys(c)?;
+        keys[0] = base;
+        let mut n = 1;
+        if let Some(modifier) = modifier {
+            keys[n] = modifier;
+            n += 1;
+        }
+        if tone != 0 {
+            keys[n] = self.mode.tone_key(tone)?;
+            n += 1;
+        }
+        Some(n)
+    }
+
+    /// Like [`feed`](Self::feed), but writes the text straight into `out` instead of
+    /// the engine's own buffer, without allocating.
+    ///
+    /// [`rendered`](Self::rendered), [`preview`](Self::preview) and
+    /// [`decision`](Self::decision) are not updated by this call.
+    pub fn feed_into<W: core::fmt::Write>(&mut self, typed: char, out: &mut W) -> core::fmt::Result {
+        let key = self.config.key_remap.get(typed);
+        if self.is_delimiter(key) {
+            let rendered = self.compose_commit();
+            let res = self.write_rendered(&rendered, out);
+            self.track_word(&rendered);
+            self.end_word();
+            self.track_sentence(typed);
+            res?;
+            return out.write_char(typed);
+        }
+        if self.starts_escape(key) {
+            self.raw_locked = true;
+            return Ok(());
+        }
+        self.push_typed(key, typed);
+        let rendered = self.compose();
+        self.write_rendered(&rendered, out)
+    }
+
+    /// Forgets the keys of the word just committed, keeping them for `reopen_last_word`.
+    fn end_word(&mut self) {
+        core::mem::swap(&mut self.last_word, &mut self.raw_buffer);
+        core::mem::swap(&mut self.last_typed, &mut self.typed_buffer);
+        self.raw_buffer.clear();
+        self.typed_buffer.clear();
+        self.raw_locked = false;
+    }
+
+    /// Finalizes the word being composed without a delimiter (Enter, focus loss) and
+    /// returns it; `""` when nothing was being composed.
+    pub fn commit(&mut self) -> &str {
         if self.raw_buffer.is_empty() {
             self.out_buffer.clear();
             return &self.out_buffer;
         }
+        self.render_commit();
+        self.end_word();
+        &self.out_buffer
+    }
+
+    /// Feeds every char of `s` and returns what that produced: the words it committed
+    /// followed by the render of the word still being composed. A word already in
+    /// progress is included whole.
+    pub fn feed_str(&mut self, s: &str) -> &str {
+        self.seg_buffer.clear();
+        for c in s.chars() {
+            self.feed(c);
+            if self.raw_buffer.is_empty() && !push_str(&mut self.seg_buffer, &self.out_buffer) {
+                self.overflowed(EngineError::OutOverflow);
+            }
+        }
+        if !self.raw_buffer.is_empty() && !push_str(&mut self.seg_buffer, &self.out_buffer) {
+            self.overflowed(EngineError::OutOverflow);
+        }
+        &self.seg_buffer
+    }
+
+    /// Like [`feed`](Self::feed), but tells previews, commits and untouched keys apart.
+    ///
+    /// Outside a word, keys that cannot start one (digits in Telex, punctuation that
+    /// is not a delimiter) pass through without touching the engine state.
+    pub fn feed_event(&mut self, key: char) -> FeedResult<'_> {
+        let composing = !self.raw_buffer.is_empty();
+        let mapped = self.config.key_remap.get(key);
+        if !composing && !mapped.is_alphabetic() && (!mapped.is_ascii() || self.mode.classify[mapped as usize] == 0) {
+            self.last_word.clear();
+            self.out_buffer.clear();
+            if !push_char(&mut self.out_buffer, key) {
+                self.overflowed(EngineError::OutOverflow);
+            }
+            self.track_sentence(key);
+            return FeedResult::PassThrough(key);
+        }
+        if self.is_delimiter(mapped) {
+            self.feed(key);
+            // A full heapless buffer may have dropped the delimiter
+            let out = self.out_buffer.as_str();
+            return FeedResult::Committed {
+                word: out.strip_suffix(key).unwrap_or(out),
+                delimiter: key,
+            };
+        }
+        FeedResult::Preview(self.feed(key))
+    }
+
+    /// Handles a key event the way a text field expects.
+    ///
+    /// Chars go through [`feed_event`](Self::feed_event). While a word is composed,
+    /// Backspace removes its last key and Escape reverts it to the keys typed, both
+    /// returning the new preview; Enter, Tab and other keys commit it and hand the key
+    /// back. Other keys may have moved the caret, so the committed word cannot be
+    /// [reopened](Self::reopen_last_word) after them. Keys that find no word are
+    /// forwarded untouched.
+    pub fn process(&mut self, key: Key) -> FeedResult<'_> {
+        if let Key::Char(c) = key {
+            return self.feed_event(c);
+        }
+        if self.raw_buffer.is_empty() {
+            if key == Key::Other {
+                self.last_word.clear();
+            }
+            self.track_key(key);
+            return FeedResult::Forward(key);
+        }
+        match key {
+            Key::Backspace => FeedResult::Preview(self.undo_last_key()),
+            Key::Escape => FeedResult::Preview(self.revert_raw()),
+            _ => {
+                self.commit();
+                if key == Key::Other {
+                    self.last_word.clear();
+                }
+                self.track_key(key);
+                FeedResult::CommittedThen {
+                    word: &self.out_buffer,
+                    key,
+                }
+            }
+        }
+    }
+
+    /// Like [`feed`](Self::feed), but also reports how the word is being treated.
+    pub fn feed_result(&mut self, key: char) -> InputResult<'_> {
+        let commits = self.is_delimiter(self.config.key_remap.get(key));
+        let delta = self.feed_delta(key);
+        let effect = if commits {
+            KeystrokeEffect::Committed
+        } else if delta.backspaces == 0 && delta.insert.is_empty() {
+            KeystrokeEffect::NoEffect
+        } else if delta.backspaces == 0 && delta.insert.chars().eq(core::iter::once(key)) {
+            KeystrokeEffect::Appended
+        } else {
+            KeystrokeEffect::Recomposed
+        };
+        InputResult {
+            decision: self.decision(),
+            effect,
+            output: &self.out_buffer,
+        }
+    }
+
+    pub fn save_state(&self) -> EngineState<RAW> {
+        EngineState {
+            raw: self.raw_buffer.clone(),
+            typed: self.typed_buffer.clone(),
+            raw_locked: self.raw_locked,
+            input_method: self.config.input_method,
+        }
+    }
+
+    /// Resumes the composition captured by [`save_state`](Self::save_state) and returns
+    /// its render. A custom layout is kept when it is based on the saved method.
+    pub fn restore_state(&mut self, state: EngineState<RAW>) -> &str {
+        if self.config.input_method != state.input_method {
+            self.set_input_method(state.input_method);
+        }
+        self.raw_buffer = state.raw;
+        self.typed_buffer = state.typed;
+        self.raw_locked = state.raw_locked;
+        self.render_str()
+    }
+
+    /// Makes the word committed by the last delimiter the active composition again, so
+    /// further keys edit it (`hoa `, Backspace in the editor, `s` → `hoá`).
+    ///
+    /// Only the word right before the delimiter can be reopened, and only while no new
+    /// word has been started. The host removes the delimiter itself. Returns whether a
+    /// word was reopened.
+    pub fn reopen_last_word(&mut self) -> bool {
+        if !self.raw_buffer.is_empty() || self.last_word.is_empty() {
+            return false;
+        }
+        core::mem::swap(&mut self.last_word, &mut self.raw_buffer);
+        core::mem::swap(&mut self.last_typed, &mut self.typed_buffer);
+        self.sentence = self.last_sentence;
+        self.invalidate_render();
+        self.render_str();
+        true
+    }
+
+    /// Removes the last keystroke of the word being composed and re-renders it.
+    ///
+    /// The decision is recomputed from the remaining keys, so a word that fell back
+    /// can become composable again.
+    pub fn backspace(&mut self) -> &str {
+        self.undo_last_key()
+    }
+
+    /// Steps the composition back one keystroke (for Ctrl+Z in an editor) and returns
+    /// the new render, which is empty once no keys are left.
+    ///
+    /// Undoing the second key of a cancelled tone (`ss`) brings the tone back.
+    pub fn undo_last_key(&mut self) -> &str {
+        self.raw_buffer.pop();
+        self.typed_buffer.pop();
+        if self.raw_buffer.is_empty() {
+            self.raw_locked = false;
+        }
+        self.render_str()
+    }
+
+    /// The current word with each tone applied, in the order ngang, sắc, huyền, hỏi,
+    /// ngã, nặng, for a tone-picker popup.
+    ///
+    /// Syllables ending in a stop (c, ch, p, t) only offer sắc and nặng. Returns an
+    /// empty list when nothing is composed or the word fell back to raw.
+    #[cfg(feature = "std")]
+    pub fn tone_variants(&self) -> Vec<String> {
+        if matches!(self.decision(), Decision::Idle | Decision::Fallback) {
+            return Vec::new();
+        }
+        if self.config.output_form == OutputForm::Nfd {
+            let mut nfc = self.clone();
+            nfc.set_output_form(OutputForm::Nfc);
+            nfc.render_str();
+            return nfc.tone_variants().iter().map(|v| to_nfd(v)).collect();
+        }
+        let Some(base) = BaseWord::parse(&self.out_buffer) else {
+            return Vec::new();
+        };
+        let Some(target) = base.tone_position(self.config.tone_style) else {
+            return Vec::new();
+        };
+        let stop = base.has_stop_coda();
+        (0..=5u8)
+            .filter(|&t| !stop || t == 1 || t == 5)
+            .map(|t| base.with_tone(t, Some(target)).collect())
+            .collect()
+    }
+
+    /// Discards the Vietnamese rendering of the current word (the Esc key in most IMEs).
+    ///
+    /// The output becomes exactly the keys typed so far, and further keys of the same
+    /// word are appended literally until the next delimiter.
+    pub fn revert_raw(&mut self) -> &str {
+        if !self.raw_buffer.is_empty() {
+            self.raw_locked = true;
+        }
+        self.render_str()
+    }
+
+    /// Keys typed for the word being composed; empty right after a commit.
+    pub fn raw(&self) -> &str {
+        self.typed()
+    }
+
+    /// Render of the word being composed as of the most recent call, without
+    /// re-rendering; `""` once it has been committed or cleared. Safe to call from
+    /// paint callbacks that only hold `&self`.
+    pub fn preview(&self) -> &str {
+        if self.raw_buffer.is_empty() { "" } else { &self.out_buffer }
+    }
+
+    /// The last render, without re-rendering. After a commit it holds the committed
+    /// word followed by its delimiter.
+    pub fn rendered(&self) -> &str {
+        &self.out_buffer
+    }
+
+    pub fn decision(&self) -> Decision {
+        if self.raw_buffer.is_empty() {
+            Decision::Idle
+        } else if self.fallback {
+            Decision::Fallback
+        } else if self.out_buffer.as_str() == self.typed() {
+            Decision::ComposingUnchanged
+        } else {
+            Decision::Composing
+        }
+    }
+
+    /// Like [`feed`](Self::feed), but returns the suffix edit to apply to the text the
+    /// host already displays for the word being composed.
+    pub fn feed_delta(&mut self, key: char) -> Delta<'_> {
+        // Only the composing word is editable; committed text is never patched.
+        let composing = !self.raw_buffer.is_empty();
+        core::mem::swap(&mut self.prev_buffer, &mut self.out_buffer);
+        self.invalidate_render();
+        if !composing {
+            self.prev_buffer.clear();
+        }
+        self.feed(key);
+
+        let prev = self.prev_buffer.as_str();
+        let next = self.out_buffer.as_str();
+        let mut prefix = 0usize;
+        for ((pi, pc), nc) in prev.char_indices().zip(next.chars()) {
+            if pc != nc {
+                break;
+            }
+            prefix = pi + pc.len_utf8();
+        }
+
+        Delta {
+            backspaces: prev[prefix..].chars().count(),
+            insert: &next[prefix..],
+        }
+    }
+
+    /// Converts a whole string, appending the result to `out`.
+    ///
+    /// Runs of word keys go through the same rendering as [`feed`](Self::feed); every
+    /// other character is copied verbatim. The trailing word is rendered even without a
+    /// delimiter, and the engine is cleared afterwards, except for
+    /// [`last_error`](Self::last_error).
+    #[cfg(feature = "std")]
+    pub fn transform_str(&mut self, input: &str, out: &mut String) {
+        self.clear();
+        for c in input.chars() {
+            self.transform_push(c, out);
+        }
+        self.transform_finish(out);
+        let error = self.error;
+        self.clear();
+        self.error = error;
+    }
+
+    /// One char of a [`transform_str`](Self::transform_str) run: buffers word keys and
+    /// appends finished words and other chars to `out`.
+    #[cfg(feature = "std")]
+    pub(crate) fn transform_push(&mut self, c: char, out: &mut String) {
+        if let Some(keys) = self.extended_telex_keys(c) {
+            keys.chars().for_each(|k| self.transform_push(k, out));
+            return;
+        }
+        if self.starts_escape(c) {
+            self.raw_locked = true;
+            return;
+        }
+        if self.is_word_key(c) {
+            // Like `push_key`, a full fixed-size buffer leaves the word as typed
+            if !push_char(&mut self.raw_buffer, c) {
+                self.raw_locked = true;
+                self.overflowed(EngineError::RawOverflow);
+            }
+            return;
+        }
+        if !self.raw_buffer.is_empty() {
+            out.push_str(self.render_commit());
+            self.raw_buffer.clear();
+        }
+        self.raw_locked = false;
+        self.track_sentence(c);
+        out.push(c);
+    }
+
+    /// Ends a [`transform_push`](Self::transform_push) run, rendering the trailing word.
+    #[cfg(feature = "std")]
+    pub(crate) fn transform_finish(&mut self, out: &mut String) {
+        if !self.raw_buffer.is_empty() {
+            out.push_str(self.render_commit());
+            self.raw_buffer.clear();
+        }
+        self.raw_locked = false;
+    }
+
+    #[cfg(feature = "std")]
+    #[inline(always)]
+    fn is_word_key(&self, c: char) -> bool {
+        if c.is_ascii_alphanumeric() {
+            return true;
+        }
+        // Mark and modifier keys that double as punctuation (VIQR) only join a word
+        // already in progress; the render decides whether they act as marks.
+        c.is_ascii()
+            && !self.raw_buffer.is_empty()
+            && (self.mode.classify[c as usize] & (IS_TONE_KEY | IS_MODIFIER) != 0
+                || (self.mode.mark_escape != 0 && c as u8 == self.mode.mark_escape))
+    }
+
+    /// Renders the word for a commit and moves the sentence position past it.
+    fn render_commit(&mut self) -> &str {
+        let rendered = self.compose_commit();
+        self.store(&rendered);
+        self.track_word(&rendered);
+        &self.out_buffer
+    }
+
+    /// Moves the sentence position past the word being committed as `rendered`.
+    fn track_word(&mut self, rendered: &Rendered<RAW>) {
+        if !self.config.auto_capitalize || self.raw_buffer.is_empty() {
+            return;
+        }
+        self.last_sentence = self.sentence;
+        let (mut sentence, ellipsis_ends) = (self.sentence, self.config.ellipsis_ends_sentence);
+        let _ = self.write_rendered_as(rendered, &mut |c| {
+            sentence.track(c, ellipsis_ends);
+            Ok(())
+        });
+        self.sentence = sentence;
+        // A cached render may have been capitalized for the old position
+        self.invalidate_render();
+    }
+
+    /// Moves the sentence position past `c`, typed outside a word.
+    #[inline(always)]
+    fn track_sentence(&mut self, c: char) {
+        if self.config.auto_capitalize {
+            self.sentence.track(c, self.config.ellipsis_ends_sentence);
+            self.invalidate_render();
+        }
+    }
+
+    /// Moves the sentence position past Enter or Tab, which the host types after the word.
+    fn track_key(&mut self, key: Key) {
+        match key {
+            Key::Enter => self.track_sentence('\n'),
+            Key::Tab => self.track_sentence('\t'),
+            _ => {}
+        }
+    }
+
+    /// Whether the word starts a sentence and is written with a capital.
+    #[inline(always)]
+    fn capitalized(&self) -> bool {
+        self.config.auto_capitalize && self.sentence.start
+    }
+
+    /// Final render of the word at a delimiter: macros, then the stricter syllable check.
+    fn compose_commit(&mut self) -> Rendered<RAW> {
+        #[cfg(feature = "std")]
+        if let Some(i) = self.macro_index() {
+            self.fallback = false;
+            return Rendered::Macro(i);
+        }
+        let mut rendered = self.compose();
+        if let Rendered::Chars { buf, len, upper } = &rendered
+            // A plain-ASCII render is already literal (e.g. a cancelled tone: `ass` -> `as`)
+            && !buf[..*len].iter().all(char::is_ascii)
+            && !is_d_abbreviation(&buf[..*len], *upper)
+            && !self.accepts(&buf[..*len])
+        {
+            return self.fallback_raw();
+        }
+        if let Rendered::Chars { buf, len, .. } = &mut rendered {
+            respell_i_y(&mut buf[..*len], self.config.y_orthography);
+        }
+        rendered
+    }
+
+    /// Whether a converted word may be committed: a syllable unless that check is off,
+    /// and listed in the dictionary if one is set.
+    fn accepts(&self, word: &[char]) -> bool {
+        let strict = match self.config.validation {
+            ValidationLevel::Off => return true,
+            ValidationLevel::Basic => false,
+            ValidationLevel::Strict => true,
+        };
+        if self.config.restore_english_on_commit && !is_valid_letters(word.iter().copied(), strict) {
+            return false;
+        }
+        #[cfg(feature = "dictionary")]
+        if let Some(words) = self.dictionary {
+            let lower = word.iter().map(|&c| to_lower_vi(c));
+            return words.binary_search_by(|entry| entry.chars().cmp(lower.clone())).is_ok();
+        }
+        true
+    }
+
+    /// Renders the word into `out_buffer`, unless it already holds the render of the
+    /// same keys (`restore_state` or `revert_raw` with nothing new).
+    fn render_str(&mut self) -> &str {
+        if !self.raw_buffer.is_empty() && self.render_locked == self.raw_locked && self.render_key == self.raw_buffer {
+            return &self.out_buffer;
+        }
+        let rendered = self.compose();
+        self.store(&rendered);
+        self.render_key.clone_from(&self.raw_buffer);
+        self.render_locked = self.raw_locked;
+        &self.out_buffer
+    }
 
-        let bytes_all = self.raw_buffer.as_bytes();
-        let bytes = &bytes_all[..bytes_all.len().min(32)];
+    #[inline(always)]
+    fn invalidate_render(&mut self) {
+        self.render_key.clear();
+    }
+
+    /// Drops everything rendered under the previous config or layout.
+    fn config_changed(&mut self) {
+        self.invalidate_render();
+        self.resolved.keys_len = 0;
+    }
+
+    /// Writes `rendered` into `out_buffer`.
+    fn store(&mut self, rendered: &Rendered<RAW>) -> &str {
+        self.invalidate_render();
+        let mut out = core::mem::take(&mut self.out_buffer);
+        out.clear();
+        // A full heapless buffer keeps what fit
+        if self.write_rendered(rendered, &mut out).is_err() {
+            self.overflowed(EngineError::OutOverflow);
+        }
+        self.out_buffer = out;
+        &self.out_buffer
+    }
+
+    fn write_rendered<W: core::fmt::Write>(&self, rendered: &Rendered<RAW>, out: &mut W) -> core::fmt::Result {
+        match (self.config.output_form, rendered) {
+            (OutputForm::Nfd, _) => self.write_rendered_as(rendered, &mut |c| write_decomposed(c, out)),
+            (OutputForm::Nfc, Rendered::Raw) if !self.capitalized() => out.write_str(self.typed()),
+            (OutputForm::Nfc, Rendered::Raw) => self.write_rendered_as(rendered, &mut |c| out.write_char(c)),
+            (OutputForm::Nfc, Rendered::Chars { buf, len, upper }) => {
+                let upper = upper | u32::from(self.capitalized());
+                // Encode the word on the stack and hand it over in one piece
+                let mut bytes = [0u8; 4 * 32];
+                let mut n = 0;
+                for (k, &c) in buf[..*len].iter().enumerate() {
+                    let c = if upper & (1 << k) != 0 { to_upper_vi(c) } else { c };
+                    n += c.encode_utf8(&mut bytes[n..]).len();
+                }
+                let word = core::str::from_utf8(&bytes[..n]).unwrap_or_default();
+                // A full heapless buffer still takes the letters that fit
+                out.write_str(word).or_else(|_| word.chars().try_for_each(|c| out.write_char(c)))
+            }
+            #[cfg(feature = "std")]
+            (OutputForm::Nfc, Rendered::Macro(_)) => self.write_rendered_as(rendered, &mut |c| out.write_char(c)),
+        }
+    }
+
+    #[inline(always)]
+    fn write_rendered_as(&self, rendered: &Rendered<RAW>, write: &mut impl FnMut(char) -> core::fmt::Result) -> core::fmt::Result {
+        match rendered {
+            Rendered::Raw => {
+                let mut chars = self.typed().chars();
+                if self.capitalized()
+                    && let Some(first) = chars.next()
+                {
+                    write(to_upper_vi(first))?;
+                }
+                chars.try_for_each(write)
+            }
+            #[cfg(feature = "std")]
+            Rendered::Macro(i) => {
+                let mut chars = self.config.macros[*i].1.chars();
+                if let Some(first) = chars.next() {
+                    let upper = self.capitalized() || self.raw_buffer.chars().next().is_some_and(|c| c.is_uppercase());
+                    write(if upper { to_upper_vi(first) } else { first })?;
+                }
+                chars.try_for_each(write)
+            }
+            Rendered::Chars { buf, len, upper } => {
+                let upper = upper | u32::from(self.capitalized());
+                for (k, &c) in buf[..*len].iter().enumerate() {
+                    write(if upper & (1 << k) != 0 { to_upper_vi(c) } else { c })?;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Gives up on the current word: it renders as the raw keys verbatim.
+    fn fallback_raw(&mut self) -> Rendered<RAW> {
+        self.fallback = true;
+        Rendered::Raw
+    }
+
+    /// Runs the rendering rules over the raw keys of the current word.
+    fn compose(&mut self) -> Rendered<RAW> {
+        self.fallback = false;
+        if self.raw_buffer.is_empty() {
+            return Rendered::Raw;
+        }
+        if self.raw_locked || self.raw_buffer.len() > Self::WORD_KEYS {
+            return self.fallback_raw();
+        }
+
+        if self.config.input_method == InputMethod::Auto {
+            let method = detect_method(self.raw_buffer.as_bytes());
+            if self.mode.method != method {
+                self.mode = derive_mode(method, &self.config);
+            }
+        }
+
+        // At most WORD_KEYS, which the scratch arrays below have room for
+        let bytes = self.raw_buffer.as_bytes();
+        let validate = self.config.validation != ValidationLevel::Off;
+        if validate && (starts_english(bytes) || self.is_alphanumeric_token(bytes))
+            || (self.config.english_detection && crate::english::looks_english(&self.raw_buffer))
+        {
+            return self.fallback_raw();
+        }
+        if let Some(rendered) = self.extend_resolved() {
+            return rendered;
+        }
+        let bytes = self.raw_buffer.as_bytes();
 
         // Filter tone + Toggling (ddd -> d) in one pass
-        let mut toggled = [0u8; 32];
+        // `toggled` keeps the keys in their original case; every rule below matches on
+        // the lowercased byte so case only matters again when characters are emitted.
+        let mut toggled = [0u8; RAW];
         let mut t_len = 0usize;
         let mut last_tone_char = 0u8;
         let mut tone_cancelled = false;
+        // Key index of the last doubled tone key
+        let mut tone_cancel_at: Option<usize> = None;
+        // Key index of the last doubled modifier cancel, and `tone_cancelled` before it
+        let mut modifier_cancel: Option<(usize, bool)> = None;
         // State for toggling: track consecutive count of the current character
         let mut run_char: u8 = 0;
         let mut run_count: u8 = 0;
@@ -87,49 +1555,114 @@ impl UltraFastViEngine {
         let mut seen_mod: u8 = 0;   // bitmask: bit0=a, bit1=e, bit2=o, bit3=d
         let mut need_mod_bubble = false;
         let mut has_w = false;
+        // Only consulted by modes whose marks double as punctuation (VIQR)
+        let mut mark_ok = false;
+        let mut escaped = false;
+        // Flags a key the resolver must output as typed (keys are ASCII here: any other
+        // char locks the word raw)
+        const LITERAL: u8 = 0x80;
+
+        let strict = self.config.telex_variant == TelexVariant::Strict && self.mode.method == InputMethod::Telex;
+        // Vowel and tone keys as one bit per key, so rules that look across the word test
+        // a mask instead of rescanning the keys
+        let (mut vowel_keys, mut tone_keys) = (0u32, 0u32);
+        for (idx, &k) in bytes.iter().enumerate() {
+            let attr = self.mode.classify[k.to_ascii_lowercase() as usize];
+            vowel_keys |= u32::from(attr & IS_VOWEL != 0) << idx;
+            tone_keys |= u32::from(attr & IS_TONE_KEY != 0) << idx;
+        }
+        let all_keys = u32::MAX >> (32 - bytes.len());
+        let before = |idx: usize| (1u32 << idx) - 1;
+        let after = |idx: usize| all_keys & !before(idx) & !(1 << idx);
+
+        for (idx, &raw) in bytes.iter().enumerate() {
+            let b = raw.to_ascii_lowercase();
+
+            // Escaped key: literal content, the escape key itself is dropped
+            if escaped {
+                escaped = false;
+                mark_ok = false;
+                run_char = b;
+                run_count = 1;
+                toggled[t_len] = raw;
+                t_len += 1;
+                continue;
+            }
+            if self.mode.mark_escape != 0 && b == self.mode.mark_escape {
+                escaped = true;
+                continue;
+            }
 
-        for (idx, &b) in bytes.iter().enumerate() {
             let attr = self.mode.classify[b as usize];
             let is_tone = (attr & IS_TONE_KEY) != 0;
 
             if is_tone {
                 // Rule 1: First character is always treated as consonant/content
-                if idx == 0 {
+                // VIQR: a mark not following a vowel, modifier or mark is punctuation
+                // Strict Telex: a tone before the vowels or among them is content
+                let misplaced = strict && (vowel_keys & before(idx) == 0 || vowel_keys & after(idx) != 0);
+                if idx == 0 || (self.mode.tone_needs_vowel && !mark_ok) || misplaced {
+                    mark_ok = false;
                     run_char = b;
                     run_count = 1;
-                    toggled[t_len] = b;
+                    toggled[t_len] = raw;
                     t_len += 1;
                     continue;
                 }
 
                 // Rule 2: 'r' after 't' is 'tr'
-                // Extended: 'r' after 'p', 'f', 'c', 'b', 'd', 'g', 'k'
+                // Extended: 'r' after 'p', 'f', 'c', 'b', 'd', 'g', 'k', in the onset only
+                // (`khoangr` is `khoảng`)
                 if b == b'r' {
-                    let prev = bytes[idx - 1];
-                    if matches!(prev, b't' | b'p' | b'f' | b'c' | b'b' | b'd' | b'g' | b'k') {
+                    let prev = bytes[idx - 1].to_ascii_lowercase();
+                    if matches!(prev, b't' | b'p' | b'f' | b'c' | b'b' | b'd' | b'g' | b'k')
+                        && vowel_keys & before(idx - 1) == 0
+                    {
                         run_char = b;
                         run_count = 1;
-                        toggled[t_len] = b;
+                        toggled[t_len] = raw;
                         t_len += 1;
                         continue;
                     }
                 }
 
+                // A third press right after a cancel re-applies the tone in place of the
+                // literal, so the cycle goes á, as, á, as
+                if let Some(at) = tone_cancel_at.take()
+                    && at + 1 == idx
+                    && toggled[t_len - 1] == raw
+                {
+                    t_len -= 1;
+                    last_tone_char = b;
+                    tone_cancelled = false;
+                    continue;
+                }
+                // The remove-tone key (Telex z, VNI 0) only acts on a word with a tone or a
+                // modified letter; elsewhere it is content (`size`, `pizza`)
+                if self.mode.tone[b as usize] == 0 && last_tone_char == 0 && !self.has_marks(&toggled[..t_len]) {
+                    run_char = b;
+                    run_count = 1;
+                    toggled[t_len] = raw;
+                    t_len += 1;
+                    continue;
+                }
+
                 // Double tone key cancellation: ss, ff, rr, xx, jj -> undo tone, put key back as literal
                 if b == last_tone_char {
                     // Cancel the tone and re-insert the key as a literal
-                    if t_len < 32 {
-                        toggled[t_len] = b;
+                    if t_len < Self::WORD_KEYS {
+                        toggled[t_len] = raw;
                         t_len += 1;
                     }
                     last_tone_char = 0;
                     tone_cancelled = true;
+                    tone_cancel_at = Some(idx);
                 } else {
                     // If tone was previously cancelled and we see a new tone key,
                     // don't re-apply tone (the user already cancelled)
                     if tone_cancelled {
-                        if t_len < 32 {
-                            toggled[t_len] = b;
+                        if t_len < Self::WORD_KEYS {
+                            toggled[t_len] = raw;
                             t_len += 1;
                         }
                     } else {
@@ -137,9 +1670,52 @@ impl UltraFastViEngine {
                     }
                 }
             } else {
+                // Modifier keys that are not letters (VNI digits): typed twice the modifier is
+                // undone and the key kept as typed (o66 -> o6), a third time re-applies it like
+                // Telex ddd/dddd (o666 -> ô); after any cancel they are literal
+                if attr & IS_MODIFIER != 0 && !b.is_ascii_alphabetic() {
+                    let resolver = self.mode.resolver;
+                    if let Some((at, was_cancelled)) = modifier_cancel.take()
+                        && at + 1 == idx
+                        && toggled[t_len - 1] == raw | LITERAL
+                    {
+                        toggled[t_len - 1] = raw;
+                        tone_cancelled = was_cancelled;
+                        continue;
+                    }
+                    if t_len > 0
+                        && toggled[t_len - 1] == raw
+                        && toggled[..t_len - 1].iter().any(|&k| resolver(k.to_ascii_lowercase(), Some(b)).1)
+                    {
+                        toggled[t_len - 1] |= LITERAL;
+                        modifier_cancel = Some((idx, tone_cancelled));
+                        tone_cancelled = true;
+                        continue;
+                    }
+                    if tone_cancelled {
+                        toggled[t_len] = raw | LITERAL;
+                        t_len += 1;
+                        continue;
+                    }
+                }
+                mark_ok = attr & (IS_VOWEL | IS_MODIFIER) != 0;
                 // Fused toggling: detect triple-repeat (aaa->a, ddd->d, etc.)
                 if b == run_char {
                     run_count += 1;
+                    // A third `o` before the coda keeps a literal `oo` (xooong -> xoong)
+                    if run_count == 3
+                        && b == b'o'
+                        && t_len >= 2
+                        && toggled[t_len - 2..t_len].iter().all(|k| k.eq_ignore_ascii_case(&b'o'))
+                        && after(idx) & !tone_keys != 0
+                    {
+                        for k in &mut toggled[t_len - 2..t_len] {
+                            *k |= LITERAL;
+                        }
+                        run_char = 0;
+                        run_count = 0;
+                        continue;
+                    }
                     if run_count == 3 && matches!(b, b'a' | b'e' | b'o' | b'd') {
                         t_len -= 1;
                         run_count = 1;
@@ -158,7 +1734,7 @@ impl UltraFastViEngine {
                     b'w' => { has_w = true; }
                     _ => {}
                 }
-                toggled[t_len] = b;
+                toggled[t_len] = raw;
                 t_len += 1;
             }
         }
@@ -167,10 +1743,11 @@ impl UltraFastViEngine {
         // Handles: free-style modifier bubbling (aa/ee/oo/dd), double-w cancellation, w-bubbling
         // Flags need_mod_bubble / has_w were computed in the first pass above (zero extra scan)
         const W_LITERAL: u8 = 0x01;
+        const W_LITERAL_UPPER: u8 = 0x02;
         let need_w_pass = has_w && self.mode.enable_w_bubbling;
         {
-            if need_mod_bubble || need_w_pass {
-                let mut buf = [0u8; 32];
+            if need_mod_bubble && !strict || need_w_pass {
+                let mut buf = [0u8; RAW];
                 let mut b_len = 0usize;
 
                 // Phase 1: modifier bubbling + double-w collapse in one scan
@@ -178,11 +1755,12 @@ impl UltraFastViEngine {
                 let mut wi = 0usize;
                 while wi < t_len {
                     let c = toggled[wi];
+                    let lc = c.to_ascii_lowercase();
 
                     // Double-w cancellation
-                    if c == b'w' && self.mode.enable_w_bubbling {
-                        if wi + 1 < t_len && toggled[wi + 1] == b'w' {
-                            buf[b_len] = W_LITERAL;
+                    if lc == b'w' && self.mode.enable_w_bubbling {
+                        if wi + 1 < t_len && toggled[wi + 1].eq_ignore_ascii_case(&b'w') {
+                            buf[b_len] = if c == b'W' { W_LITERAL_UPPER } else { W_LITERAL };
                             b_len += 1;
                             wi += 2;
                             continue;
@@ -195,7 +1773,7 @@ impl UltraFastViEngine {
                     }
 
                     // Modifier bubbling for a,e,o,d
-                    let slot = match c {
+                    let slot = match lc {
                         b'a' => Some(0),
                         b'e' => Some(1),
                         b'o' => Some(2),
@@ -203,7 +1781,7 @@ impl UltraFastViEngine {
                         _ => None,
                     };
 
-                    if let Some(s) = slot {
+                    if let Some(s) = slot.filter(|_| !strict) {
                         if last_pos[s] != 0xFF {
                             // Bubble: insert next to first occurrence
                             let insert_at = last_pos[s] as usize + 1;
@@ -230,27 +1808,26 @@ impl UltraFastViEngine {
                 }
 
                 // Phase 2: w-bubbling in-place on buf (only if needed)
-                if need_w_pass {
-                    let mut out = [0u8; 32];
+                if need_w_pass && !strict {
+                    let mut out = [0u8; RAW];
                     let mut o_len = 0usize;
                     let mut last_target_pos: Option<usize> = None;
 
-                    for k in 0..b_len {
-                        let c = buf[k];
-                        if c == b'w' {
+                    for &c in &buf[..b_len] {
+                        if c.eq_ignore_ascii_case(&b'w') {
                             if let Some(tp) = last_target_pos {
                                 let insert_at = tp + 1;
                                 out.copy_within(insert_at..o_len, insert_at + 1);
-                                out[insert_at] = b'w';
+                                out[insert_at] = c;
                                 o_len += 1;
                             } else {
-                                out[o_len] = b'w';
+                                out[o_len] = c;
                                 o_len += 1;
                             }
                         } else {
                             out[o_len] = c;
                             o_len += 1;
-                            if self.mode.w_target[c as usize] {
+                            if self.mode.w_target[c.to_ascii_lowercase() as usize] {
                                 last_target_pos = Some(o_len - 1);
                             }
                         }
@@ -264,55 +1841,95 @@ impl UltraFastViEngine {
             }
         }
 
+        // Modifier keys that are not letters (VNI digits) move back next to the nearest
+        // earlier letter they modify that is not modified yet: truong7 -> truo7ng
+        if self.mode.enable_modifier_bubbling {
+            let resolver = self.mode.resolver;
+            let modifies = |base: u8, key: u8| resolver(base.to_ascii_lowercase(), Some(key.to_ascii_lowercase())).1;
+            for j in 1..t_len {
+                let k = toggled[j];
+                if self.mode.classify[k.to_ascii_lowercase() as usize] & IS_MODIFIER == 0 || modifies(toggled[j - 1], k) {
+                    continue;
+                }
+                if let Some(p) = (0..j - 1)
+                    .rev()
+                    .find(|&p| modifies(toggled[p], k) && !modifies(toggled[p], toggled[p + 1]))
+                {
+                    toggled.copy_within(p + 1..j, p + 2);
+                    toggled[p + 1] = k;
+                }
+            }
+        }
+
         // Resolve mode rules & Build Char Buffer
-        let mut char_buf = ['\0'; 32];
+        let mut char_buf = ['\0'; RAW];
         let mut c_len = 0usize;
-        let mut vowel_mask = 0u16;
+        // One bit per char_buf slot; c_len never exceeds WORD_KEYS <= 32 so every position is representable.
+        let mut vowel_mask = 0u32;
+        // Slots whose base key was typed uppercase; char_buf itself stays lowercase.
+        let mut upper_mask = 0u32;
 
         let mut i = 0usize;
         while i < t_len {
-            let curr = toggled[i];
+            let raw = toggled[i];
 
             // W_LITERAL sentinel: output literal 'w', skip resolver
-            if curr == W_LITERAL {
+            if raw == W_LITERAL || raw == W_LITERAL_UPPER {
+                if raw == W_LITERAL_UPPER {
+                    upper_mask |= 1 << c_len;
+                }
                 char_buf[c_len] = 'w';
                 c_len += 1;
                 i += 1;
                 continue;
             }
+            if raw & LITERAL != 0 {
+                let key = raw & !LITERAL;
+                if key.is_ascii_uppercase() {
+                    upper_mask |= 1 << c_len;
+                }
+                let c = key.to_ascii_lowercase() as char;
+                if is_vowel_unicode(c) {
+                    vowel_mask |= 1 << c_len;
+                }
+                char_buf[c_len] = c;
+                c_len += 1;
+                i += 1;
+                continue;
+            }
 
+            let curr = raw.to_ascii_lowercase();
             let next = if i + 1 < t_len {
-                Some(toggled[i + 1])
+                Some(toggled[i + 1].to_ascii_lowercase())
             } else {
                 None
             };
 
             let (mut c, consumed) = (self.mode.resolver)(curr, next);
+            // Without the shorthand a `w` that modifies nothing is not Vietnamese (`wow`)
+            if curr == b'w' && !consumed && !self.config.w_shorthand {
+                return self.fallback_raw();
+            }
 
-            // uow -> ươ
-            if curr == b'u' && !consumed {
-                if let Some(n) = next {
-                    if n == b'o' {
-                        if i + 2 < t_len && toggled[i + 2] == b'w' {
-                            let is_qu = if i > 0 {
-                                let prev = toggled[i - 1];
-                                prev == b'q' || prev == b'Q'
-                            } else {
-                                false
-                            };
-
-                            if !is_qu {
-                                c = 'ư';
-                            }
-                        }
-                    }
+            // uow -> ươ, except in quở and a word-final thuở
+            if curr == b'u'
+                && !consumed
+                && next == Some(b'o')
+                && i + 2 < t_len
+                && (self.mode.resolver)(b'o', Some(toggled[i + 2].to_ascii_lowercase())) == ('ơ', true)
+            {
+                let is_qu = i > 0 && toggled[i - 1].eq_ignore_ascii_case(&b'q');
+                let is_thuo = i == 2 && i + 3 == t_len && toggled[..2].eq_ignore_ascii_case(b"th");
+                if !is_qu && !is_thuo {
+                    c = 'ư';
                 }
             }
 
             if is_vowel_unicode(c) {
-                if c_len < 16 {
-                    vowel_mask |= 1 << c_len;
-                }
+                vowel_mask |= 1 << c_len;
+            }
+            if raw.is_ascii_uppercase() {
+                upper_mask |= 1 << c_len;
             }
 
             char_buf[c_len] = c;
@@ -321,57 +1938,188 @@ impl UltraFastViEngine {
             i += if consumed { 2 } else { 1 };
         }
 
+        // A horn typed after uou goes on the ươ cluster (ruouwj -> rượu)
+        if let Some(p) = char_buf[..c_len].windows(3).position(|w| w == ['u', 'o', 'ư']) {
+            char_buf[p..p + 3].copy_from_slice(&['ư', 'ơ', 'u']);
+        }
+
+        let bytes = self.raw_buffer.as_bytes();
+        let resolved = &mut self.resolved;
+        resolved.keys[..bytes.len()].copy_from_slice(bytes);
+        resolved.keys_len = bytes.len();
+        resolved.chars[..c_len].copy_from_slice(&char_buf[..c_len]);
+        resolved.len = c_len;
+        resolved.vowel_mask = vowel_mask;
+        resolved.upper_mask = upper_mask;
+        resolved.last_tone_char = last_tone_char;
+        resolved.tone_cancelled = tone_cancelled;
+        resolved.last_key = if t_len > 0 { toggled[t_len - 1] } else { 0 };
+        self.finish_render(char_buf, c_len, vowel_mask, upper_mask, last_tone_char, tone_cancelled)
+    }
+
+    /// Renders the word from its last render's letters when the one key typed since is
+    /// a consonant that no rule treats specially, so long words are not composed anew
+    /// on every key. `None` when the word must be composed in full.
+    fn extend_resolved(&mut self) -> Option<Rendered<RAW>> {
+        let keys = self.raw_buffer.as_bytes();
+        let prev = &self.resolved;
+        let n = prev.keys_len;
+        if n == 0 || n + 1 != keys.len() || keys[..n] != prev.keys[..n] || self.config.input_method == InputMethod::Auto {
+            return None;
+        }
+        // A key after these changes earlier letters: `uơ` (`huow`, then `n` is `hươn`) or
+        // a third `o` (`xooo`, then `n` is `xoon`)
+        if prev.chars[..prev.len].windows(2).any(|w| w == ['u', 'ơ'])
+            || keys.iter().filter(|k| k.eq_ignore_ascii_case(&b'o')).count() >= 3
+        {
+            return None;
+        }
+        let raw = keys[n];
+        let key = raw.to_ascii_lowercase();
+        // a e o d w combine with earlier letters in any layout (`dod`, `tuw`)
+        if !key.is_ascii_alphabetic()
+            || matches!(key, b'a' | b'e' | b'o' | b'd' | b'w')
+            || self.mode.classify[key as usize] & (IS_TONE_KEY | IS_MODIFIER | IS_VOWEL) != 0
+            || prev.last_key != 0 && (self.mode.resolver)(prev.last_key.to_ascii_lowercase(), Some(key)).1
+        {
+            return None;
+        }
+        let (c, false) = (self.mode.resolver)(key, None) else {
+            return None;
+        };
+
+        let next = &mut self.resolved;
+        next.keys[n] = raw;
+        next.keys_len += 1;
+        next.chars[next.len] = c;
+        if is_vowel_unicode(c) {
+            next.vowel_mask |= 1 << next.len;
+        }
+        if raw.is_ascii_uppercase() {
+            next.upper_mask |= 1 << next.len;
+        }
+        next.len += 1;
+        next.last_key = raw;
+        let Resolved { chars, len, vowel_mask, upper_mask, last_tone_char, tone_cancelled, .. } = *next;
+        Some(self.finish_render(chars, len, vowel_mask, upper_mask, last_tone_char, tone_cancelled))
+    }
+
+    /// Checks the resolved letters of a word and places its tone.
+    #[inline(always)]
+    fn finish_render(
+        &mut self,
+        mut char_buf: [char; RAW],
+        c_len: usize,
+        vowel_mask: u32,
+        upper_mask: u32,
+        last_tone_char: u8,
+        tone_cancelled: bool,
+    ) -> Rendered<RAW> {
+        let validate = self.config.validation != ValidationLevel::Off;
         // If no vowels in the resolved output and tone keys were stripped, fall back to raw
         // This handles cases like "txt", "sx" where tone keys have no vowel to act on
         // Exception: if a modifier was applied (e.g. dd -> đ), keep the resolved output
-        if vowel_mask == 0 && last_tone_char != 0 && !tone_cancelled {
+        if validate && vowel_mask == 0 && last_tone_char != 0 && !tone_cancelled {
             let has_modified = char_buf[..c_len].iter().any(|&c| !c.is_ascii());
             if !has_modified {
-                self.out_buffer.clear();
-                let _ = self.out_buffer.push_str(&self.raw_buffer);
-                return &self.out_buffer;
+                return self.fallback_raw();
             }
         }
 
         // Validation
-        if self.is_invalid_vietnamese_chars(&char_buf[..c_len], vowel_mask) {
-            self.out_buffer.clear();
-            let _ = self.out_buffer.push_str(&self.raw_buffer);
-            return &self.out_buffer;
+        if validate
+            && !is_d_abbreviation(&char_buf[..c_len], upper_mask)
+            && self.is_invalid_vietnamese_chars(&char_buf[..c_len], vowel_mask)
+        {
+            return self.fallback_raw();
         }
 
         // Tone Placement
-        if last_tone_char > 0 {
+        if last_tone_char > 0 && vowel_mask != 0 {
+            // Only c, ch, m, n, ng, nh, p, t may follow the last vowel (`havs`, `modj` are English)
+            let last_vowel = 31 - vowel_mask.leading_zeros() as usize;
+            let coda = char_buf[last_vowel + 1..c_len].iter().copied().filter(|c| c.is_alphabetic());
+            if validate && !is_valid_coda(coda.clone()) {
+                return self.fallback_raw();
+            }
             let tone_id = self.mode.tone[last_tone_char as usize];
-            self.apply_tone_in_place(&mut char_buf[..c_len], vowel_mask, tone_id);
+            // Stop finals only take sắc and nặng
+            let stop = matches!(coda.clone().next(), Some('c' | 'p' | 't'));
+            if validate && stop && matches!(tone_id, 2..=4) {
+                match self.config.stop_final_tone {
+                    StopFinalTone::Raw => return self.fallback_raw(),
+                    StopFinalTone::Ignore => {}
+                }
+            } else {
+                self.apply_tone_in_place(&mut char_buf[..c_len], vowel_mask, tone_id);
+            }
         }
 
-        self.out_buffer.clear();
-        for &c in &char_buf[..c_len] {
-            let _ = self.out_buffer.push(c);
+        Rendered::Chars {
+            buf: char_buf,
+            len: c_len,
+            upper: upper_mask,
         }
+    }
 
-        &self.out_buffer
+    /// Whether `keys` make a modified letter: an adjacent pair like `aa` or `o7`, or a
+    /// modifier key that is not a plain letter (Telex `w`, VNI digits).
+    fn has_marks(&self, keys: &[u8]) -> bool {
+        let resolver = self.mode.resolver;
+        keys.windows(2)
+            .any(|w| resolver(w[0].to_ascii_lowercase(), Some(w[1].to_ascii_lowercase())).1)
+            || keys.iter().any(|&k| {
+                let k = k.to_ascii_lowercase();
+                self.mode.classify[k as usize] & IS_MODIFIER != 0
+                    && (!k.is_ascii_alphabetic() || resolver(k, None).0 != k as char)
+            })
     }
 
-    fn is_invalid_vietnamese_chars(&self, chars: &[char], vowel_mask: u16) -> bool {
+    /// Whether digit keys (VNI) are part of a token rather than marks: `file1`, `utf8`,
+    /// `x264`. The letters must be shaped like a syllable and no digit may follow a
+    /// consonant before the first vowel, except a modifier of that letter (`d9uong`).
+    fn is_alphanumeric_token(&self, keys: &[u8]) -> bool {
+        let is_digit_key =
+            |k: u8| k.is_ascii_digit() && self.mode.classify[k as usize] & (IS_TONE_KEY | IS_MODIFIER) != 0;
+        if !keys.iter().any(|&k| is_digit_key(k)) {
+            return false;
+        }
+        let mut has_vowel = false;
+        for (i, &k) in keys.iter().enumerate() {
+            if self.mode.classify[k.to_ascii_lowercase() as usize] & IS_VOWEL != 0 {
+                has_vowel = true;
+                break;
+            }
+            if is_digit_key(k)
+                && (i == 0
+                    || keys[i - 1].is_ascii_alphabetic()
+                        && !(self.mode.resolver)(keys[i - 1].to_ascii_lowercase(), Some(k)).1)
+            {
+                return true;
+            }
+        }
+        // Without a vowel there is no syllable to check yet (`d9`)
+        if !has_vowel {
+            return false;
+        }
+        let letters = keys.iter().filter(|k| k.is_ascii_alphabetic()).map(|&k| k.to_ascii_lowercase() as char);
+        !is_valid_letters(letters, false)
+    }
+
+    fn is_invalid_vietnamese_chars(&self, chars: &[char], vowel_mask: u32) -> bool {
         if vowel_mask == 0 {
-            return chars.len() > 1;
+            // Literal digits left by a cancel don't count (VNI d99 -> d9)
+            return chars.iter().filter(|c| c.is_alphabetic()).count() > 1;
         }
 
         let mut mask_o: u32 = 0;
         let mut mask_u: u32 = 0;
-        let mut idx: u32 = 0;
-        for &c in chars.iter() {
-            if idx >= 32 {
-                break;
-            }
+        for (idx, &c) in chars.iter().take(32).enumerate() {
             if c == 'o' {
                 mask_o |= 1u32 << idx;
             } else if c == 'u' {
                 mask_u |= 1u32 << idx;
             }
-            idx += 1;
         }
 
         if (mask_o & (mask_u >> 1)) != 0 {
@@ -381,10 +2129,8 @@ impl UltraFastViEngine {
         let first_vowel_pos = vowel_mask.trailing_zeros() as usize;
 
         if first_vowel_pos >= 3 {
-            if first_vowel_pos == 3 {
-                if chars.len() >= 3 && chars[0] == 'n' && chars[1] == 'g' && chars[2] == 'h' {
-                    return false;
-                }
+            if first_vowel_pos == 3 && chars.len() >= 3 && chars[0] == 'n' && chars[1] == 'g' && chars[2] == 'h' {
+                return false;
             }
             return true;
         }
@@ -404,72 +2150,19 @@ impl UltraFastViEngine {
         false
     }
 
-    fn apply_tone_in_place(&self, chars: &mut [char], mask: u16, tone: u8) {
-        let count = mask.count_ones();
-        if count == 0 {
+    fn apply_tone_in_place(&self, chars: &mut [char], mask: u32, tone: u8) {
+        let Some(target_pos) = tone_position(chars, mask, self.config.tone_style) else {
             return;
-        }
-
-        let target_pos = match count {
-            1 => mask.trailing_zeros() as usize,
-            2 => {
-                let first = mask.trailing_zeros() as usize;
-                let second = (mask & !(1 << first)).trailing_zeros() as usize;
-
-                let f = chars.get(first).copied().unwrap_or('\0');
-                let sc = chars.get(second).copied().unwrap_or('\0');
-
-                // Special case: ui/ưi (e.g. "túi", "gửi") place tone on the first vowel.
-                // Exception: in "qu" prefix, 'u' is a glide, so tone belongs to the following vowel.
-                let mut prefer_first = (f == 'u' || f == 'ư') && sc == 'i';
-
-                // Modified/circumflex vowels paired with a plain vowel: tone on the modified vowel.
-                // e.g. ơi(mới), ôi(tối), êu(nếu), âu(đầu), ây(đấy), âo(cháo/nấo)
-                // Exception: ươ pair — tone goes on ơ (second), not ư.
-                let f_is_modified = matches!(f, 'ơ' | 'ô' | 'ê' | 'â' | 'ă');
-                let sc_is_plain = matches!(sc, 'a' | 'e' | 'i' | 'o' | 'u' | 'y');
-                if f_is_modified && sc_is_plain {
-                    prefer_first = true;
-                }
-
-                // Standard open pairs that often prefer tone on the first vowel.
-                let mut is_open_pair = (f == 'i' && (sc == 'a' || sc == 'u'))
-                    || (f == 'u' && (sc == 'a' || sc == 'e'))
-                    || (f == 'ư' && (sc == 'a' || sc == 'u'))
-                    || (f == 'a'
-                        && (sc == 'o' || sc == 'e' || sc == 'i' || sc == 'u' || sc == 'y'))
-                    || (f == 'e' && (sc == 'o' || sc == 'u'))
-                    || (f == 'o' && sc == 'i')
-                    || (f == 'â' && (sc == 'y' || sc == 'u'));
-
-                // Exception: "qu" and "gi" logic
-                if chars.len() >= 2 {
-                    let p0 = chars[0];
-                    let p1 = chars[1];
-
-                    if (p0 == 'q' || p0 == 'Q') && (p1 == 'u' || p1 == 'U') && first == 1 {
-                        is_open_pair = false;
-                        prefer_first = false;
-                    } else if (p0 == 'g' || p0 == 'G') && (p1 == 'i' || p1 == 'I') && first == 1 {
-                        is_open_pair = false;
-                        prefer_first = false;
-                    }
-                }
-
-                if prefer_first {
-                    first
-                } else if is_open_pair {
-                    let has_coda = (second + 1) < chars.len();
-                    if has_coda { second } else { first }
-                } else {
-                    second
-                }
-            }
-            _ => (mask & !(1 << mask.trailing_zeros())).trailing_zeros() as usize,
         };
-
         if let Some(target) = chars.get_mut(target_pos) {
             *target = map_vowel_with_tone(*target, tone);
         }
     }
 }
+
+/// Built-in layout for `method`, with w-bubbling turned off if `config` asks.
+fn derive_mode(method: InputMethod, config: &EngineConfig) -> Mode {
+    let mut mode = mode_for(method).clone();
+    mode.enable_w_bubbling &= config.w_bubbling;
+    mode
+}
@@ -0,0 +1,47 @@
+//! Guess at whether the keys of a word spell English rather than Vietnamese, for the
+//! engine's opt-in English detection.
+
+/// Whether `keys`, as typed, look like an English word: `f`, `j` or `z` starting it, an
+/// initial consonant pair Vietnamese has no use for (`print`, but not `tr` or `ngh`), a
+/// doubled consonant other than `dd` (`hello`), or a vowel pair Vietnamese never spells
+/// (`ea`, `ae`, `ei`, `ou`).
+///
+/// Case is ignored; keys other than ASCII letters split the word.
+///
+/// ```
+/// use uvie::english::looks_english;
+/// assert!(looks_english("search"));
+/// assert!(!looks_english("vieejt"));
+/// ```
+pub fn looks_english(keys: &str) -> bool {
+    let keys = keys.as_bytes();
+    let is_vowel = |k: u8| matches!(k, b'a' | b'e' | b'i' | b'o' | b'u' | b'y');
+    let key = |i: usize| keys.get(i).map(u8::to_ascii_lowercase).filter(u8::is_ascii_lowercase);
+
+    for i in 0..keys.len() {
+        let Some(k) = key(i) else {
+            continue;
+        };
+        let prev = i.checked_sub(1).and_then(key);
+        let next = key(i + 1);
+        if prev.is_none() && matches!(k, b'f' | b'j' | b'z') {
+            return true;
+        }
+        if prev.is_none()
+            && let Some(next) = next.filter(|&n| !is_vowel(k) && !is_vowel(n) && k != b'w' && n != b'w')
+            && !matches!(
+                (k, next),
+                (b'c' | b'k' | b'n' | b'p' | b't' | b'g', b'h') | (b'n', b'g') | (b't', b'r') | (b'd', b'd')
+            )
+        {
+            return true;
+        }
+        if next == Some(k) && !is_vowel(k) && !matches!(k, b'd' | b'w') {
+            return true;
+        }
+        if matches!((k, next), (b'e', Some(b'a')) | (b'a', Some(b'e')) | (b'e', Some(b'i')) | (b'o', Some(b'u'))) {
+            return true;
+        }
+    }
+    false
+}
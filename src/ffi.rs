@@ -0,0 +1,127 @@
+//! C ABI for embedding the engine in C/C++ IMEs (`ffi` feature); see `include/uvie.h`.
+//!
+//! Every function catches panics so none unwinds into C.
+
+use core::ffi::{c_char, c_int};
+use std::panic::{AssertUnwindSafe, catch_unwind};
+
+use crate::{InputMethod, UltraFastViEngine};
+
+pub const UVIE_METHOD_TELEX: c_int = 0;
+pub const UVIE_METHOD_VNI: c_int = 1;
+pub const UVIE_METHOD_VIQR: c_int = 2;
+pub const UVIE_METHOD_AUTO: c_int = 3;
+
+/// Returns a new Telex engine, or null if it could not be created.
+#[unsafe(no_mangle)]
+pub extern "C" fn uvie_engine_new() -> *mut UltraFastViEngine {
+    catch_unwind(|| Box::into_raw(Box::new(UltraFastViEngine::new()))).unwrap_or(core::ptr::null_mut())
+}
+
+/// # Safety
+/// `engine` must come from [`uvie_engine_new`] and not be used afterwards. Null is a no-op.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn uvie_engine_free(engine: *mut UltraFastViEngine) {
+    if engine.is_null() {
+        return;
+    }
+    // SAFETY: the caller hands back ownership of a pointer from `uvie_engine_new`.
+    let engine = unsafe { Box::from_raw(engine) };
+    let _ = catch_unwind(AssertUnwindSafe(move || drop(engine)));
+}
+
+/// Selects one of the `UVIE_METHOD_*` input methods. Returns 0, or -1 for an unknown
+/// method or a null engine.
+///
+/// # Safety
+/// `engine` must be null or a live pointer from [`uvie_engine_new`].
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn uvie_engine_set_method(engine: *mut UltraFastViEngine, method: c_int) -> c_int {
+    // SAFETY: guaranteed by the caller.
+    let Some(engine) = (unsafe { engine.as_mut() }) else {
+        return -1;
+    };
+    let method = match method {
+        UVIE_METHOD_TELEX => InputMethod::Telex,
+        UVIE_METHOD_VNI => InputMethod::Vni,
+        UVIE_METHOD_VIQR => InputMethod::Viqr,
+        UVIE_METHOD_AUTO => InputMethod::Auto,
+        _ => return -1,
+    };
+    catch_unwind(AssertUnwindSafe(|| {
+        engine.set_input_method(method);
+    }))
+    .map_or(-1, |_| 0)
+}
+
+/// Feeds one Unicode scalar value and writes the render, NUL-terminated UTF-8, to `out`.
+///
+/// Returns the render's length in bytes without the NUL. When that is `>= out_cap`
+/// nothing but an empty string is written; the key is still consumed, so fetch the
+/// render with [`uvie_engine_rendered`] and a buffer of at least the returned size + 1.
+/// Invalid code points are ignored.
+///
+/// # Safety
+/// `engine` must be null or a live pointer from [`uvie_engine_new`]; `out` must be null
+/// or valid for `out_cap` bytes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn uvie_engine_feed(
+    engine: *mut UltraFastViEngine,
+    codepoint: u32,
+    out: *mut c_char,
+    out_cap: usize,
+) -> usize {
+    // SAFETY: guaranteed by the caller.
+    let Some(engine) = (unsafe { engine.as_mut() }) else {
+        return 0;
+    };
+    if let Some(key) = char::from_u32(codepoint)
+        && catch_unwind(AssertUnwindSafe(|| {
+            engine.feed(key);
+        }))
+        .is_err()
+    {
+        return 0;
+    }
+    // SAFETY: guaranteed by the caller.
+    unsafe { write_c_str(engine.rendered(), out, out_cap) }
+}
+
+/// Writes the last render without feeding anything; same contract as [`uvie_engine_feed`].
+///
+/// # Safety
+/// Same as [`uvie_engine_feed`].
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn uvie_engine_rendered(engine: *const UltraFastViEngine, out: *mut c_char, out_cap: usize) -> usize {
+    // SAFETY: guaranteed by the caller.
+    match unsafe { engine.as_ref() } {
+        // SAFETY: guaranteed by the caller.
+        Some(engine) => unsafe { write_c_str(engine.rendered(), out, out_cap) },
+        None => 0,
+    }
+}
+
+/// # Safety
+/// `engine` must be null or a live pointer from [`uvie_engine_new`].
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn uvie_engine_clear(engine: *mut UltraFastViEngine) {
+    // SAFETY: guaranteed by the caller.
+    if let Some(engine) = unsafe { engine.as_mut() } {
+        let _ = catch_unwind(AssertUnwindSafe(|| engine.clear()));
+    }
+}
+
+/// # Safety
+/// `out` must be null or valid for `out_cap` bytes.
+unsafe fn write_c_str(s: &str, out: *mut c_char, out_cap: usize) -> usize {
+    if out.is_null() || out_cap == 0 {
+        return s.len();
+    }
+    let n = if s.len() < out_cap { s.len() } else { 0 };
+    // SAFETY: `n + 1 <= out_cap` bytes are written, within the caller's buffer.
+    unsafe {
+        core::ptr::copy_nonoverlapping(s.as_ptr(), out.cast::<u8>(), n);
+        *out.add(n) = 0;
+    }
+    s.len()
+}
@@ -0,0 +1,36 @@
+//! Diacritic removal for slugs, search keys and file names.
+
+use core::fmt;
+
+use crate::tone::base_vowel;
+
+/// `text` with every Vietnamese vowel folded to its plain Latin base and `đ`/`Đ` to
+/// `d`/`D`; other chars are kept.
+///
+/// ```
+/// assert_eq!(uvie::fold::remove_diacritics("Đường Nguyễn Trãi"), "Duong Nguyen Trai");
+/// ```
+#[cfg(feature = "alloc")]
+pub fn remove_diacritics(text: &str) -> alloc::string::String {
+    let mut out = alloc::string::String::with_capacity(text.len());
+    // Writing to a String cannot fail
+    let _ = remove_diacritics_into(text, &mut out);
+    out
+}
+
+/// Like [`remove_diacritics`], writing to `out` instead of allocating.
+pub fn remove_diacritics_into<W: fmt::Write>(text: &str, out: &mut W) -> fmt::Result {
+    for c in text.chars() {
+        out.write_char(fold_char(c))?;
+    }
+    Ok(())
+}
+
+fn fold_char(c: char) -> char {
+    match c {
+        c if c.is_ascii() => c,
+        'đ' => 'd',
+        'Đ' => 'D',
+        c => base_vowel(c),
+    }
+}
@@ -0,0 +1,55 @@
+use crate::modes::{InputMethod, mode_for};
+
+/// Returns the key sequences that make a fresh engine render `c` in `method`.
+///
+/// The first entry is the canonical sequence (base letter, modifier key, tone key);
+/// the rest are accepted alternates such as typing the tone before the modifier.
+/// Returns an empty list for characters the method cannot produce.
+pub fn key_sequences_for(c: char, method: InputMethod) -> Vec<String> {
+    // Auto reads a word without digits as Telex
+    let method = if method == InputMethod::Auto { InputMethod::Telex } else { method };
+    if c.is_ascii_digit() {
+        return vec![c.into()];
+    }
+    let mode = mode_for(method);
+    let Some((base, modifier, tone)) = mode.letter_keys(c) else {
+        return Vec::new();
+    };
+    let upper = c.is_uppercase();
+    let base = char::from(base);
+    let modifier_keys = modifier.map(|k| char::from(k.to_ascii_lowercase()));
+    let tone_key = if tone == 0 { None } else { mode.tone_key(tone).map(char::from) };
+
+    let mut out = Vec::with_capacity(3);
+
+    // Telex needs `ww` for a literal w, a single w renders ư
+    if method == InputMethod::Telex && c.eq_ignore_ascii_case(&'w') {
+        out.push(core::iter::repeat_n(base, 2).collect());
+        return out;
+    }
+
+    let mut canonical = String::new();
+    canonical.push(base);
+    canonical.extend(modifier_keys);
+    canonical.extend(tone_key);
+    out.push(canonical);
+
+    if let (Some(tone_key), Some(modifier_key)) = (tone_key, modifier_keys) {
+        // Tone keys are order independent: they may come before the modifier
+        let mut tone_first = String::new();
+        tone_first.push(base);
+        tone_first.push(tone_key);
+        tone_first.push(modifier_key);
+        out.push(tone_first);
+    }
+
+    // Telex shorthand: a bare w renders ư
+    if method == InputMethod::Telex && base.eq_ignore_ascii_case(&'u') && modifier.is_some() {
+        let mut short = String::new();
+        short.push(if upper { 'W' } else { 'w' });
+        short.extend(tone_key);
+        out.push(short);
+    }
+
+    out
+}
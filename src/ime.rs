@@ -0,0 +1,109 @@
+//! Preedit/commit policy for IME frameworks (ibus, fcitx): a [`Session`] turns key
+//! events into the actions such a framework performs, while the engine stays a pure
+//! converter.
+
+use alloc::borrow::ToOwned;
+use alloc::string::String;
+
+use crate::{FeedResult, InputMethod, Key, UltraFastViEngine};
+
+/// What the IME framework should do with a key event.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ImeAction<'a> {
+    /// Show this as the preedit text; `""` hides it.
+    UpdatePreedit(&'a str),
+    /// Drop the preedit and insert this text.
+    Commit(String),
+    /// Drop the preedit, insert this text, then pass the key on to the application.
+    CommitAndForward(String, Key),
+    /// The key was not consumed: pass it on to the application.
+    Forward(Key),
+}
+
+/// One input context (a focused text field) of an IME.
+///
+/// - Word keys update the preedit.
+/// - A delimiter commits the word with the delimiter.
+/// - Enter, Tab and other keys commit it and are forwarded.
+/// - Digits in Telex are forwarded, not composed.
+/// - Escape drops the preedit and is not forwarded.
+/// - Losing focus commits it.
+///
+/// ```
+/// use uvie::ime::{ImeAction, Session};
+/// use uvie::{InputMethod, Key};
+///
+/// let mut session = Session::new(InputMethod::Telex);
+/// session.key_event(Key::Char('a'));
+/// assert_eq!(session.key_event(Key::Char('s')), ImeAction::UpdatePreedit("á"));
+/// assert_eq!(session.focus_out().as_deref(), Some("á"));
+/// ```
+#[derive(Debug, Clone)]
+pub struct Session {
+    engine: UltraFastViEngine,
+}
+
+impl Session {
+    pub fn new(method: InputMethod) -> Self {
+        Self::with_engine(UltraFastViEngine::builder().input_method(method).build())
+    }
+
+    /// A session around an engine configured by the caller.
+    pub fn with_engine(engine: UltraFastViEngine) -> Self {
+        Self { engine }
+    }
+
+    pub fn engine(&self) -> &UltraFastViEngine {
+        &self.engine
+    }
+
+    pub fn engine_mut(&mut self) -> &mut UltraFastViEngine {
+        &mut self.engine
+    }
+
+    /// Preedit text currently shown; `""` when no word is being composed.
+    pub fn preedit(&self) -> &str {
+        self.engine.preview()
+    }
+
+    pub fn key_event(&mut self, key: Key) -> ImeAction<'_> {
+        let composing = !self.engine.raw().is_empty();
+        match key {
+            Key::Escape if composing => {
+                self.engine.clear();
+                return ImeAction::UpdatePreedit("");
+            }
+            Key::Char(c) if c.is_ascii_digit() && self.engine.input_method() == InputMethod::Telex => {
+                return match composing {
+                    true => ImeAction::CommitAndForward(self.engine.commit().to_owned(), key),
+                    false => ImeAction::Forward(key),
+                };
+            }
+            _ => {}
+        }
+        match self.engine.process(key) {
+            FeedResult::Preview(preedit) => ImeAction::UpdatePreedit(preedit),
+            FeedResult::Committed { word, delimiter } => {
+                let mut text = String::with_capacity(word.len() + delimiter.len_utf8());
+                text.push_str(word);
+                text.push(delimiter);
+                ImeAction::Commit(text)
+            }
+            FeedResult::CommittedThen { word, key } => ImeAction::CommitAndForward(word.to_owned(), key),
+            FeedResult::PassThrough(c) => ImeAction::Forward(Key::Char(c)),
+            FeedResult::Forward(key) => ImeAction::Forward(key),
+        }
+    }
+
+    /// Commits the word being composed, if any, when the input context loses focus.
+    pub fn focus_out(&mut self) -> Option<String> {
+        let word = self.engine.commit().to_owned();
+        self.engine.clear();
+        (!word.is_empty()).then_some(word)
+    }
+
+    /// Drops the word being composed without committing it (the framework's reset).
+    pub fn reset(&mut self) {
+        self.engine.clear();
+    }
+}
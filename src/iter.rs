@@ -0,0 +1,55 @@
+use crate::{InputMethod, UltraFastViEngine};
+
+/// Adds [`transform_vi`](TransformViExt::transform_vi) to every `char` iterator.
+pub trait TransformViExt: Iterator<Item = char> + Sized {
+    /// Lazily converts the chars like [`transform`](crate::transform) would.
+    ///
+    /// A word is yielded once a non-word char (or the end of the stream) finishes it, so
+    /// nothing already yielded ever needs correcting.
+    fn transform_vi(self, method: InputMethod) -> TransformVi<Self> {
+        TransformVi {
+            chars: self,
+            engine: UltraFastViEngine::builder().input_method(method).build(),
+            pending: String::new(),
+            pos: 0,
+            done: false,
+        }
+    }
+}
+
+impl<I: Iterator<Item = char>> TransformViExt for I {}
+
+/// Iterator returned by [`TransformViExt::transform_vi`].
+pub struct TransformVi<I> {
+    chars: I,
+    engine: UltraFastViEngine,
+    // Converted text not yielded yet, from byte `pos` on.
+    pending: String,
+    pos: usize,
+    done: bool,
+}
+
+impl<I: Iterator<Item = char>> Iterator for TransformVi<I> {
+    type Item = char;
+
+    fn next(&mut self) -> Option<char> {
+        loop {
+            if let Some(c) = self.pending[self.pos..].chars().next() {
+                self.pos += c.len_utf8();
+                return Some(c);
+            }
+            self.pending.clear();
+            self.pos = 0;
+            if self.done {
+                return None;
+            }
+            match self.chars.next() {
+                Some(c) => self.engine.transform_push(c, &mut self.pending),
+                None => {
+                    self.engine.transform_finish(&mut self.pending);
+                    self.done = true;
+                }
+            }
+        }
+    }
+}
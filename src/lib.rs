@@ -1,12 +1,80 @@
 #![cfg_attr(not(feature = "std"), no_std)]
 
-pub mod buffers;
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+mod buffers;
+pub mod config;
 pub mod engine;
+pub mod english;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod fold;
+#[cfg(feature = "std")]
+pub mod hints;
+#[cfg(feature = "alloc")]
+pub mod ime;
+#[cfg(feature = "std")]
+pub mod iter;
 pub mod modes;
+pub mod prelude;
+#[cfg(feature = "python")]
+pub mod python;
+#[cfg(feature = "std")]
+pub mod retone;
+#[cfg(feature = "alloc")]
+pub mod reverse;
+pub mod syllable;
 pub mod tone;
+pub mod validate;
+#[cfg(feature = "std")]
+pub mod vectors;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+#[cfg(feature = "std")]
+pub mod writer;
 
-#[cfg(test)]
+#[cfg(all(test, feature = "std"))]
 mod tests;
 
-pub use crate::engine::UltraFastViEngine;
-pub use crate::modes::InputMethod;
+pub use crate::config::{Delimiters, EngineConfig, KeyRemap, OutputForm, StopFinalTone, TelexVariant, ValidationLevel, YOrthography};
+pub use crate::engine::{Decision, Delta, EngineBuilder, EngineError, EngineState, FeedResult, InputResult, Key, KeystrokeEffect, UltraFastViEngine};
+#[cfg(feature = "std")]
+pub use crate::iter::TransformViExt;
+pub use crate::modes::{InputMethod, ParseInputMethodError};
+#[cfg(feature = "std")]
+pub use crate::retone::retone_text;
+pub use crate::tone::ToneStyle;
+#[cfg(feature = "std")]
+pub use crate::vectors::run_vectors;
+#[cfg(feature = "std")]
+pub use crate::writer::{ViWriter, convert};
+
+/// Converts a whole string with a fresh engine; see [`UltraFastViEngine::transform_str`].
+#[cfg(feature = "std")]
+pub fn transform(input: &str, method: InputMethod) -> String {
+    let mut engine = UltraFastViEngine::new();
+    engine.set_input_method(method);
+    let mut out = String::with_capacity(input.len() + input.len() / 2);
+    engine.transform_str(input, &mut out);
+    out
+}
+
+/// Renders the keys of a single word, e.g. `transform_word("vieejt", Telex)` → `việt`.
+///
+/// Callable from any thread without setting up an engine: each thread reuses its own
+/// engine, so the returned `String` is the only allocation once warmed up.
+#[cfg(feature = "std")]
+pub fn transform_word(raw: &str, method: InputMethod) -> String {
+    std::thread_local! {
+        static ENGINE: core::cell::RefCell<UltraFastViEngine> = core::cell::RefCell::new(UltraFastViEngine::new());
+    }
+    ENGINE.with_borrow_mut(|engine| {
+        if engine.input_method() != method {
+            engine.set_input_method(method);
+        }
+        let mut out = String::with_capacity(raw.len() + raw.len() / 2);
+        engine.transform_str(raw, &mut out);
+        out
+    })
+}
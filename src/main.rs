@@ -1,33 +1,38 @@
-use std::io::{self, Read};
-use uvie::{InputMethod, UltraFastViEngine};
+use std::io::{self, IsTerminal, Read, Write};
+use std::time::{Duration, Instant};
+use uvie::{Decision, InputMethod, ToneStyle, UltraFastViEngine, retone_text};
 
 fn main() {
     let mut engine = UltraFastViEngine::new();
 
-    let mut args = std::env::args().skip(1);
+    let mut args = std::env::args().skip(1).peekable();
+    if args.peek().map(String::as_str) == Some("retone") {
+        args.next();
+        retone(args);
+        return;
+    }
+    if args.peek().map(String::as_str) == Some("convert") {
+        args.next();
+        convert(args);
+        return;
+    }
+    if args.peek().map(String::as_str) == Some("check") {
+        args.next();
+        check(args);
+        return;
+    }
+
     let mut method = InputMethod::Telex;
     while let Some(arg) = args.next() {
         match arg.as_str() {
             "--help" | "-h" => {
+                let methods = method_names();
                 println!(
-                    "Usage: uvie [--mode telex|vni]\n\n  --mode telex|vni   Select input method (default: telex)"
+                    "Usage: uvie [--mode {methods}]\n       uvie convert [--mode {methods}] [FILE]\n       uvie retone [--style old|new] [FILE]\n       uvie check FILE\n\n  --mode {methods:<16} Select input method (default: telex)\n  convert                 Convert FILE (or stdin) to Vietnamese on stdout; also used when stdin is piped\n  retone                  Re-place tone marks of FILE (or stdin) in the given style\n  check                   Verify the method<TAB>keys<TAB>expected test vectors in FILE"
                 );
                 return;
             }
-            "--mode" => {
-                let Some(v) = args.next() else {
-                    eprintln!("--mode requires a value: telex|vni");
-                    return;
-                };
-                method = match v.as_str() {
-                    "telex" => InputMethod::Telex,
-                    "vni" => InputMethod::Vni,
-                    _ => {
-                        eprintln!("Unsupported mode: {v} (use telex|vni)");
-                        return;
-                    }
-                };
-            }
+            "--mode" => method = parse_mode(args.next()),
             _ => {
                 eprintln!("Unknown argument: {arg} (use --help)");
                 return;
@@ -35,32 +40,599 @@ fn main() {
         }
     }
 
+    if !io::stdin().is_terminal() {
+        convert_to_stdout(io::stdin().lock(), method, "stdin");
+        return;
+    }
+
     engine.set_input_method(method);
+    match interactive(&mut engine) {
+        Ok(stats) => println!("{}", format_stats(&stats)),
+        Err(err) => {
+            eprintln!("Terminal error: {err}");
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Raw terminal mode for as long as it is alive, restored on drop (and so on panic).
+struct RawTerminal;
+
+impl RawTerminal {
+    fn enable() -> io::Result<Self> {
+        crossterm::terminal::enable_raw_mode()?;
+        Ok(Self)
+    }
+}
+
+impl Drop for RawTerminal {
+    fn drop(&mut self) {
+        let _ = crossterm::terminal::disable_raw_mode();
+    }
+}
+
+/// Types into a single line: committed words followed by the live preview of the word
+/// being composed. Enter finishes the line, Backspace removes the last keystroke (or the
+/// last committed char), Esc reverts the word to its raw keys, F9 cycles the input method,
+/// Ctrl+C / Ctrl+D quit. A line typed as one of the [`Command`]s runs it instead.
+fn interactive(engine: &mut UltraFastViEngine) -> io::Result<Stats> {
+    let _raw = RawTerminal::enable()?;
+    let mut stats = Stats::default();
     let mut stdin = io::stdin().lock();
+    let mut stdout = io::stdout().lock();
+    let mut line = String::new();
+    // Chars typed on the line, to recognize commands whatever the input method made of them
+    let mut keys = String::new();
+    let mut buf = [0u8; 64];
+    let mut decoder = Utf8Decoder::default();
+    let mut chars = Vec::new();
+    let mut reader = KeyReader::default();
+    let mut term_keys = Vec::new();
+
+    'read: loop {
+        let n = stdin.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        chars.clear();
+        decoder.decode(&buf[..n], &mut chars);
+        term_keys.clear();
+        reader.read(&chars, &mut term_keys);
 
-    let mut buf = [0u8; 1];
+        for key in &term_keys {
+            let c = match key {
+                TermKey::Char(c) => *c,
+                TermKey::Esc => {
+                    keys.clear();
+                    engine.revert_raw();
+                    redraw(&mut stdout, &line, engine.preview())?;
+                    continue;
+                }
+                // Escape sequences (arrow keys, ...) other than F9 are skipped
+                TermKey::Sequence(sequence) => {
+                    if let Some(command) = parse_sequence(sequence, engine.input_method()) {
+                        line.push_str(&stats.commit(engine));
+                        run_command(engine, command);
+                        write!(stdout, "\r\n{}\r\n", confirmation(command))?;
+                        redraw(&mut stdout, &line, "")?;
+                    }
+                    continue;
+                }
+            };
 
-    loop {
-        if let Ok(n) = stdin.read(&mut buf) {
-            if n == 0 {
-                continue;
+            match c {
+                '\x03' | '\x04' => break 'read,
+                '\r' | '\n' => {
+                    line.push_str(&stats.commit(engine));
+                    redraw(&mut stdout, &line, "")?;
+                    write!(stdout, "\r\n")?;
+                    if let Some(command) = parse_command(&keys) {
+                        run_command(engine, command);
+                        write!(stdout, "{}\r\n", confirmation(command))?;
+                    }
+                    line.clear();
+                    keys.clear();
+                    continue;
+                }
+                '\x7f' | '\x08' => {
+                    keys.pop();
+                    if engine.preview().is_empty() {
+                        line.pop();
+                    } else {
+                        engine.backspace();
+                    }
+                }
+                c => {
+                    keys.push(c);
+                    stats.feed(engine, c);
+                    if engine.preview().is_empty() {
+                        line.push_str(engine.rendered());
+                    }
+                }
             }
-            let b = buf[0];
+            redraw(&mut stdout, &line, engine.preview())?;
+        }
+    }
+
+    line.push_str(&stats.commit(engine));
+    redraw(&mut stdout, &line, "")?;
+    write!(stdout, "\r\n")?;
+    stdout.flush()?;
+    Ok(stats)
+}
+
+/// Counters of an interactive session, kept by the CLI around the engine calls.
+#[derive(Debug, Default)]
+struct Stats {
+    keystrokes: u32,
+    words: u32,
+    /// Words committed as the raw keys rather than converted (English).
+    fallbacks: u32,
+    feed_time: Duration,
+}
+
+impl Stats {
+    /// Feeds `key`, timing the call and counting the word it commits, if any.
+    fn feed(&mut self, engine: &mut UltraFastViEngine, key: char) {
+        let (raw, decision) = (engine.raw().to_owned(), engine.decision());
+        let start = Instant::now();
+        engine.feed(key);
+        self.feed_time += start.elapsed();
+        self.keystrokes += 1;
+        // An escape char starting a word leaves nothing rendered and commits nothing
+        if engine.preview().is_empty()
+            && let Some(word) = engine.rendered().strip_suffix(key)
+        {
+            self.count_word(&raw, decision, word);
+        }
+    }
+
+    fn commit(&mut self, engine: &mut UltraFastViEngine) -> String {
+        let (raw, decision) = (engine.raw().to_owned(), engine.decision());
+        let word = engine.commit().to_owned();
+        self.count_word(&raw, decision, &word);
+        word
+    }
+
+    fn count_word(&mut self, raw: &str, decision: Decision, word: &str) {
+        if raw.is_empty() {
+            return;
+        }
+        self.words += 1;
+        // Either shown as typed already, or converted and then restored on commit
+        if word == raw && matches!(decision, Decision::Fallback | Decision::Composing) {
+            self.fallbacks += 1;
+        }
+    }
+}
+
+fn format_stats(stats: &Stats) -> String {
+    let average = match stats.keystrokes {
+        0 => Duration::ZERO,
+        n => stats.feed_time / n,
+    };
+    format!(
+        "{} keystrokes, {} words committed ({} kept as typed), {:.2}µs per keystroke",
+        stats.keystrokes,
+        stats.words,
+        stats.fallbacks,
+        average.as_secs_f64() * 1e6
+    )
+}
+
+/// A key read from the terminal.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum TermKey {
+    Char(char),
+    Esc,
+    /// An escape sequence (arrow keys, function keys) after its `ESC [` or `ESC O`.
+    Sequence(String),
+}
 
-            if b == b'\n' {
-                let out = engine.feed(' ');
-                println!("\n{}", out);
-                continue;
+#[derive(Default)]
+enum Escape {
+    #[default]
+    None,
+    Started,
+    Sequence,
+}
+
+/// Splits terminal input into keys. A terminal sends an escape sequence in one read,
+/// so an ESC ending a read is the Esc key, and so is one followed by anything but
+/// `[` or `O`.
+#[derive(Default)]
+struct KeyReader {
+    escape: Escape,
+    sequence: String,
+}
+
+impl KeyReader {
+    /// Appends the keys completed by the chars of one read to `out`.
+    fn read(&mut self, chars: &[char], out: &mut Vec<TermKey>) {
+        for &c in chars {
+            match self.escape {
+                Escape::Started if c == '[' || c == 'O' => {
+                    self.escape = Escape::Sequence;
+                    self.sequence.clear();
+                    continue;
+                }
+                Escape::Started => {
+                    self.escape = Escape::None;
+                    out.push(TermKey::Esc);
+                }
+                Escape::Sequence => {
+                    self.sequence.push(c);
+                    if ('\x40'..='\x7e').contains(&c) {
+                        self.escape = Escape::None;
+                        out.push(TermKey::Sequence(std::mem::take(&mut self.sequence)));
+                    }
+                    continue;
+                }
+                Escape::None => {}
             }
+            match c {
+                '\x1b' => self.escape = Escape::Started,
+                c => out.push(TermKey::Char(c)),
+            }
+        }
+        if let Escape::Started = self.escape {
+            self.escape = Escape::None;
+            out.push(TermKey::Esc);
+        }
+    }
+}
 
-            if b == 3 {
-                break;
+/// Interactive command, typed on its own line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Command {
+    /// `:telex`, `:vni`, `:viqr` or `:auto` (any of [`InputMethod::ALL`])
+    Method(InputMethod),
+    /// `:clear`
+    Clear,
+}
+
+fn parse_command(line: &str) -> Option<Command> {
+    match line.trim().strip_prefix(':')? {
+        "clear" => Some(Command::Clear),
+        name => name.parse().ok().map(Command::Method),
+    }
+}
+
+/// The command bound to an escape sequence: F9 (`20~`) switches from `current` to the
+/// next of [`InputMethod::ALL`].
+fn parse_sequence(sequence: &str, current: InputMethod) -> Option<Command> {
+    if sequence != "20~" {
+        return None;
+    }
+    let all = InputMethod::ALL;
+    let index = all.iter().position(|&m| m == current).unwrap_or(0);
+    Some(Command::Method(all[(index + 1) % all.len()]))
+}
+
+/// Runs `command` and returns the text it committed. Switching the input method commits
+/// the word being composed as rendered so far; `:clear` discards it.
+fn run_command(engine: &mut UltraFastViEngine, command: Command) -> String {
+    match command {
+        Command::Method(method) => {
+            let committed = engine.commit().to_owned();
+            engine.set_input_method(method);
+            committed
+        }
+        Command::Clear => {
+            engine.clear();
+            String::new()
+        }
+    }
+}
+
+fn confirmation(command: Command) -> String {
+    match command {
+        Command::Method(method) => format!("[input method: {method}]"),
+        Command::Clear => "[cleared]".to_owned(),
+    }
+}
+
+/// Decodes UTF-8 read in arbitrary chunks, holding back a sequence split between reads.
+#[derive(Default)]
+struct Utf8Decoder {
+    buf: [u8; 4],
+    len: usize,
+}
+
+impl Utf8Decoder {
+    /// Appends the chars completed by `bytes` to `out`; invalid or truncated sequences
+    /// become U+FFFD.
+    fn decode(&mut self, bytes: &[u8], out: &mut Vec<char>) {
+        for &b in bytes {
+            if self.len > 0 && b & 0xc0 != 0x80 {
+                out.push(char::REPLACEMENT_CHARACTER);
+                self.len = 0;
+            }
+            self.buf[self.len] = b;
+            self.len += 1;
+            match std::str::from_utf8(&self.buf[..self.len]) {
+                Ok(s) => {
+                    out.extend(s.chars());
+                    self.len = 0;
+                }
+                Err(e) if e.error_len().is_some() => {
+                    out.push(char::REPLACEMENT_CHARACTER);
+                    self.len = 0;
+                }
+                Err(_) => {}
             }
+        }
+    }
+}
+
+fn redraw(out: &mut impl Write, committed: &str, preview: &str) -> io::Result<()> {
+    use crossterm::terminal::{Clear, ClearType};
+    crossterm::queue!(out, crossterm::cursor::MoveToColumn(0), Clear(ClearType::CurrentLine))?;
+    write!(out, "{committed}{preview}")?;
+    out.flush()
+}
 
-            let c = b as char;
-            let out = engine.feed(c);
-            print!("\r{}", out);
-            io::Write::flush(&mut io::stdout()).unwrap();
+fn parse_mode(value: Option<String>) -> InputMethod {
+    let Some(value) = value else {
+        eprintln!("--mode requires a value: {}", method_names());
+        std::process::exit(2);
+    };
+    value.parse().unwrap_or_else(|err| {
+        eprintln!("--mode {value:?}: {err}");
+        std::process::exit(2);
+    })
+}
+
+/// `telex|vni|viqr|auto`
+fn method_names() -> String {
+    InputMethod::ALL.iter().copied().map(InputMethod::name).collect::<Vec<_>>().join("|")
+}
+
+fn convert(mut args: impl Iterator<Item = String>) {
+    let mut method = InputMethod::Telex;
+    let mut path = None;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--mode" => method = parse_mode(args.next()),
+            _ if path.is_none() => path = Some(arg),
+            _ => {
+                eprintln!("Unexpected argument: {arg} (use --help)");
+                std::process::exit(2);
+            }
         }
     }
+
+    match &path {
+        Some(p) => match std::fs::File::open(p) {
+            Ok(file) => convert_to_stdout(io::BufReader::new(file), method, p),
+            Err(err) => {
+                eprintln!("Cannot read {p}: {err}");
+                std::process::exit(1);
+            }
+        },
+        None => convert_to_stdout(io::stdin().lock(), method, "stdin"),
+    }
+}
+
+fn convert_to_stdout(input: impl io::BufRead, method: InputMethod, name: &str) {
+    let stdout = io::BufWriter::new(io::stdout().lock());
+    if let Err(err) = uvie::convert(input, stdout, method) {
+        eprintln!("Cannot convert {name}: {err}");
+        std::process::exit(1);
+    }
+}
+
+fn retone(mut args: impl Iterator<Item = String>) {
+    let mut style = ToneStyle::Modern;
+    let mut path = None;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--style" => {
+                let Some(v) = args.next() else {
+                    eprintln!("--style requires a value: old|new");
+                    std::process::exit(2);
+                };
+                style = match v.as_str() {
+                    "old" | "traditional" => ToneStyle::Traditional,
+                    "new" | "modern" => ToneStyle::Modern,
+                    _ => {
+                        eprintln!("Unsupported style: {v} (use old|new)");
+                        std::process::exit(2);
+                    }
+                };
+            }
+            _ if path.is_none() => path = Some(arg),
+            _ => {
+                eprintln!("Unexpected argument: {arg} (use --help)");
+                std::process::exit(2);
+            }
+        }
+    }
+
+    let text = match &path {
+        Some(p) => std::fs::read_to_string(p),
+        None => io::read_to_string(io::stdin()),
+    };
+    match text {
+        Ok(text) => print!("{}", retone_text(&text, style)),
+        Err(err) => {
+            eprintln!("Cannot read {}: {err}", path.as_deref().unwrap_or("stdin"));
+            std::process::exit(1);
+        }
+    }
+}
+
+fn check(mut args: impl Iterator<Item = String>) {
+    let (Some(path), None) = (args.next(), args.next()) else {
+        eprintln!("check requires exactly one vector file (use --help)");
+        std::process::exit(2);
+    };
+    let failures = match std::fs::File::open(&path).and_then(|file| uvie::run_vectors(io::BufReader::new(file))) {
+        Ok(failures) => failures,
+        Err(err) => {
+            eprintln!("Cannot read {path}: {err}");
+            std::process::exit(1);
+        }
+    };
+    for failure in &failures {
+        eprintln!("{path}:{failure}");
+    }
+    if !failures.is_empty() {
+        eprintln!("{} failure(s)", failures.len());
+        std::process::exit(1);
+    }
+    println!("{path}: all vectors pass");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        Command, KeyReader, Stats, TermKey, Utf8Decoder, format_stats, parse_command, parse_sequence, run_command,
+    };
+    use std::time::Duration;
+    use uvie::{InputMethod, UltraFastViEngine};
+
+    fn decode_chunks(chunks: &[&[u8]]) -> String {
+        let mut decoder = Utf8Decoder::default();
+        let mut out = Vec::new();
+        for chunk in chunks {
+            decoder.decode(chunk, &mut out);
+        }
+        out.into_iter().collect()
+    }
+
+    #[test]
+    fn decodes_whole_sequences() {
+        assert_eq!(decode_chunks(&["việt 中".as_bytes()]), "việt 中");
+    }
+
+    #[test]
+    fn decodes_sequences_split_between_reads() {
+        let bytes = "ệ€".as_bytes();
+        assert_eq!(decode_chunks(&[&bytes[..1], &bytes[1..2], &bytes[2..4], &bytes[4..]]), "ệ€");
+    }
+
+    #[test]
+    fn replaces_invalid_bytes() {
+        assert_eq!(decode_chunks(&[b"a\x80b\xffc"]), "a\u{fffd}b\u{fffd}c");
+        // A sequence cut short by the next char
+        assert_eq!(decode_chunks(&[b"\xe1\xbb", b"a"]), "\u{fffd}a");
+    }
+
+    fn read_keys(reads: &[&str]) -> Vec<TermKey> {
+        let mut reader = KeyReader::default();
+        let mut out = Vec::new();
+        for read in reads {
+            reader.read(&read.chars().collect::<Vec<_>>(), &mut out);
+        }
+        out
+    }
+
+    #[test]
+    fn tells_esc_from_escape_sequences() {
+        use TermKey::{Char, Esc, Sequence};
+        // An arrow key mid-word leaves the word alone
+        assert_eq!(read_keys(&["a\x1b[D", "b"]), [Char('a'), Sequence("D".into()), Char('b')]);
+        assert_eq!(read_keys(&["\x1b[20~"]), [Sequence("20~".into())]);
+        assert_eq!(read_keys(&["\x1b[2", "0~"]), [Sequence("20~".into())]);
+        // Esc on its own, then `O` or `[` typed later
+        assert_eq!(read_keys(&["a\x1b", "Oa"]), [Char('a'), Esc, Char('O'), Char('a')]);
+        assert_eq!(read_keys(&["\x1b", "[a"]), [Esc, Char('['), Char('a')]);
+        assert_eq!(read_keys(&["\x1ba"]), [Esc, Char('a')]);
+    }
+
+    #[test]
+    fn parses_commands() {
+        assert_eq!(parse_command(":vni"), Some(Command::Method(InputMethod::Vni)));
+        assert_eq!(parse_command(" :telex "), Some(Command::Method(InputMethod::Telex)));
+        assert_eq!(parse_command(":VIQR"), Some(Command::Method(InputMethod::Viqr)));
+        assert_eq!(parse_command(":auto"), Some(Command::Method(InputMethod::Auto)));
+        assert_eq!(parse_command(":clear"), Some(Command::Clear));
+        assert_eq!(parse_command("vni"), None);
+        assert_eq!(parse_command(":vni now"), None);
+    }
+
+    #[test]
+    fn switching_mid_word_commits_it() {
+        let mut engine = UltraFastViEngine::new();
+        engine.feed_str("vieet");
+        assert_eq!(run_command(&mut engine, Command::Method(InputMethod::Vni)), "viêt");
+        assert_eq!(engine.input_method(), InputMethod::Vni);
+        assert_eq!(engine.preview(), "");
+        assert_eq!(engine.feed_str("a6"), "â");
+    }
+
+    #[test]
+    fn f9_mid_word_commits_the_rendered_word() {
+        let mut engine = UltraFastViEngine::new();
+        let mut stats = Stats::default();
+        let mut line = String::new();
+        for key in read_keys(&["vieejt", "\x1b[20~"]) {
+            match key {
+                TermKey::Char(c) => stats.feed(&mut engine, c),
+                TermKey::Sequence(sequence) => {
+                    let command = parse_sequence(&sequence, engine.input_method()).unwrap();
+                    line.push_str(&stats.commit(&mut engine));
+                    run_command(&mut engine, command);
+                }
+                TermKey::Esc => unreachable!("F9 is not a lone Esc"),
+            }
+        }
+        assert_eq!(line, "việt");
+        assert_eq!(engine.input_method(), InputMethod::Vni);
+        assert_eq!(parse_sequence("20~", InputMethod::Auto), Some(Command::Method(InputMethod::Telex)));
+        assert_eq!(parse_sequence("D", InputMethod::Telex), None);
+    }
+
+    #[test]
+    fn clear_discards_the_word() {
+        let mut engine = UltraFastViEngine::new();
+        engine.feed_str("vieet");
+        assert_eq!(run_command(&mut engine, Command::Clear), "");
+        assert_eq!(engine.preview(), "");
+        assert_eq!(engine.commit(), "");
+    }
+
+    #[test]
+    fn counts_words_and_fallbacks() {
+        let mut engine = UltraFastViEngine::new();
+        let mut stats = Stats::default();
+        for key in "vieejt clear ".chars() {
+            stats.feed(&mut engine, key);
+        }
+        for key in "ass".chars() {
+            stats.feed(&mut engine, key);
+        }
+        assert_eq!(stats.commit(&mut engine), "as");
+        assert_eq!(stats.commit(&mut engine), "");
+        assert_eq!((stats.keystrokes, stats.words, stats.fallbacks), (16, 3, 1));
+    }
+
+    #[test]
+    fn counts_escaped_words() {
+        let mut engine = UltraFastViEngine::new();
+        let mut stats = Stats::default();
+        for key in "\\vieejt \\".chars() {
+            stats.feed(&mut engine, key);
+        }
+        assert_eq!(engine.rendered(), "");
+        assert_eq!(stats.commit(&mut engine), "");
+        // The escaped word is kept as typed
+        assert_eq!((stats.keystrokes, stats.words, stats.fallbacks), (9, 1, 1));
+    }
+
+    #[test]
+    fn formats_stats() {
+        let stats = Stats {
+            keystrokes: 4,
+            words: 2,
+            fallbacks: 1,
+            feed_time: Duration::from_micros(6),
+        };
+        assert_eq!(format_stats(&stats), "4 keystrokes, 2 words committed (1 kept as typed), 1.50µs per keystroke");
+        assert_eq!(
+            format_stats(&Stats::default()),
+            "0 keystrokes, 0 words committed (0 kept as typed), 0.00µs per keystroke"
+        );
+    }
 }
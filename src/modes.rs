@@ -1,47 +1,271 @@
+use crate::tone::{tone_row_base, tone_vowel_index};
+
 pub const IS_VOWEL: u8 = 1 << 0;
 pub const IS_MODIFIER: u8 = 1 << 1;
 pub const IS_TONE_KEY: u8 = 1 << 2;
 
-#[derive(Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
 pub enum InputMethod {
     Telex,
     Vni,
+    Viqr,
+    /// Picks Telex or VNI for each word from its keys; see
+    /// [`UltraFastViEngine::set_input_method`](crate::UltraFastViEngine::set_input_method).
+    Auto,
+}
+
+impl InputMethod {
+    pub const ALL: &'static [InputMethod] =
+        &[InputMethod::Telex, InputMethod::Vni, InputMethod::Viqr, InputMethod::Auto];
+
+    /// Lowercase name, as parsed by [`FromStr`](core::str::FromStr) and shown by `Display`.
+    pub const fn name(self) -> &'static str {
+        match self {
+            InputMethod::Telex => "telex",
+            InputMethod::Vni => "vni",
+            InputMethod::Viqr => "viqr",
+            InputMethod::Auto => "auto",
+        }
+    }
 }
 
-type ResolverFn = fn(u8, Option<u8>) -> (char, bool);
+impl core::fmt::Display for InputMethod {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.pad(self.name())
+    }
+}
+
+/// Case-insensitive, e.g. `"VNI".parse()` → `Ok(InputMethod::Vni)`.
+impl core::str::FromStr for InputMethod {
+    type Err = ParseInputMethodError;
 
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        InputMethod::ALL
+            .iter()
+            .copied()
+            .find(|method| method.name().eq_ignore_ascii_case(s))
+            .ok_or(ParseInputMethodError)
+    }
+}
+
+/// A string that names none of [`InputMethod::ALL`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParseInputMethodError;
+
+impl core::fmt::Display for ParseInputMethodError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str("unsupported input method (use ")?;
+        for (i, method) in InputMethod::ALL.iter().enumerate() {
+            if i > 0 {
+                f.write_str("|")?;
+            }
+            f.write_str(method.name())?;
+        }
+        f.write_str(")")
+    }
+}
+
+impl core::error::Error for ParseInputMethodError {}
+
+/// Resolves a lowercase key and the key after it into a letter, reporting whether
+/// the next key was consumed (e.g. `a` + `w` -> `ă`).
+pub type ResolverFn = fn(u8, Option<u8>) -> (char, bool);
+
+#[derive(Clone)]
 pub struct Mode {
-    pub classify: &'static [u8; 256],
-    pub tone: &'static [u8; 256],
-    pub w_target: &'static [bool; 256],
+    /// Input method this mode was derived from.
+    pub method: InputMethod,
+    pub classify: [u8; 256],
+    pub tone: [u8; 256],
+    pub w_target: [bool; 256],
     pub resolver: ResolverFn,
     pub enable_w_bubbling: bool,
+    /// Lets a modifier key typed later in the word reach back to the letter it modifies
+    /// (VNI `truong7` → `trương`).
+    pub enable_modifier_bubbling: bool,
+    /// Tone keys only act as marks right after a vowel, a modifier key or another mark;
+    /// elsewhere they are literal content (VIQR marks double as punctuation).
+    pub tone_needs_vowel: bool,
+    /// Key that makes the following mark literal (`\` in VIQR), 0 for none.
+    pub mark_escape: u8,
+}
+
+impl Mode {
+    /// Key typed after `base` to make `letter` (Telex `w` for `a` → `ă`), if any.
+    pub fn modifier_key(&self, base: u8, letter: char) -> Option<u8> {
+        (b'!'..=b'~').find(|&k| (self.resolver)(base, Some(k)) == (letter, true))
+    }
+
+    /// First key that types `tone` (1-5 = sắc, huyền, hỏi, ngã, nặng), if any.
+    pub fn tone_key(&self, tone: u8) -> Option<u8> {
+        (b'!'..=b'~').find(|&k| self.classify[k as usize] & IS_TONE_KEY != 0 && self.tone[k as usize] == tone)
+    }
+
+    /// How to type the Vietnamese letter `c`: its base key and modifier key, in the case
+    /// of `c`, and its tone (0-5), e.g. Telex `Ộ` → (`O`, `O`, 5). `None` for other chars.
+    pub fn letter_keys(&self, c: char) -> Option<(u8, Option<u8>, u8)> {
+        let upper = c.is_uppercase();
+        let lower = if upper { c.to_lowercase().next()? } else { c };
+        let (letter, tone) = match tone_vowel_index(lower) {
+            Some((row, tone)) => (tone_row_base(row), tone),
+            None if lower == 'đ' => ('đ', 0),
+            None if lower.is_ascii_alphabetic() => (lower, 0),
+            None => return None,
+        };
+        let base = match letter {
+            'ă' | 'â' => b'a',
+            'ê' => b'e',
+            'ô' | 'ơ' => b'o',
+            'ư' => b'u',
+            'đ' => b'd',
+            c => c as u8,
+        };
+        let case = |k: u8| if upper { k.to_ascii_uppercase() } else { k };
+        let modifier = match letter != base as char {
+            true => Some(case(self.modifier_key(base, letter)?)),
+            false => None,
+        };
+        Some((case(base), modifier, tone))
+    }
 }
 
-pub fn mode_for(method: InputMethod) -> &'static Mode {
+// The built-in layouts are statics read from every thread.
+const _: () = {
+    const fn assert_sync<T: Sync>() {}
+    assert_sync::<Mode>();
+};
+
+/// Built-in layout of `method`; `Auto` starts out as Telex.
+pub(crate) fn mode_for(method: InputMethod) -> &'static Mode {
     match method {
-        InputMethod::Telex => &TELEX_MODE,
+        InputMethod::Telex | InputMethod::Auto => &TELEX_MODE,
         InputMethod::Vni => &VNI_MODE,
+        InputMethod::Viqr => &VIQR_MODE,
     }
 }
 
 const TELEX_MODE: Mode = Mode {
-    classify: &CLASSIFY_TELEX,
-    tone: &TONE_TELEX,
-    w_target: &W_TARGET_TELEX,
+    method: InputMethod::Telex,
+    classify: CLASSIFY_TELEX,
+    tone: TONE_TELEX,
+    w_target: W_TARGET_TELEX,
     resolver: resolve_telex,
     enable_w_bubbling: true,
+    enable_modifier_bubbling: false,
+    tone_needs_vowel: false,
+    mark_escape: 0,
 };
 
 const VNI_MODE: Mode = Mode {
-    classify: &CLASSIFY_VNI,
-    tone: &TONE_VNI,
-    w_target: &W_TARGET_VNI,
+    method: InputMethod::Vni,
+    classify: CLASSIFY_VNI,
+    tone: TONE_VNI,
+    w_target: W_TARGET_VNI,
     resolver: resolve_vni,
     enable_w_bubbling: false,
+    enable_modifier_bubbling: true,
+    tone_needs_vowel: false,
+    mark_escape: 0,
+};
+
+const VIQR_MODE: Mode = Mode {
+    method: InputMethod::Viqr,
+    classify: CLASSIFY_VIQR,
+    tone: TONE_VIQR,
+    w_target: W_TARGET_VIQR,
+    resolver: resolve_viqr,
+    enable_w_bubbling: false,
+    enable_modifier_bubbling: false,
+    tone_needs_vowel: true,
+    mark_escape: b'\\',
 };
 
-pub const CLASSIFY_TELEX: [u8; 256] = {
+/// Builds a custom [`Mode`] starting from one of the built-in layouts.
+///
+/// Keys are ASCII bytes and are matched case-insensitively, so only lowercase
+/// entries are written.
+///
+/// ```
+/// use uvie::modes::ModeBuilder;
+/// use uvie::{InputMethod, UltraFastViEngine};
+///
+/// // Telex with nặng on `.` instead of `j`
+/// let mode = ModeBuilder::new(InputMethod::Telex)
+///     .remove_key(b'j')
+///     .tone_key(b'.', 5)
+///     .build();
+/// let mut e = UltraFastViEngine::new();
+/// e.set_custom_mode(mode);
+/// for c in "vieet".chars() {
+///     e.feed(c);
+/// }
+/// assert_eq!(e.feed('.'), "việt");
+/// ```
+#[derive(Clone)]
+pub struct ModeBuilder {
+    mode: Mode,
+}
+
+impl ModeBuilder {
+    pub fn new(base: InputMethod) -> Self {
+        Self {
+            mode: mode_for(base).clone(),
+        }
+    }
+
+    /// Makes `key` a tone key applying `tone` (0 removes the tone, 1-5 sắc..nặng).
+    pub fn tone_key(mut self, key: u8, tone: u8) -> Self {
+        let k = key.to_ascii_lowercase() as usize;
+        self.mode.classify[k] = IS_TONE_KEY;
+        self.mode.tone[k] = tone;
+        self
+    }
+
+    /// Turns `key` into plain content: no tone, modifier or w-target role.
+    pub fn remove_key(mut self, key: u8) -> Self {
+        let k = key.to_ascii_lowercase() as usize;
+        self.mode.classify[k] &= IS_VOWEL;
+        self.mode.tone[k] = 0;
+        self.mode.w_target[k] = false;
+        self
+    }
+
+    /// Overrides the classification bits (`IS_VOWEL`, `IS_MODIFIER`, `IS_TONE_KEY`) of `key`.
+    pub fn classify(mut self, key: u8, attr: u8) -> Self {
+        self.mode.classify[key.to_ascii_lowercase() as usize] = attr;
+        self
+    }
+
+    /// Sets whether a trailing `w` may bubble back to `key`.
+    pub fn w_target(mut self, key: u8, target: bool) -> Self {
+        self.mode.w_target[key.to_ascii_lowercase() as usize] = target;
+        self
+    }
+
+    pub fn w_bubbling(mut self, enable: bool) -> Self {
+        self.mode.enable_w_bubbling = enable;
+        self
+    }
+
+    pub fn modifier_bubbling(mut self, enable: bool) -> Self {
+        self.mode.enable_modifier_bubbling = enable;
+        self
+    }
+
+    /// Replaces the modifier pair resolver (e.g. to add or remap `aa`/`aw`-style pairs).
+    pub fn resolver(mut self, resolver: ResolverFn) -> Self {
+        self.mode.resolver = resolver;
+        self
+    }
+
+    pub fn build(self) -> Mode {
+        self.mode
+    }
+}
+
+pub(crate) const CLASSIFY_TELEX: [u8; 256] = {
     let mut t = [0u8; 256];
     t[b'a' as usize] = IS_VOWEL;
     t[b'e' as usize] = IS_VOWEL;
@@ -62,7 +286,7 @@ pub const CLASSIFY_TELEX: [u8; 256] = {
     t
 };
 
-pub const CLASSIFY_VNI: [u8; 256] = {
+pub(crate) const CLASSIFY_VNI: [u8; 256] = {
     let mut t = [0u8; 256];
     t[b'a' as usize] = IS_VOWEL;
     t[b'e' as usize] = IS_VOWEL;
@@ -77,10 +301,36 @@ pub const CLASSIFY_VNI: [u8; 256] = {
     t[b'3' as usize] = IS_TONE_KEY;
     t[b'4' as usize] = IS_TONE_KEY;
     t[b'5' as usize] = IS_TONE_KEY;
+
+    t[b'6' as usize] = IS_MODIFIER;
+    t[b'7' as usize] = IS_MODIFIER;
+    t[b'8' as usize] = IS_MODIFIER;
+    t[b'9' as usize] = IS_MODIFIER;
     t
 };
 
-pub const W_TARGET_TELEX: [bool; 256] = {
+pub(crate) const CLASSIFY_VIQR: [u8; 256] = {
+    let mut t = [0u8; 256];
+    t[b'a' as usize] = IS_VOWEL;
+    t[b'e' as usize] = IS_VOWEL;
+    t[b'o' as usize] = IS_VOWEL;
+    t[b'u' as usize] = IS_VOWEL;
+    t[b'i' as usize] = IS_VOWEL;
+    t[b'y' as usize] = IS_VOWEL;
+
+    t[b'^' as usize] = IS_MODIFIER;
+    t[b'(' as usize] = IS_MODIFIER;
+    t[b'+' as usize] = IS_MODIFIER;
+
+    t[b'\'' as usize] = IS_TONE_KEY;
+    t[b'`' as usize] = IS_TONE_KEY;
+    t[b'?' as usize] = IS_TONE_KEY;
+    t[b'~' as usize] = IS_TONE_KEY;
+    t[b'.' as usize] = IS_TONE_KEY;
+    t
+};
+
+pub(crate) const W_TARGET_TELEX: [bool; 256] = {
     let mut t = [false; 256];
     t[b'a' as usize] = true;
     t[b'o' as usize] = true;
@@ -89,9 +339,11 @@ pub const W_TARGET_TELEX: [bool; 256] = {
     t
 };
 
-pub const W_TARGET_VNI: [bool; 256] = [false; 256];
+pub(crate) const W_TARGET_VNI: [bool; 256] = [false; 256];
+
+pub(crate) const W_TARGET_VIQR: [bool; 256] = [false; 256];
 
-pub const TONE_TELEX: [u8; 256] = {
+pub(crate) const TONE_TELEX: [u8; 256] = {
     let mut t = [0u8; 256];
     t[b's' as usize] = 1;
     t[b'f' as usize] = 2;
@@ -102,7 +354,7 @@ pub const TONE_TELEX: [u8; 256] = {
     t
 };
 
-pub const TONE_VNI: [u8; 256] = {
+pub(crate) const TONE_VNI: [u8; 256] = {
     let mut t = [0u8; 256];
     t[b'0' as usize] = 0;
     t[b'1' as usize] = 1;
@@ -113,6 +365,16 @@ pub const TONE_VNI: [u8; 256] = {
     t
 };
 
+pub(crate) const TONE_VIQR: [u8; 256] = {
+    let mut t = [0u8; 256];
+    t[b'\'' as usize] = 1;
+    t[b'`' as usize] = 2;
+    t[b'?' as usize] = 3;
+    t[b'~' as usize] = 4;
+    t[b'.' as usize] = 5;
+    t
+};
+
 #[inline(always)]
 fn resolve_telex(curr: u8, next: Option<u8>) -> (char, bool) {
     match (curr, next) {
@@ -141,3 +403,17 @@ fn resolve_vni(curr: u8, next: Option<u8>) -> (char, bool) {
         _ => (curr as char, false),
     }
 }
+
+#[inline(always)]
+fn resolve_viqr(curr: u8, next: Option<u8>) -> (char, bool) {
+    match (curr, next) {
+        (b'a', Some(b'^')) => ('â', true),
+        (b'a', Some(b'(')) => ('ă', true),
+        (b'e', Some(b'^')) => ('ê', true),
+        (b'o', Some(b'^')) => ('ô', true),
+        (b'o', Some(b'+')) => ('ơ', true),
+        (b'u', Some(b'+')) => ('ư', true),
+        (b'd', Some(b'd')) => ('đ', true),
+        _ => (curr as char, false),
+    }
+}
@@ -0,0 +1,17 @@
+//! The items most programs need, for a glob import:
+//!
+//! ```
+//! use uvie::prelude::*;
+//!
+//! let mut engine = UltraFastViEngine::builder().input_method(InputMethod::Vni).build();
+//! assert_eq!(engine.feed_str("vie65t"), "việt");
+//! ```
+
+pub use crate::config::{EngineConfig, OutputForm, TelexVariant};
+pub use crate::engine::{EngineBuilder, FeedResult, Key, UltraFastViEngine};
+#[cfg(feature = "std")]
+pub use crate::iter::TransformViExt;
+pub use crate::modes::InputMethod;
+pub use crate::tone::ToneStyle;
+#[cfg(feature = "std")]
+pub use crate::transform;
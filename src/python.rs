@@ -0,0 +1,84 @@
+//! PyO3 bindings (`python` feature): a `uvie` module with a `ViEngine` class and a
+//! `transform` function.
+//!
+//! Build an importable extension with `maturin build --features python,pyo3/extension-module`.
+
+use std::sync::{Mutex, MutexGuard};
+
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+
+use crate::{InputMethod, UltraFastViEngine};
+
+/// Engine shared by any number of Python threads; calls are serialized by a mutex.
+#[pyclass(name = "ViEngine", module = "uvie")]
+pub struct PyViEngine {
+    engine: Mutex<UltraFastViEngine>,
+}
+
+#[pymethods]
+impl PyViEngine {
+    #[new]
+    #[pyo3(signature = (method = "telex"))]
+    fn new(method: &str) -> PyResult<Self> {
+        Ok(Self {
+            engine: Mutex::new(UltraFastViEngine::builder().input_method(parse_method(method)?).build()),
+        })
+    }
+
+    fn feed(&self, key: char) -> String {
+        self.engine().feed(key).to_owned()
+    }
+
+    fn feed_str(&self, text: &str) -> String {
+        self.engine().feed_str(text).to_owned()
+    }
+
+    fn commit(&self) -> String {
+        self.engine().commit().to_owned()
+    }
+
+    fn clear(&self) {
+        self.engine().clear();
+    }
+
+    fn set_method(&self, method: &str) -> PyResult<()> {
+        let method = parse_method(method)?;
+        self.engine().set_input_method(method);
+        Ok(())
+    }
+}
+
+impl PyViEngine {
+    fn engine(&self) -> MutexGuard<'_, UltraFastViEngine> {
+        // A panic mid-call leaves the engine usable; at worst the word is garbled.
+        self.engine.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// Converts a whole string, releasing the GIL while it runs.
+#[pyfunction]
+#[pyo3(signature = (text, method = "telex"))]
+fn transform(py: Python<'_>, text: &str, method: &str) -> PyResult<String> {
+    let method = parse_method(method)?;
+    Ok(py.allow_threads(|| crate::transform(text, method)))
+}
+
+/// Adds the `uvie` module's contents to `m`.
+pub fn register(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyViEngine>()?;
+    m.add_function(wrap_pyfunction!(transform, m)?)?;
+    Ok(())
+}
+
+#[pymodule]
+#[pyo3(name = "uvie")]
+fn uvie_module(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    register(m)
+}
+
+fn parse_method(method: &str) -> PyResult<InputMethod> {
+    method
+        .parse()
+        .map_err(|err| PyValueError::new_err(format!("{method:?}: {err}")))
+}
@@ -0,0 +1,47 @@
+use crate::tone::{BaseWord, ToneStyle};
+
+/// Re-places the tone mark of every word in `text` according to `style`.
+///
+/// Only words whose tone position actually depends on the style (open `oa`, `oe`,
+/// `uy`) are rewritten; case, punctuation and everything else is copied verbatim.
+pub fn retone_text(text: &str, style: ToneStyle) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut word_start: Option<usize> = None;
+    for (i, c) in text.char_indices() {
+        if c.is_alphabetic() {
+            word_start.get_or_insert(i);
+            continue;
+        }
+        if let Some(start) = word_start.take() {
+            retone_word(&text[start..i], style, &mut out);
+        }
+        out.push(c);
+    }
+    if let Some(start) = word_start {
+        retone_word(&text[start..], style, &mut out);
+    }
+    out
+}
+
+fn retone_word(word: &str, style: ToneStyle, out: &mut String) {
+    let Some(base) = BaseWord::parse(word) else {
+        out.push_str(word);
+        return;
+    };
+
+    let modern = base.tone_position(ToneStyle::Modern);
+    let traditional = base.tone_position(ToneStyle::Traditional);
+    let target = match style {
+        ToneStyle::Modern => modern,
+        ToneStyle::Traditional => traditional,
+    };
+
+    // Leave style-independent words and tones sitting somewhere unexpected alone
+    let current = base.toned_at;
+    if current.is_none() || modern == traditional || (current != modern && current != traditional) {
+        out.push_str(word);
+        return;
+    }
+
+    out.extend(base.with_tone(base.tone, target));
+}
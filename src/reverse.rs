@@ -0,0 +1,86 @@
+//! Key sequences that type a given text, for test vectors and typing tutorials.
+//!
+//! Sequences are canonical: a modifier key comes right after its letter (`ư` → `uw`,
+//! `đ` → `dd`) and a word's tone key after its last letter (`người` → `nguwowif`).
+//! Chars other than Latin and Vietnamese letters are copied as they are.
+
+use alloc::string::String;
+
+use crate::modes::{InputMethod, mode_for};
+
+/// Telex keys typing `text`, e.g. `người` → `nguwowif`, `Đà` → `DDaf`.
+///
+/// ```
+/// assert_eq!(uvie::reverse::to_telex("Đường phố"), "DDuwowngf phoos");
+/// ```
+pub fn to_telex(text: &str) -> String {
+    keys_for_text(text, InputMethod::Telex)
+}
+
+/// VNI keys typing `text`, e.g. `người` → `ngu7o7i2`, `đắc` → `d9a8c1`.
+///
+/// VNI has no way to type a literal digit inside a word, so words mixing letters and
+/// digits (`b52`, `mp3`) are returned unchanged.
+///
+/// ```
+/// assert_eq!(uvie::reverse::to_vni("Đường phố"), "D9u7o7ng2 pho61");
+/// ```
+pub fn to_vni(text: &str) -> String {
+    keys_for_text(text, InputMethod::Vni)
+}
+
+fn keys_for_text(text: &str, method: InputMethod) -> String {
+    let mut out = String::with_capacity(text.len() + text.len() / 2);
+    let mut rest = text;
+    while let Some(start) = rest.find(char::is_alphanumeric) {
+        out.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let end = rest.find(|c: char| !c.is_alphanumeric()).unwrap_or(rest.len());
+        let (word, tail) = rest.split_at(end);
+        if method == InputMethod::Vni && word.contains(|c: char| c.is_ascii_digit()) {
+            out.push_str(word);
+        } else {
+            keys_for_word(word, method, &mut out);
+        }
+        rest = tail;
+    }
+    out.push_str(rest);
+    out
+}
+
+fn keys_for_word(word: &str, method: InputMethod, out: &mut String) {
+    let mode = mode_for(method);
+    // Tone key held back until the word ends
+    let mut tone = None;
+    let mut prev_o = false;
+    let mut chars = word.chars().peekable();
+    while let Some(c) = chars.next() {
+        let Some((base, modifier, tone_of_c)) = mode.letter_keys(c) else {
+            out.push(c);
+            prev_o = false;
+            continue;
+        };
+        if tone_of_c != 0 {
+            // A second toned letter is not a syllable; type the first tone where it was
+            out.extend(tone.take());
+            tone = mode
+                .tone_key(tone_of_c)
+                .map(|k| if c.is_uppercase() { k.to_ascii_uppercase() } else { k } as char);
+        }
+        out.push(base as char);
+        out.extend(modifier.map(char::from));
+        // Telex types a literal `oo` before a final consonant with a third `o` (`xooong`)
+        let is_o = base.eq_ignore_ascii_case(&b'o') && modifier.is_none();
+        if method == InputMethod::Telex
+            && prev_o
+            && is_o
+            && chars.peek().and_then(|&next| mode.letter_keys(next)).is_some_and(|(next, ..)| {
+                !matches!(next.to_ascii_lowercase(), b'a' | b'e' | b'i' | b'o' | b'u' | b'y')
+            })
+        {
+            out.push(base as char);
+        }
+        prev_o = is_o && !prev_o;
+    }
+    out.extend(tone);
+}
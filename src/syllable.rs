@@ -0,0 +1,69 @@
+//! Splitting a composed syllable into its parts.
+
+use crate::tone::{Tone, strip_tone};
+use crate::validate::parts;
+
+/// A syllable split into parts, each borrowed from the word as written.
+///
+/// The medial `o`/`u` of `hoa`, `tuần`, `quốc` is the `glide`; in `qu` the onset is
+/// `q`. `gi` before a vowel is a whole onset (`giữa` = `gi` + `ưa`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Syllable<'a> {
+    pub onset: &'a str,
+    pub glide: Option<char>,
+    pub nucleus: &'a str,
+    pub coda: &'a str,
+    /// `None` for the unmarked tone (ngang).
+    pub tone: Option<Tone>,
+}
+
+/// Splits `word` if it is a syllable by [`is_valid_syllable`](crate::validate::is_valid_syllable).
+///
+/// ```
+/// use uvie::syllable::parse;
+/// use uvie::tone::Tone;
+///
+/// let s = parse("Quyết").unwrap();
+/// assert_eq!((s.onset, s.glide, s.nucleus, s.coda), ("Q", Some('u'), "yế", "t"));
+/// assert_eq!(s.tone, Some(Tone::Sac));
+/// assert_eq!(parse("clear"), None);
+/// ```
+pub fn parse(word: &str) -> Option<Syllable<'_>> {
+    if word.is_empty() || !word.chars().all(char::is_alphabetic) {
+        return None;
+    }
+    let p = parts(word.chars(), true)?;
+    let offset = |letters: usize| word.char_indices().nth(letters).map_or(word.len(), |(i, _)| i);
+    let (onset_end, nucleus_end) = (offset(p.onset), offset(p.onset + p.nucleus));
+    let (mut onset, mut nucleus) = (&word[..onset_end], &word[onset_end..nucleus_end]);
+
+    let mut glide = None;
+    if onset.eq_ignore_ascii_case("qu") {
+        glide = onset[1..].chars().next();
+        onset = &onset[..1];
+    } else {
+        let mut chars = nucleus.chars();
+        if let (Some(first), Some(next)) = (chars.next(), chars.next())
+            && matches!(
+                (untoned(first), untoned(next)),
+                ('o', 'a' | 'ă' | 'e') | ('u', 'â' | 'ê' | 'y' | 'ơ')
+            )
+        {
+            glide = Some(first);
+            nucleus = &nucleus[first.len_utf8()..];
+        }
+    }
+    Some(Syllable {
+        onset,
+        glide,
+        nucleus,
+        coda: &word[nucleus_end..],
+        tone: (p.tone as usize).checked_sub(1).map(|i| Tone::ALL[i]),
+    })
+}
+
+/// Lowercase `c` without its tone.
+fn untoned(c: char) -> char {
+    let c = strip_tone(c);
+    c.to_lowercase().next().unwrap_or(c)
+}
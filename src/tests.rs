@@ -1,16 +1,16 @@
-use crate::{InputMethod, UltraFastViEngine};
+use crate::{InputMethod, UltraFastViEngine, ValidationLevel};
 
 fn type_seq(engine: &mut UltraFastViEngine, seq: &str) -> String {
-    let mut out = String::new();
-    for c in seq.chars() {
-        out = engine.feed(c).to_string();
-    }
-    out
+    engine.feed_str(seq).to_string()
 }
 
 fn type_seq_vni(seq: &str) -> String {
-    let mut e = UltraFastViEngine::new();
-    e.set_input_method(InputMethod::Vni);
+    let mut e = UltraFastViEngine::builder().input_method(InputMethod::Vni).build();
+    type_seq(&mut e, seq)
+}
+
+fn type_seq_viqr(seq: &str) -> String {
+    let mut e = UltraFastViEngine::builder().input_method(InputMethod::Viqr).build();
     type_seq(&mut e, seq)
 }
 
@@ -39,112 +39,276 @@ fn telex_modifier_basic() {
 }
 
 #[test]
-fn tone_single_vowel_all_tones() {
-    let mut e = UltraFastViEngine::new();
-    assert_eq!(type_seq(&mut e, "as"), "á");
-
-    let mut e = UltraFastViEngine::new();
-    assert_eq!(type_seq(&mut e, "af"), "à");
-
-    let mut e = UltraFastViEngine::new();
-    assert_eq!(type_seq(&mut e, "ar"), "ả");
-
-    let mut e = UltraFastViEngine::new();
-    assert_eq!(type_seq(&mut e, "ax"), "ã");
+fn escape_prefix_types_a_word_literally() {
+    let mut e = UltraFastViEngine::new();
+    assert_eq!(e.feed_str("\\clears "), "clears ");
+    assert_eq!(e.feed_str("\\vieejt "), "vieejt ");
+    // Only for the one word
+    assert_eq!(e.feed_str("vieejt "), "việt ");
+    // Twice types it; mid-word it is a regular char
+    assert_eq!(e.feed_str("\\\\n "), "\\n ");
+    assert_eq!(e.feed_str("a\\b "), "a\\b ");
+    assert_eq!(crate::transform("\\vieejt vieejt", InputMethod::Telex), "vieejt việt");
+
+    let mut e = UltraFastViEngine::builder().escape_char(None).build();
+    assert_eq!(e.feed_str("\\as "), "\\á ");
+}
 
-    let mut e = UltraFastViEngine::new();
-    assert_eq!(type_seq(&mut e, "aj"), "ạ");
+#[test]
+fn z_without_a_tone_is_content() {
+    let mut e = UltraFastViEngine::new();
+    assert_eq!(e.feed_str("size"), "size");
+    e.commit();
+    assert_eq!(e.feed_str("zalo"), "zalo");
+    e.commit();
+    assert_eq!(e.feed_str("pizza "), "pizza ");
+    assert_eq!(e.feed_str("asz"), "a");
+    assert_eq!(type_seq_vni("ba0"), "ba0");
 }
 
 #[test]
-fn z_key_removes_tone() {
+fn switching_method_rerenders_the_word() {
     let mut e = UltraFastViEngine::new();
-    // as -> á, z -> a
-    assert_eq!(type_seq(&mut e, "asz"), "a");
+    assert_eq!(e.feed_str("pho"), "pho");
+    assert_eq!(e.set_input_method(InputMethod::Vni), "pho");
+    assert_eq!(e.feed_str("61"), "phố");
 
-    let mut e = UltraFastViEngine::new();
-    // az -> a
-    assert_eq!(type_seq(&mut e, "az"), "a");
+    let mut e = UltraFastViEngine::builder().input_method(InputMethod::Vni).build();
+    assert_eq!(e.feed_str("pho"), "pho");
+    assert_eq!(e.set_input_method(InputMethod::Telex), "pho");
+    assert_eq!(e.feed_str("os"), "phố");
 
+    // Keys typed so far are read again under the new rules
     let mut e = UltraFastViEngine::new();
-    // axz -> a
-    assert_eq!(type_seq(&mut e, "axz"), "a");
+    assert_eq!(e.feed_str("tieng"), "tieng");
+    e.feed_str("s");
+    assert_eq!(e.set_input_method(InputMethod::Vni), "tiengs");
 }
 
 #[test]
-fn toggling_triplet() {
-    let mut e = UltraFastViEngine::new();
-    // aaa -> a
-    assert_eq!(type_seq(&mut e, "aaa"), "a");
-
-    let mut e = UltraFastViEngine::new();
-    // ddd -> d
-    assert_eq!(type_seq(&mut e, "ddd"), "d");
-
-    let mut e = UltraFastViEngine::new();
-    // eee -> e
-    assert_eq!(type_seq(&mut e, "eee"), "e");
+fn auto_input_method() {
+    let mut e = UltraFastViEngine::builder().input_method(InputMethod::Auto).build();
+    for (keys, word) in [
+        ("pho61", "phố"),
+        ("phoos", "phố"),
+        ("hello", "hello"),
+        ("truong7", "trương"),
+        ("d9uo7ng2", "đường"),
+        ("ddaay", "đây"),
+        // Digits and Telex keys together: Telex wins, the digit stays
+        ("tie6s", "tié6"),
+    ] {
+        assert_eq!(e.feed_str(keys), word, "{keys}");
+        e.commit();
+    }
+    assert_eq!(e.input_method(), InputMethod::Auto);
+}
 
-    let mut e = UltraFastViEngine::new();
-    // ooo -> o
-    assert_eq!(type_seq(&mut e, "ooo"), "o");
+#[test]
+fn telex_variants() {
+    use crate::TelexVariant;
+    let cases = [
+        // keys, free-style, strict
+        ("nana", "nân", "nana"),
+        // An adjacent pair is plain Telex in both
+        ("naa", "nâ", "nâ"),
+        ("truongw", "trương", "truongw"),
+        ("hsoa", "hoá", "hsoa"),
+        ("vieetj", "việt", "việt"),
+        ("truowngf", "trường", "trường"),
+        ("aww", "aw", "aw"),
+    ];
+    for (variant, column) in [(TelexVariant::FreeStyle, 1), (TelexVariant::Strict, 2)] {
+        let mut e = UltraFastViEngine::builder().telex_variant(variant).build();
+        for case in cases {
+            let (keys, word) = (case.0, [case.1, case.2][column - 1]);
+            e.feed_str(keys);
+            assert_eq!(e.commit(), word, "{variant:?} {keys}");
+        }
+    }
 }
 
 #[test]
-fn tone_on_modified_vowels() {
-    let mut e = UltraFastViEngine::new();
-    assert_eq!(type_seq(&mut e, "aas"), "ấ");
+fn w_shorthand_off() {
+    let mut e = UltraFastViEngine::builder().w_shorthand(false).build();
+    for (keys, word) in [("www", "www"), ("wow", "wow"), ("w", "w"), ("tuwf", "từ"), ("owf", "ờ"), ("aws", "ắ")] {
+        assert_eq!(e.feed_str(keys), word, "{keys}");
+        assert_eq!(e.commit(), word);
+    }
+    // On by default
+    assert_eq!(UltraFastViEngine::new().feed_str("twf"), "từ");
+}
 
-    let mut e = UltraFastViEngine::new();
-    assert_eq!(type_seq(&mut e, "awj"), "ặ");
+#[test]
+fn extending_a_word_matches_composing_it_anew() {
+    let words = [
+        (InputMethod::Telex, "nghieengtrangthanhcong Nguwowif huowng xooong tuwn hoafnh Vieetj thuyeenf dodng khoangr cass hocf"),
+        (InputMethod::Vni, "nghie6ngtrangthanhcong Ngu7o72i huo7ng truong7 d9ung hoa2nh file1 utf8"),
+        (InputMethod::Viqr, "Vie^.t nghie^ng hoa`nh"),
+    ];
+    for (method, text) in words {
+        for word in text.split(' ') {
+            let mut e = UltraFastViEngine::builder().input_method(method).build();
+            let mut anew = e.clone();
+            for key in word.chars() {
+                anew.feed(key);
+                // Switching layouts composes the word from scratch
+                assert_eq!(e.feed(key), anew.set_input_method(method), "{word} at {key}");
+            }
+        }
+    }
+}
 
-    let mut e = UltraFastViEngine::new();
-    assert_eq!(type_seq(&mut e, "ees"), "ế");
+#[test]
+fn config_changes_bust_the_render_cache() {
+    let mut e = UltraFastViEngine::new();
+    assert_eq!(e.feed_str("hoaf"), "hoà");
+    let state = e.save_state();
+    assert_eq!(e.restore_state(state.clone()), "hoà");
+
+    e.set_tone_style(crate::ToneStyle::Traditional);
+    assert_eq!(e.restore_state(state.clone()), "hòa");
+    e.set_output_form(crate::OutputForm::Nfd);
+    assert_eq!(e.restore_state(state.clone()), "ho\u{300}a");
+    e.set_output_form(crate::OutputForm::Nfc);
+    e.set_validation_level(ValidationLevel::Off);
+    assert_eq!(e.restore_state(state.clone()), "hòa");
+    assert_eq!(e.revert_raw(), "hoaf");
+    assert_eq!(e.revert_raw(), "hoaf");
+    assert_eq!(e.feed('s'), "hoafs");
+    assert_eq!(e.undo_last_key(), "hoaf");
+}
 
-    let mut e = UltraFastViEngine::new();
-    assert_eq!(type_seq(&mut e, "oos"), "ố");
+#[test]
+fn validation_levels() {
+    let mut off = UltraFastViEngine::builder().validation(ValidationLevel::Off).build();
+    for (keys, word) in [("clears", "cleá"), ("hocf", "hòc"), ("txt", "tt"), ("vieetj", "việt")] {
+        assert_eq!(off.feed_str(keys), word, "{keys}");
+        assert_eq!(off.commit(), word);
+    }
 
-    let mut e = UltraFastViEngine::new();
-    assert_eq!(type_seq(&mut e, "ows"), "ớ");
+    // Basic is the default
+    let mut basic = UltraFastViEngine::new();
+    for (keys, word) in [("clears", "clears"), ("hocf", "hocf"), ("txt", "txt"), ("kos", "kó"), ("vieet", "viêt")] {
+        assert_eq!(basic.feed_str(keys), word, "{keys}");
+        assert_eq!(basic.commit(), word);
+    }
 
-    let mut e = UltraFastViEngine::new();
-    assert_eq!(type_seq(&mut e, "uws"), "ứ");
+    let mut strict = UltraFastViEngine::builder().validation(ValidationLevel::Strict).build();
+    for (keys, word) in [("kos", "kos"), ("vieet", "vieet"), ("vieetj", "việt"), ("clears", "clears")] {
+        strict.feed_str(keys);
+        assert_eq!(strict.commit(), word, "{keys}");
+    }
 }
 
 #[test]
-fn greedy_tone_last_wins() {
-    let mut e = UltraFastViEngine::new();
-    assert_eq!(type_seq(&mut e, "asf"), "à");
+fn english_heuristic() {
+    use crate::english::looks_english;
+    for word in ["for", "just", "zone", "hello", "pizza", "class", "search", "break", "young", "print", "Street"] {
+        assert!(looks_english(word), "{word}");
+    }
+    for word in [
+        "vieejt", "nguwowif", "tieengs", "hoaf", "Ddaay", "tuowngf", "xin", "chaof", "khoongr", "quaas", "giaf",
+        "thuyeenf", "nghieeng", "trowif", "phos", "tosan", "ddeeuf", "ghees", "hoa2", "u7o7",
+    ] {
+        assert!(!looks_english(word), "{word}");
+    }
 
-    let mut e = UltraFastViEngine::new();
-    assert_eq!(type_seq(&mut e, "afsj"), "ạ");
+    let mut e = UltraFastViEngine::builder().english_detection(true).build();
+    for (keys, word) in [("for", "for"), ("just", "just"), ("hello", "hello"), ("vieejt", "việt")] {
+        assert_eq!(e.feed_str(keys), word, "{keys}");
+        assert_eq!(e.commit(), word);
+    }
+    // Off by default
+    assert_eq!(UltraFastViEngine::new().feed_str("for"), "fỏ");
 }
 
 #[test]
-fn tone_placement_two_vowels_no_coda() {
-    let mut e = UltraFastViEngine::new();
-    assert_eq!(type_seq(&mut e, "hoas"), "hoá");
+fn extended_telex_brackets() {
+    let mut e = UltraFastViEngine::builder().extended_telex(true).build();
+    for (keys, word) in [("t][ngf", "tường"), ("m]a", "mưa"), ("c[f", "cờ"), ("}", "Ư"), ("{n", "Ơn")] {
+        assert_eq!(e.feed_str(keys), word, "{keys}");
+        assert_eq!(e.commit(), word);
+    }
+    assert_eq!(crate::transform("t][ngf", InputMethod::Telex), "t][ngf");
 
+    // Off (the default) they stay punctuation
     let mut e = UltraFastViEngine::new();
-    assert_eq!(type_seq(&mut e, "hoaf"), "hoà");
+    assert_eq!(e.feed_str("m]a"), "m]a");
+    e.commit();
+    // Only Telex has them
+    let mut e = UltraFastViEngine::builder().input_method(InputMethod::Vni).extended_telex(true).build();
+    assert_eq!(e.feed_str("m]a"), "m]a");
 }
 
 #[test]
-fn tone_placement_two_vowels_with_coda() {
-    let mut e = UltraFastViEngine::new();
-    assert_eq!(type_seq(&mut e, "hoans"), "hoán");
-
-    let mut e = UltraFastViEngine::new();
-    assert_eq!(type_seq(&mut e, "hoanj"), "hoạn");
+fn key_remap_presets() {
+    use crate::{FeedResult, KeyRemap};
+
+    // `phos` and `phoos` typed with the QWERTY keys on a Dvorak layout
+    let mut e = UltraFastViEngine::builder().key_remap(KeyRemap::DVORAK).build();
+    assert_eq!(e.feed_str("ldro"), "phó");
+    assert_eq!(e.commit(), "phó");
+    assert_eq!(e.feed_str("ldrro"), "phố");
+    assert_eq!(e.commit(), "phố");
+    // Dvorak's `,` is where QWERTY has `w`
+    assert_eq!(e.feed_event(','), FeedResult::Preview("ư"));
+    e.clear();
+
+    // Left as typed, the word shows the keys the keyboard sent
+    e.feed_str("ldrr");
+    assert_eq!(e.backspace(), "pho");
+    assert_eq!(e.raw(), "ldr");
+    assert_eq!(e.revert_raw(), "ldr");
+    assert_eq!(e.feed_str("o "), "ldro ");
+    assert!(e.reopen_last_word());
+    assert_eq!(e.revert_raw(), "ldro");
+    let state = e.save_state();
+    let mut restored = UltraFastViEngine::new();
+    assert_eq!(restored.restore_state(state), "ldro");
+
+    let mut e = UltraFastViEngine::builder().key_remap(KeyRemap::AZERTY).build();
+    assert_eq!(e.feed_str("Mqis truzozngf"), "Mái trường");
+
+    let mut remap = KeyRemap::new(&[('9', 'w')]);
+    remap.set('v', 'f');
+    assert_eq!(remap.iter().collect::<Vec<_>>(), [('9', 'w'), ('V', 'F'), ('v', 'f')]);
+    assert!(KeyRemap::default().is_identity());
+    let mut e = UltraFastViEngine::builder().key_remap(remap).build();
+    assert_eq!(e.feed_str("to9v"), "tờ");
 }
 
 #[test]
-fn tone_placement_three_vowels_targets_second_vowel() {
-    let mut e = UltraFastViEngine::new();
-    assert_eq!(type_seq(&mut e, "khuya"), "khuya");
+fn tone_key_cycle() {
+    // Odd presses apply the tone, even ones cancel it and keep the key; the remove-tone
+    // key is tried on `á`
+    let toned = ["á", "à", "ả", "ã", "ạ", "a"];
+    for (method, keys) in [(InputMethod::Telex, "sfrxjz"), (InputMethod::Vni, "123450")] {
+        for (key, toned) in keys.chars().zip(toned) {
+            let mut e = UltraFastViEngine::builder().input_method(method).build();
+            e.feed('a');
+            if toned == "a" {
+                e.feed(keys.chars().next().unwrap());
+            }
+            let literal = format!("a{key}");
+            for (presses, expected) in (1..=4).zip([toned, &literal, toned, &literal]) {
+                assert_eq!(e.feed(key), expected, "{method} a + {presses} x {key}");
+            }
+        }
+    }
+}
 
+#[test]
+fn literal_oo_before_a_coda() {
     let mut e = UltraFastViEngine::new();
-    assert_eq!(type_seq(&mut e, "khuyas"), "khuýa");
+    for (keys, word) in [("xooong", "xoong"), ("sooong", "soong"), ("xooongf", "xoòng"), ("Xooong", "Xoong")] {
+        assert_eq!(e.feed_str(keys), word);
+        assert_eq!(e.commit(), word);
+    }
+    // Without a coda the triple still toggles back
+    assert_eq!(e.feed_str("booo"), "bo");
+    e.commit();
+    assert_eq!(e.feed_str("xoong"), "xông");
 }
 
 #[test]
@@ -156,435 +320,1503 @@ fn whitespace_flushes_and_resets_buffer() {
 }
 
 #[test]
-fn tone_only_input_produces_empty() {
-    let mut e = UltraFastViEngine::new();
-    // First char is treated as consonant
-    assert_eq!(type_seq(&mut e, "s"), "s");
+fn vni_basic_modifiers() {
+    assert_eq!(type_seq_vni("a6"), "â");
+    assert_eq!(type_seq_vni("a8"), "ă");
+    assert_eq!(type_seq_vni("e6"), "ê");
+    assert_eq!(type_seq_vni("o6"), "ô");
+    assert_eq!(type_seq_vni("o7"), "ơ");
+    assert_eq!(type_seq_vni("u7"), "ư");
+    assert_eq!(type_seq_vni("d9"), "đ");
+}
 
-    let mut e = UltraFastViEngine::new();
-    assert_eq!(type_seq(&mut e, "z"), "z");
+#[test]
+fn vni_modifier_digit_cycle() {
+    for (base, digit, modified) in [("d", '9', "đ"), ("a", '6', "â"), ("a", '8', "ă"), ("o", '7', "ơ")] {
+        let mut e = UltraFastViEngine::builder().input_method(InputMethod::Vni).build();
+        e.feed_str(base);
+        let literal = format!("{base}{digit}");
+        for render in [modified, &literal, modified, &literal] {
+            assert_eq!(e.feed(digit), render);
+        }
+    }
+    assert_eq!(type_seq_vni("truong777"), "trương");
 }
 
 #[test]
-fn do_not_apply_to_english() {
-    let mut e = UltraFastViEngine::new();
-    assert_eq!(type_seq(&mut e, "clear"), "clear");
+fn vni_digits_in_alphanumeric_tokens() {
+    for token in ["file1", "mp3", "x264", "utf8", "h2o"] {
+        assert_eq!(type_seq_vni(token), token);
+    }
+    assert_eq!(type_seq_vni("pho61"), "phố");
+    assert_eq!(type_seq_vni("d9uo7ng2"), "đường");
+}
 
-    let mut e = UltraFastViEngine::new();
-    assert_eq!(type_seq(&mut e, "flan"), "flan");
+#[test]
+fn vni_tones_on_modified_vowels() {
+    // a6 + 1 => ấ
+    assert_eq!(type_seq_vni("a61"), "ấ");
+    // o6 + 1 => ố
+    assert_eq!(type_seq_vni("o61"), "ố");
+    // o7 + 1 => ớ
+    assert_eq!(type_seq_vni("o71"), "ớ");
+    // u7 + 1 => ứ
+    assert_eq!(type_seq_vni("u71"), "ứ");
+    // d9 + 1 should not tone (đ is not in mapping), stays đ
+    assert_eq!(type_seq_vni("d91"), "đ");
+}
 
-    let mut e = UltraFastViEngine::new();
-    assert_eq!(type_seq(&mut e, "blob"), "blob");
+#[test]
+fn viqr_basic_modifiers() {
+    assert_eq!(type_seq_viqr("a^"), "â");
+    assert_eq!(type_seq_viqr("a("), "ă");
+    assert_eq!(type_seq_viqr("e^"), "ê");
+    assert_eq!(type_seq_viqr("o^"), "ô");
+    assert_eq!(type_seq_viqr("o+"), "ơ");
+    assert_eq!(type_seq_viqr("u+"), "ư");
+    assert_eq!(type_seq_viqr("dd"), "đ");
+}
+
+#[test]
+fn viqr_marks_after_consonants_are_punctuation() {
+    assert_eq!(type_seq_viqr("ok."), "ok.");
+    assert_eq!(type_seq_viqr("what?"), "what?");
+    let mut e = UltraFastViEngine::builder().input_method(InputMethod::Viqr).build();
+    assert_eq!(type_seq(&mut e, "'a"), "'a");
+    // A mark after a vowel is a tone; escape it to keep it literal
+    assert_eq!(type_seq_viqr("toi."), "tọi");
+    assert_eq!(type_seq_viqr("toi\\."), "toi.");
+
+    assert_eq!(
+        crate::transform("Vie^.t Nam (ok.) to^i\\.", InputMethod::Viqr),
+        "Việt Nam (ok.) tôi."
+    );
 }
 
+// ===== Comprehensive edge case tests =====
+
 #[test]
-fn special_uow_combo() {
+fn edge_double_w_various() {
+    // aww -> aw (undo ă)
     let mut e = UltraFastViEngine::new();
-    assert_eq!(type_seq(&mut e, "huow"), "hươ");
+    assert_eq!(type_seq(&mut e, "aww"), "aw");
 
+    // ddoww -> đow (undo ơ, keep đ)
     let mut e = UltraFastViEngine::new();
-    assert_eq!(type_seq(&mut e, "huows"), "hướ");
+    assert_eq!(type_seq(&mut e, "ddoww"), "đow");
 }
 
 #[test]
-fn valid_consonant_cluster() {
+fn edge_consecutive_words_via_space() {
     let mut e = UltraFastViEngine::new();
-    assert_eq!(type_seq(&mut e, "nghe"), "nghe");
+    assert_eq!(type_seq(&mut e, "vieetj"), "việt");
+    assert_eq!(e.feed(' '), "việt ");
+    assert_eq!(type_seq(&mut e, "namm"), "namm");
+}
 
+#[test]
+fn free_style_does_not_break_normal() {
+    // Normal adjacent modifiers still work
     let mut e = UltraFastViEngine::new();
-    assert_eq!(type_seq(&mut e, "nghes"), "nghé");
+    assert_eq!(type_seq(&mut e, "aas"), "ấ");
 
     let mut e = UltraFastViEngine::new();
-    assert_eq!(type_seq(&mut e, "nghees"), "nghế");
-}
+    assert_eq!(type_seq(&mut e, "ees"), "ế");
 
-#[test]
-fn regression_qu_gi_placement() {
-    // qu + a -> quá (tone on a)
     let mut e = UltraFastViEngine::new();
-    assert_eq!(type_seq(&mut e, "quas"), "quá");
+    assert_eq!(type_seq(&mut e, "oos"), "ố");
 
-    // qu + y -> quỳ (tone on y)
     let mut e = UltraFastViEngine::new();
-    assert_eq!(type_seq(&mut e, "quyf"), "quỳ");
+    assert_eq!(type_seq(&mut e, "dd"), "đ");
 
-    // qu + i -> quỉ (tone on i)
+    // Triple still toggles back
     let mut e = UltraFastViEngine::new();
-    assert_eq!(type_seq(&mut e, "quir"), "quỉ");
+    assert_eq!(type_seq(&mut e, "aaa"), "a");
 
-    // gi + a -> giá (tone on a)
     let mut e = UltraFastViEngine::new();
-    assert_eq!(type_seq(&mut e, "gias"), "giá");
+    assert_eq!(type_seq(&mut e, "eee"), "e");
 }
 
 #[test]
-fn regression_vowel_pairs() {
-    // oa -> hoà (tone on a, new style)
+fn long_vowel_run_keeps_tone() {
+    // 20 vowels: positions past 16 used to fall outside the u16 vowel mask.
     let mut e = UltraFastViEngine::new();
-    assert_eq!(type_seq(&mut e, "hoaf"), "hoà");
+    let out = type_seq(&mut e, "iuiuiuiuiuiuiuiuiuius");
+    assert_eq!(out.chars().count(), 20);
+    assert_eq!(out.chars().filter(|c| !c.is_ascii()).count(), 1);
 
-    // oe -> hoè (tone on e, new style)
+    // Only vowels beyond slot 16 besides the onset vowel.
     let mut e = UltraFastViEngine::new();
-    assert_eq!(type_seq(&mut e, "hoef"), "hoè");
+    let out = type_seq(&mut e, "ohhhhhhhhhhhhhhhais");
+    assert_eq!(out, "ohhhhhhhhhhhhhhhái");
+}
 
-    // uy -> tuỳ (tone on y, new style)
-    let mut e = UltraFastViEngine::new();
-    assert_eq!(type_seq(&mut e, "tuyf"), "tuỳ");
+#[test]
+fn key_sequences_canonical() {
+    use crate::hints::key_sequences_for;
+
+    assert_eq!(key_sequences_for('ặ', InputMethod::Telex)[0], "awj");
+    assert_eq!(key_sequences_for('ấ', InputMethod::Telex)[0], "aas");
+    assert_eq!(key_sequences_for('đ', InputMethod::Telex)[0], "dd");
+    assert_eq!(key_sequences_for('ừ', InputMethod::Telex)[0], "uwf");
+    assert_eq!(key_sequences_for('Ộ', InputMethod::Telex)[0], "Ooj");
+    assert_eq!(key_sequences_for('e', InputMethod::Telex)[0], "e");
+    assert_eq!(key_sequences_for('w', InputMethod::Telex)[0], "ww");
+
+    assert_eq!(key_sequences_for('ặ', InputMethod::Vni)[0], "a85");
+    assert_eq!(key_sequences_for('đ', InputMethod::Vni)[0], "d9");
+    assert_eq!(key_sequences_for('ở', InputMethod::Vni)[0], "o73");
+
+    assert!(key_sequences_for('€', InputMethod::Telex).is_empty());
+}
 
-    // ia -> mía (tone on i)
-    let mut e = UltraFastViEngine::new();
-    assert_eq!(type_seq(&mut e, "mias"), "mía");
+#[test]
+fn key_sequences_reproduce_char() {
+    use crate::hints::key_sequences_for;
+
+    let sample = "aăâeêioôơuưyáàảãạắằẳẵặấầẩẫậéèẻẽẹếềểễệíìỉĩịóòỏõọốồổỗộớờởỡợúùủũụứừửữựýỳỷỹỵđĐẶỨƠbw";
+    for method in [InputMethod::Telex, InputMethod::Vni, InputMethod::Viqr] {
+        for c in sample.chars() {
+            let seqs = key_sequences_for(c, method);
+            assert!(!seqs.is_empty(), "no sequence for {c}");
+            for seq in seqs {
+                let mut e = UltraFastViEngine::builder().input_method(method).build();
+                assert_eq!(type_seq(&mut e, &seq), c.to_string(), "{seq} should give {c}");
+            }
+        }
+    }
+}
 
-    // ua -> múa (tone on u)
-    let mut e = UltraFastViEngine::new();
-    assert_eq!(type_seq(&mut e, "muas"), "múa");
+fn deltas(engine: &mut UltraFastViEngine, seq: &str) -> Vec<(usize, String)> {
+    seq.chars()
+        .map(|c| {
+            let d = engine.feed_delta(c);
+            (d.backspaces, d.insert.to_string())
+        })
+        .collect()
+}
 
-    // ưa -> mứa (tone on ư)
-    let mut e = UltraFastViEngine::new();
-    assert_eq!(type_seq(&mut e, "muwas"), "mứa");
+#[test]
+fn delta_phoos() {
+    let mut e = UltraFastViEngine::new();
+    let d = deltas(&mut e, "phoos ");
+    let expected: Vec<(usize, String)> = [
+        (0, "p"),
+        (0, "h"),
+        (0, "o"),
+        (1, "ô"),
+        (1, "ố"),
+        (0, " "),
+    ]
+    .iter()
+    .map(|&(b, s)| (b, s.to_string()))
+    .collect();
+    assert_eq!(d, expected);
+
+    // Next word starts from an empty composition
+    assert_eq!(deltas(&mut e, "a"), vec![(0, "a".to_string())]);
+}
 
+#[test]
+fn delta_clear_and_fallback() {
     let mut e = UltraFastViEngine::new();
-    assert_eq!(type_seq(&mut e, "pro"), "pro");
+    let d = deltas(&mut e, "clear");
+    assert!(d.iter().all(|(b, _)| *b == 0));
+    assert_eq!(d.iter().map(|(_, s)| s.as_str()).collect::<String>(), "clear");
 
+    // Cancelling the tone of a composed word re-emits everything after the common prefix
     let mut e = UltraFastViEngine::new();
-    assert_eq!(type_seq(&mut e, "free"), "free");
+    type_seq(&mut e, "tieeng");
+    assert_eq!(e.feed_delta('s'), crate::Delta { backspaces: 3, insert: "ếng" });
+    assert_eq!(e.feed_delta('s'), crate::Delta { backspaces: 3, insert: "êngs" });
 }
 
 #[test]
-fn regression_pho_validity() {
+fn delta_asz() {
     let mut e = UltraFastViEngine::new();
-    assert_eq!(type_seq(&mut e, "phos"), "phó");
+    let d = deltas(&mut e, "asz");
+    assert_eq!(
+        d,
+        vec![(0, "a".to_string()), (1, "á".to_string()), (1, "a".to_string())]
+    );
 }
 
 #[test]
-fn regression_ui_tone_on_first_vowel() {
+fn transform_str_keeps_punctuation_and_last_word() {
     let mut e = UltraFastViEngine::new();
-    // guiwr -> gửi (tone on ư, not on i)
-    assert_eq!(type_seq(&mut e, "guiwr"), "gửi");
+    let mut out = String::new();
+    e.transform_str("Xin chaof, cacs banj!\nTooi teen laf Nam", &mut out);
+    assert_eq!(out, "Xin chào, các bạn!\nTôi tên là Nam");
+
+    // State is reset afterwards
+    assert_eq!(e.feed('a'), "a");
 }
 
 #[test]
-fn vni_basic_modifiers() {
-    assert_eq!(type_seq_vni("a6"), "â");
-    assert_eq!(type_seq_vni("a8"), "ă");
-    assert_eq!(type_seq_vni("e6"), "ê");
-    assert_eq!(type_seq_vni("o6"), "ô");
-    assert_eq!(type_seq_vni("o7"), "ơ");
-    assert_eq!(type_seq_vni("u7"), "ư");
-    assert_eq!(type_seq_vni("d9"), "đ");
+fn auto_capitalize_sentences() {
+    let text = "ddaay laf nhaf tooi owr Q.1. gaanf chowj Beens Thanhf. aam nhacj owr ddaay raats hay... khoong nhir?";
+    let mut e = UltraFastViEngine::builder().auto_capitalize(true).build();
+    let mut out = String::new();
+    e.transform_str(text, &mut out);
+    assert_eq!(out, "Đây là nhà tôi ở Q.1. gần chợ Bến Thành. Âm nhạc ở đây rất hay... không nhỉ?");
+
+    let mut config = e.config().clone();
+    config.ellipsis_ends_sentence = true;
+    e.set_config(config);
+    out.clear();
+    e.transform_str(text, &mut out);
+    assert!(out.ends_with("rất hay... Không nhỉ?"), "{out}");
+
+    // While typing, the preview is capitalized and a reopened word keeps its capital
+    let mut e = UltraFastViEngine::builder().auto_capitalize(true).build();
+    assert_eq!(e.feed_str("xin chaof. ddi"), "Xin chào. Đi");
+    assert_eq!(e.feed(' '), "Đi ");
+    assert!(e.reopen_last_word());
+    assert_eq!(e.feed('!'), "Đi!");
+    assert_eq!(e.feed_str(" nhes"), " Nhé");
+    assert_eq!(e.feed_str(". clear"), "Nhé. Clear");
+
+    // A word committed through feed_into moves the sentence on like feed does
+    let mut e = UltraFastViEngine::builder().auto_capitalize(true).build();
+    assert_eq!(e.feed('a'), "A");
+    let mut out = String::new();
+    e.feed_into(' ', &mut out).unwrap();
+    assert_eq!(out, "A ");
+    assert_eq!(e.feed('a'), "a");
+
+    // Off by default
+    assert_eq!(UltraFastViEngine::new().feed_str("a. b"), "a. b");
 }
 
 #[test]
-fn vni_basic_tones() {
-    assert_eq!(type_seq_vni("a1"), "á");
-    assert_eq!(type_seq_vni("a2"), "à");
-    assert_eq!(type_seq_vni("a3"), "ả");
-    assert_eq!(type_seq_vni("a4"), "ã");
-    assert_eq!(type_seq_vni("a5"), "ạ");
+fn y_orthography_respells_bare_i_y() {
+    use crate::YOrthography;
+
+    let cases = [
+        ("lys", "lý", "lí"),
+        ("kix", "kỹ", "kĩ"),
+        ("kyx", "kỹ", "kĩ"),
+        ("quis", "quý", "quí"),
+        ("Quys", "Quý", "Quí"),
+        ("mix", "mỹ", "mĩ"),
+        ("ty", "ty", "ti"),
+    ];
+    for (keys, prefer_y, prefer_i) in cases {
+        for (form, word) in [(YOrthography::PreferY, prefer_y), (YOrthography::PreferI, prefer_i)] {
+            let mut e = UltraFastViEngine::builder().y_orthography(form).build();
+            e.feed_str(keys);
+            assert_eq!(e.commit(), word, "{keys} {form:?}");
+        }
+    }
+    // Only applied at commit
+    let mut e = UltraFastViEngine::builder().y_orthography(YOrthography::PreferY).build();
+    assert_eq!(e.feed_str("lis"), "lí");
+    assert_eq!(e.feed(' '), "lý ");
+    // Anything but a bare i/y after those onsets stays as typed
+    for keys in ["in", "yeeu", "tuy", "thi", "khis", "quyts", "ys"] {
+        for form in [YOrthography::PreferY, YOrthography::PreferI] {
+            let mut e = UltraFastViEngine::builder().y_orthography(form).build();
+            let preview = e.feed_str(keys).to_owned();
+            assert_eq!(e.commit(), preview, "{keys} {form:?}");
+        }
+    }
+    // As typed by default
+    assert_eq!(crate::transform("kix suw", InputMethod::Telex), "kĩ sư");
 }
 
 #[test]
-fn vni_tone_removal() {
-    // a1 -> á, then 0 -> a
-    assert_eq!(type_seq_vni("a10"), "a");
-    // a0 -> a
-    assert_eq!(type_seq_vni("a0"), "a");
+fn transform_free_function() {
+    assert_eq!(crate::transform("vieejt nam.", InputMethod::Telex), "việt nam.");
+    assert_eq!(crate::transform("Vie65t Nam (pho61)", InputMethod::Vni), "Việt Nam (phố)");
+    assert_eq!(crate::transform("clear  free\t", InputMethod::Telex), "clear  free\t");
+    assert_eq!(crate::transform("", InputMethod::Telex), "");
 }
 
 #[test]
-fn vni_tones_on_modified_vowels() {
-    // a6 + 1 => ấ
-    assert_eq!(type_seq_vni("a61"), "ấ");
-    // o6 + 1 => ố
-    assert_eq!(type_seq_vni("o61"), "ố");
-    // o7 + 1 => ớ
-    assert_eq!(type_seq_vni("o71"), "ớ");
-    // u7 + 1 => ứ
-    assert_eq!(type_seq_vni("u71"), "ứ");
-    // d9 + 1 should not tone (đ is not in mapping), stays đ
-    assert_eq!(type_seq_vni("d91"), "đ");
+fn decision_composing_vs_fallback() {
+    use crate::Decision;
+
+    let mut e = UltraFastViEngine::new();
+    assert_eq!(e.decision(), Decision::Idle);
+    type_seq(&mut e, "nhan");
+    assert_eq!(e.decision(), Decision::ComposingUnchanged);
+    assert_eq!(e.feed_result('s').decision, Decision::Composing);
+    assert_eq!(e.feed_result(' ').decision, Decision::Idle);
+
+    let mut e = UltraFastViEngine::new();
+    let r = e.feed_result('c');
+    assert_eq!((r.output, r.decision), ("c", Decision::ComposingUnchanged));
+    type_seq(&mut e, "lear");
+    assert_eq!(e.decision(), Decision::Fallback);
 }
 
 #[test]
-fn tone_on_modified_vowel_oi() {
-    // mơí -> mới (tone on ơ, not i)
-    let mut e = UltraFastViEngine::new();
-    assert_eq!(type_seq(&mut e, "mowis"), "mới");
+fn decision_through_backspace() {
+    use crate::Decision;
+
+    // clears -> cle: the cl onset keeps it a fallback
+    let mut e = UltraFastViEngine::new();
+    type_seq(&mut e, "clears");
+    e.backspace();
+    e.backspace();
+    assert_eq!(e.backspace(), "cle");
+    assert_eq!(e.decision(), Decision::Fallback);
+
+    // clea -> cl is still a fallback, c alone revives the word
+    let mut e = UltraFastViEngine::new();
+    type_seq(&mut e, "clea");
+    e.backspace();
+    assert_eq!(e.backspace(), "cl");
+    assert_eq!(e.decision(), Decision::Fallback);
+    assert_eq!(e.backspace(), "c");
+    assert_eq!(e.decision(), Decision::ComposingUnchanged);
+    assert_eq!(e.feed('a'), "ca");
+    assert_eq!(e.feed('s'), "cá");
+    assert_eq!(e.backspace(), "ca");
+    assert_eq!(e.backspace(), "c");
+    assert_eq!(e.backspace(), "");
+    assert_eq!(e.decision(), Decision::Idle);
+    assert_eq!(e.backspace(), "");
 }
 
 #[test]
-fn tone_on_modified_vowel_eu() {
-    // nêú -> nếu (tone on ê, not u)
-    let mut e = UltraFastViEngine::new();
-    assert_eq!(type_seq(&mut e, "neeus"), "nếu");
+fn retone_paragraph_between_styles() {
+    use crate::{ToneStyle, retone_text};
+
+    let modern = "Hoà bình, thuỷ thủ quý toán. HOÀ!";
+    let traditional = "Hòa bình, thủy thủ quý toán. HÒA!";
+    assert_eq!(retone_text(modern, ToneStyle::Traditional), traditional);
+    assert_eq!(retone_text(traditional, ToneStyle::Modern), modern);
+    assert_eq!(retone_text(modern, ToneStyle::Modern), modern);
+
+    // Non-Vietnamese and style-independent words are left alone
+    let other = "café naïve người khoẻ, 123";
+    assert_eq!(retone_text(other, ToneStyle::Traditional), "café naïve người khỏe, 123");
 }
 
 #[test]
-fn double_tone_key_undoes_tone() {
-    // tess -> test (double s undoes the tone, s becomes literal)
+fn modifier_after_tone_orderings() {
+    // The circumflex arrives after the tone has already been rendered on the plain vowel
     let mut e = UltraFastViEngine::new();
-    assert_eq!(type_seq(&mut e, "tess"), "tes");
+    assert_eq!(type_seq(&mut e, "nangs"), "náng");
+    assert_eq!(e.feed('a'), "nấng");
 
-    // teff -> tef
     let mut e = UltraFastViEngine::new();
-    assert_eq!(type_seq(&mut e, "teff"), "tef");
+    assert_eq!(type_seq(&mut e, "vietj"), "viẹt");
+    assert_eq!(e.feed('e'), "việt");
 
-    // terr -> ter
     let mut e = UltraFastViEngine::new();
-    assert_eq!(type_seq(&mut e, "terr"), "ter");
+    assert_eq!(type_seq(&mut e, "tois"), "tói");
+    assert_eq!(e.feed('o'), "tối");
 
-    // texx -> tex
     let mut e = UltraFastViEngine::new();
-    assert_eq!(type_seq(&mut e, "texx"), "tex");
+    assert_eq!(type_seq(&mut e, "mois"), "mói");
+    assert_eq!(e.feed('w'), "mới");
 
-    // tejj -> tej
-    let mut e = UltraFastViEngine::new();
-    assert_eq!(type_seq(&mut e, "tejj"), "tej");
+    assert_eq!(type_seq_vni("a16"), "ấ");
+    assert_eq!(type_seq_vni("o17"), "ớ");
+    assert_eq!(type_seq_vni("u27"), "ừ");
 }
 
 #[test]
-fn double_w_undoes_modification() {
-    // showw -> show (double w undoes ơ)
-    let mut e = UltraFastViEngine::new();
-    assert_eq!(type_seq(&mut e, "showw"), "show");
+fn retone_from_base_for_every_toned_vowel() {
+    use crate::tone::map_vowel_with_tone;
+
+    let rows = [
+        "aáàảãạ", "ăắằẳẵặ", "âấầẩẫậ", "eéèẻẽẹ", "êếềểễệ", "iíìỉĩị",
+        "oóòỏõọ", "ôốồổỗộ", "ơớờởỡợ", "uúùủũụ", "ưứừửữự", "yýỳỷỹỵ",
+    ];
+    for row in rows {
+        let forms: Vec<char> = row.chars().collect();
+        for &already in &forms {
+            for (tone, &expected) in forms.iter().enumerate() {
+                assert_eq!(map_vowel_with_tone(already, tone as u8), expected);
+            }
+        }
+    }
+}
+
+#[test]
+fn custom_mode_remapped_tone_key() {
+    use crate::modes::ModeBuilder;
+
+    // q is the sắc key instead of s
+    let words = ["phoos", "vieets", "tieengs", "nguowis", "DDaas", "chaos", "xas"];
+    for w in words {
+        let mut stock = UltraFastViEngine::new();
+        let expected = type_seq(&mut stock, w);
+
+        let mut e = UltraFastViEngine::new();
+        e.set_custom_mode(ModeBuilder::new(InputMethod::Telex).remove_key(b's').tone_key(b'q', 1).build());
+        let remapped: String = w.chars().map(|c| match c {
+            's' => 'q',
+            'S' => 'Q',
+            c => c,
+        }).collect();
+        assert_eq!(type_seq(&mut e, &remapped), expected, "{w}");
+        assert_eq!(e.input_method(), InputMethod::Telex);
+        // s is plain content now
+        assert_eq!(type_seq(&mut e, " as"), format!("{expected} as"));
+        // double press cancels and keeps the key that was typed
+        assert_eq!(type_seq(&mut e, " teqq"), "as teq");
+    }
+}
+
+#[test]
+fn custom_mode_disable_z_and_remap_nang() {
+    use crate::modes::ModeBuilder;
 
-    // oww -> ow
+    let mode = ModeBuilder::new(InputMethod::Telex)
+        .remove_key(b'z')
+        .remove_key(b'j')
+        .tone_key(b'.', 5)
+        .build();
     let mut e = UltraFastViEngine::new();
-    assert_eq!(type_seq(&mut e, "oww"), "ow");
+    e.set_custom_mode(mode.clone());
+    // `z` is plain content now, which no Vietnamese word ends in
+    assert_eq!(type_seq(&mut e, "asz"), "asz");
 
-    // uww -> uw
     let mut e = UltraFastViEngine::new();
-    assert_eq!(type_seq(&mut e, "uww"), "uw");
+    e.set_custom_mode(mode);
+    assert_eq!(type_seq(&mut e, "vieet."), "việt");
 }
 
 #[test]
-fn consonant_only_no_duplication() {
-    // txt should stay txt (no duplication)
-    let mut e = UltraFastViEngine::new();
-    assert_eq!(type_seq(&mut e, "txt"), "txt");
+fn tone_style_modern_vs_traditional() {
+    use crate::ToneStyle;
+
+    let cases = [
+        ("hoaf", "hoà", "hòa"),
+        ("hoef", "hoè", "hòe"),
+        ("tuyf", "tuỳ", "tùy"),
+        ("khoer", "khoẻ", "khỏe"),
+        ("thuyr", "thuỷ", "thủy"),
+        // A coda keeps the tone on the second vowel in both styles
+        ("hoans", "hoán", "hoán"),
+        ("tuaanf", "tuần", "tuần"),
+        ("khoerm", "khoẻm", "khoẻm"),
+        // qu is an onset glide, not part of the pair
+        ("quyf", "quỳ", "quỳ"),
+    ];
+    for (seq, modern, traditional) in cases {
+        let mut e = UltraFastViEngine::new();
+        assert_eq!(e.tone_style(), ToneStyle::Modern);
+        assert_eq!(type_seq(&mut e, seq), modern, "{seq}");
+
+        let mut e = UltraFastViEngine::new();
+        e.set_tone_style(ToneStyle::Traditional);
+        assert_eq!(type_seq(&mut e, seq), traditional, "{seq}");
+    }
+}
 
-    // sx should stay sx
-    let mut e = UltraFastViEngine::new();
-    assert_eq!(type_seq(&mut e, "sx"), "sx");
+#[test]
+fn keystroke_effects() {
+    use crate::KeystrokeEffect::*;
+
+    let mut e = UltraFastViEngine::new();
+    let effects: Vec<_> = "vieetj1 aaz".chars().map(|c| e.feed_result(c).effect).collect();
+    assert_eq!(
+        effects,
+        vec![
+            Appended, Appended, Appended, Recomposed, Appended, Recomposed, Appended, Committed,
+            Appended, Recomposed, NoEffect,
+        ]
+    );
+    assert!(!Appended.consumed());
+    assert!(Recomposed.consumed());
+    assert!(NoEffect.consumed());
+    assert!(!Committed.consumed());
 }
 
 #[test]
-fn double_tone_then_continue() {
-    // vieetj -> việt (double e makes ê, then tone j)
+fn revert_raw_locks_word_until_delimiter() {
+    use crate::Decision;
+
     let mut e = UltraFastViEngine::new();
-    assert_eq!(type_seq(&mut e, "vieetj"), "việt");
+    assert_eq!(type_seq(&mut e, "vieejt"), "việt");
+    assert_eq!(e.revert_raw(), "vieejt");
+    assert_eq!(e.decision(), Decision::Fallback);
+    // Tone keys are not consumed any more
+    assert_eq!(e.feed('s'), "vieejts");
+    assert_eq!(e.feed('z'), "vieejtsz");
+    assert_eq!(e.backspace(), "vieejts");
+    assert_eq!(e.feed(' '), "vieejts ");
+
+    // The next word composes normally
+    assert_eq!(type_seq(&mut e, "as"), "á");
+
+    // Nothing to revert
+    let mut e = UltraFastViEngine::new();
+    assert_eq!(e.revert_raw(), "");
+    assert_eq!(type_seq(&mut e, "as"), "á");
 }
 
 #[test]
-fn tone_placement_oi_pair() {
-    // đời -> ddowif
+fn tone_variants_open_stop_and_fallback() {
     let mut e = UltraFastViEngine::new();
-    assert_eq!(type_seq(&mut e, "ddowif"), "đời");
+    type_seq(&mut e, "Hoaf");
+    assert_eq!(e.tone_variants(), vec!["Hoa", "Hoá", "Hoà", "Hoả", "Hoã", "Hoạ"]);
 
-    // tối -> toois
     let mut e = UltraFastViEngine::new();
-    assert_eq!(type_seq(&mut e, "toois"), "tối");
+    type_seq(&mut e, "nuwowcs");
+    assert_eq!(e.tone_variants(), vec!["nước", "nược"]);
 
-    // lối -> loois
     let mut e = UltraFastViEngine::new();
-    assert_eq!(type_seq(&mut e, "loois"), "lối");
+    type_seq(&mut e, "sachs");
+    assert_eq!(e.tone_variants(), vec!["sách", "sạch"]);
+
+    let mut e = UltraFastViEngine::new();
+    type_seq(&mut e, "clear");
+    assert!(e.tone_variants().is_empty());
+
+    let e = UltraFastViEngine::new();
+    assert!(e.tone_variants().is_empty());
 }
 
 #[test]
-fn tone_placement_eu_pair() {
-    // nếu -> neeus
+fn delimiters_commit_words() {
     let mut e = UltraFastViEngine::new();
-    assert_eq!(type_seq(&mut e, "neeus"), "nếu");
+    assert_eq!(type_seq(&mut e, "phos,"), "phó,");
+    assert_eq!(type_seq(&mut e, "phoos,"), "phố,");
 
-    // kều -> keeuf (tone f = huyền)
     let mut e = UltraFastViEngine::new();
-    assert_eq!(type_seq(&mut e, "keeuf"), "kều");
+    assert_eq!(type_seq(&mut e, "(nghees)"), "(nghế)");
 
-    // kểu -> keeur (tone r = hỏi)
     let mut e = UltraFastViEngine::new();
-    assert_eq!(type_seq(&mut e, "keeur"), "kểu");
+    assert_eq!(type_seq(&mut e, "vieejt, ddi!"), "việt, đi!");
+
+    // Custom set: hyphen commits, comma no longer does
+    let mut e = UltraFastViEngine::new();
+    e.set_delimiters(&['-']);
+    assert_eq!(type_seq(&mut e, "tuwf-tuwf"), "từ-từ");
+    e.clear();
+    type_seq(&mut e, "as,");
+    assert_ne!(e.decision(), crate::Decision::Idle);
+
+    // VIQR marks keep working mid-word; a leading '.' still passes through
+    let mut e = UltraFastViEngine::builder().input_method(InputMethod::Viqr).build();
+    assert_eq!(type_seq(&mut e, ".a. a?"), ".ạ ả");
 }
 
-// ===== Comprehensive edge case tests =====
+#[test]
+fn engines_convert_concurrently_on_threads() {
+    let inputs = [
+        ("Vieejt Nam", InputMethod::Telex, "Việt Nam"),
+        ("d9u7o7ng2 xa", InputMethod::Vni, "đường xa"),
+        ("to^i ddi", InputMethod::Viqr, "tôi đi"),
+        ("hoaf binhf", InputMethod::Telex, "hoà bình"),
+    ];
+    let handles: Vec<_> = inputs
+        .iter()
+        .map(|&(text, method, expected)| {
+            let mut e = UltraFastViEngine::builder().input_method(method).build();
+            std::thread::spawn(move || {
+                for _ in 0..100 {
+                    let mut out = String::new();
+                    e.transform_str(text, &mut out);
+                    assert_eq!(out, expected);
+                }
+            })
+        })
+        .collect();
+    for h in handles {
+        h.join().unwrap();
+    }
+}
 
 #[test]
-fn edge_double_tone_various_positions() {
-    // Double tone at end of word with vowel
+fn raw_and_rendered_track_feed() {
     let mut e = UltraFastViEngine::new();
-    assert_eq!(type_seq(&mut e, "bass"), "bas");
+    assert_eq!((e.raw(), e.rendered()), ("", ""));
 
-    // Double tone in middle then more chars
-    let mut e = UltraFastViEngine::new();
-    assert_eq!(type_seq(&mut e, "tesstt"), "testt");
+    let out = e.feed('v').to_string();
+    assert_eq!((e.raw(), e.rendered()), ("v", out.as_str()));
+    type_seq(&mut e, "ieejt");
+    assert_eq!((e.raw(), e.rendered()), ("vieejt", "việt"));
 
-    // zz should also cancel
-    let mut e = UltraFastViEngine::new();
-    assert_eq!(type_seq(&mut e, "azz"), "az");
+    e.feed(' ');
+    assert_eq!((e.raw(), e.rendered()), ("", "việt "));
+    e.feed('a');
+    assert_eq!((e.raw(), e.rendered()), ("a", "a"));
+
+    e.clear();
+    assert_eq!((e.raw(), e.rendered()), ("", ""));
 }
 
 #[test]
-fn edge_double_w_various() {
-    // aww -> aw (undo ă)
+fn undo_last_key_steps_back() {
     let mut e = UltraFastViEngine::new();
-    assert_eq!(type_seq(&mut e, "aww"), "aw");
+    assert_eq!(type_seq(&mut e, "huows"), "hướ");
+    assert_eq!(e.undo_last_key(), "hươ");
+    assert_eq!(e.undo_last_key(), "huo");
 
-    // ddoww -> đow (undo ơ, keep đ)
     let mut e = UltraFastViEngine::new();
-    assert_eq!(type_seq(&mut e, "ddoww"), "đow");
+    assert_eq!(type_seq(&mut e, "ass"), "as");
+    assert_eq!(e.undo_last_key(), "á");
+    assert_eq!(e.undo_last_key(), "a");
+    assert_eq!(e.undo_last_key(), "");
+    assert_eq!(e.undo_last_key(), "");
+    assert_eq!(e.decision(), crate::Decision::Idle);
 }
 
 #[test]
-fn edge_english_words_passthrough() {
-    // Common English words that contain tone keys
+fn feed_str_returns_whole_segment() {
     let mut e = UltraFastViEngine::new();
-    assert_eq!(type_seq(&mut e, "stress"), "stress");
+    assert_eq!(e.feed_str("xin chaof"), "xin chào");
+    assert_eq!(e.raw(), "chaof");
+    assert_eq!(e.rendered(), "chào");
 
-    let mut e = UltraFastViEngine::new();
-    assert_eq!(type_seq(&mut e, "jazz"), "jaz");
+    // Same state as feeding key by key
+    let mut manual = UltraFastViEngine::new();
+    for c in "xin chaof".chars() {
+        manual.feed(c);
+    }
+    assert_eq!(manual.raw(), e.raw());
+    assert_eq!(manual.rendered(), e.rendered());
 
-    // Pure consonant sequences
-    let mut e = UltraFastViEngine::new();
-    assert_eq!(type_seq(&mut e, "txt"), "txt");
+    // The word in progress is continued and reported whole
+    assert_eq!(e.feed_str(" ban."), "chào ban.");
+    assert_eq!(e.feed_str(""), "");
+}
 
-    let mut e = UltraFastViEngine::new();
-    assert_eq!(type_seq(&mut e, "rx"), "rx");
+#[test]
+fn feed_event_sequence() {
+    use crate::FeedResult::{Committed, PassThrough, Preview};
+
+    let mut e = UltraFastViEngine::new();
+    let events: Vec<String> = "chaof ban.".chars().map(|c| format!("{:?}", e.feed_event(c))).collect();
+    let expected = [
+        Preview("c"),
+        Preview("ch"),
+        Preview("cha"),
+        Preview("chao"),
+        Preview("chào"),
+        Committed { word: "chào", delimiter: ' ' },
+        Preview("b"),
+        Preview("ba"),
+        Preview("ban"),
+        Committed { word: "ban", delimiter: '.' },
+    ];
+    let expected: Vec<String> = expected.iter().map(|r| format!("{r:?}")).collect();
+    assert_eq!(events, expected);
+
+    // Digits cannot start a Telex word; a delimiter with no word is passed through
+    assert_eq!(e.feed_event('1'), PassThrough('1'));
+    assert_eq!(e.feed_event(' '), PassThrough(' '));
+    assert_eq!(e.decision(), crate::Decision::Idle);
+    // but VNI consumes them
+    e.set_input_method(InputMethod::Vni);
+    assert_eq!(e.feed_event('a'), Preview("a"));
+    assert_eq!(e.feed_event('1'), Preview("á"));
+}
 
-    let mut e = UltraFastViEngine::new();
-    assert_eq!(type_seq(&mut e, "sx"), "sx");
+#[test]
+fn process_drives_an_editing_session() {
+    use crate::FeedResult::{CommittedThen, Forward, PassThrough, Preview};
+    use crate::Key::{self, Backspace, Char, Enter, Escape, Tab};
+
+    let mut e = UltraFastViEngine::new();
+    let mut keys = |keys: &[Key]| -> Vec<String> { keys.iter().map(|&k| format!("{:?}", e.process(k))).collect() };
+    let typed = |s: &str| s.chars().map(Char).collect::<Vec<_>>();
+    let expect = |results: &[crate::FeedResult]| -> Vec<String> { results.iter().map(|r| format!("{r:?}")).collect() };
+
+    // Type, take back the tone, retype it
+    assert_eq!(keys(&typed("vieetj")).last(), expect(&[Preview("việt")]).last());
+    assert_eq!(keys(&[Backspace]), expect(&[Preview("viêt")]));
+    assert_eq!(keys(&typed("s")), expect(&[Preview("viết")]));
+    // Escape shows the keys; further keys are literal until the word ends
+    assert_eq!(keys(&[Escape]), expect(&[Preview("vieets")]));
+    assert_eq!(keys(&typed("j")), expect(&[Preview("vieetsj")]));
+    assert_eq!(keys(&typed(" ")), expect(&[crate::FeedResult::Committed { word: "vieetsj", delimiter: ' ' }]));
+    // Backspacing a whole word empties the preview; then the host deletes on its own
+    assert_eq!(keys(&typed("as")), expect(&[Preview("a"), Preview("á")]));
+    assert_eq!(keys(&[Backspace, Backspace, Backspace]), expect(&[Preview("a"), Preview(""), Forward(Backspace)]));
+    // Enter and Tab commit and are handed back
+    keys(&typed("chaof"));
+    assert_eq!(keys(&[Enter, Enter]), expect(&[CommittedThen { word: "chào", key: Enter }, Forward(Enter)]));
+    keys(&typed("ban"));
+    assert_eq!(keys(&[Tab]), expect(&[CommittedThen { word: "ban", key: Tab }]));
+    assert_eq!(keys(&[Escape]), expect(&[Forward(Escape)]));
+    assert_eq!(keys(&typed("1")), expect(&[PassThrough('1')]));
 }
 
 #[test]
-fn edge_modified_vowel_tone_placement() {
-    // ươi -> tone on ơ (second in ươ pair)
-    let mut e = UltraFastViEngine::new();
-    assert_eq!(type_seq(&mut e, "huowis"), "hưới");
+fn other_keys_leave_the_word_behind() {
+    use crate::FeedResult::{CommittedThen, Forward, Preview};
+    use crate::Key::{Char, Other};
 
-    // ươn -> tone on ơ
     let mut e = UltraFastViEngine::new();
-    assert_eq!(type_seq(&mut e, "huowns"), "hướn");
+    for c in "hoa".chars() {
+        e.process(Char(c));
+    }
+    assert_eq!(e.process(Other), CommittedThen { word: "hoa", key: Other });
+    // The caret may be anywhere now: a tone key starts a new word instead of reaching back
+    assert!(!e.reopen_last_word());
+    assert_eq!(e.process(Char('s')), Preview("s"));
+
+    e.clear();
+    e.feed_str("hoa ");
+    assert_eq!(e.process(Other), Forward(Other));
+    assert!(!e.reopen_last_word());
+}
 
-    // ươ alone -> tone on ơ
+#[test]
+fn restore_english_on_commit() {
     let mut e = UltraFastViEngine::new();
-    assert_eq!(type_seq(&mut e, "huows"), "hướ");
+    assert_eq!(type_seq(&mut e, "expression "), "expression ");
+    assert_eq!(type_seq(&mut e, "vieejt "), "việt ");
+    assert_eq!(type_seq(&mut e, "Nguowif "), "Người ");
+    assert_eq!(crate::transform("expression vieejt", InputMethod::Telex), "expression việt");
 
-    // âu -> tone on â
+    // The live preview is untouched; only the commit is restored
     let mut e = UltraFastViEngine::new();
-    assert_eq!(type_seq(&mut e, "daauf"), "dầu");
+    e.restore_english_on_commit(false);
+    let preview = type_seq(&mut e, "expression");
+    assert_ne!(preview, "expression");
+    assert_eq!(type_seq(&mut e, " "), format!("{preview} "));
+}
 
-    // ây -> tone on â
-    let mut e = UltraFastViEngine::new();
-    assert_eq!(type_seq(&mut e, "daays"), "dấy");
+#[test]
+fn restore_english_keeps_lone_d() {
+    let mut e = UltraFastViEngine::new();
+    assert_eq!(type_seq(&mut e, "dd "), "đ ");
+    assert_eq!(type_seq(&mut e, "DD "), "Đ ");
+    assert_eq!(type_seq_vni("d9 "), "đ ");
+    assert_eq!(type_seq_vni("d999"), "đ");
+    assert_eq!(crate::transform("d999", InputMethod::Vni), "đ");
+    assert_eq!(crate::transform("dd ddi", InputMethod::Telex), "đ đi");
 }
 
 #[test]
-fn edge_consecutive_words_via_space() {
-    let mut e = UltraFastViEngine::new();
-    assert_eq!(type_seq(&mut e, "vieetj"), "việt");
-    assert_eq!(e.feed(' '), "việt ");
-    assert_eq!(type_seq(&mut e, "namm"), "namm");
+fn macros_expand_at_commit() {
+    let mut e = UltraFastViEngine::new();
+    e.add_macro("vn", "Việt Nam");
+    e.add_macro("ko", "không");
+    assert_eq!(type_seq(&mut e, "vn "), "Việt Nam ");
+    assert_eq!(type_seq(&mut e, "Ko, ko."), "Không, không.");
+    assert_eq!(type_seq(&mut e, "KO "), "Không ");
+
+    // Only whole words fire, and never mid-word
+    assert_eq!(type_seq(&mut e, "kos "), "kó ");
+    assert_eq!(type_seq(&mut e, "vn"), "vn");
+    e.clear();
+
+    // A valid Vietnamese word still expands once the user opted in
+    e.add_macro("hoa", "hoa hồng");
+    assert_eq!(type_seq(&mut e, "hoa "), "hoa hồng ");
+    assert!(e.remove_macro("HOA"));
+    assert!(!e.remove_macro("hoa"));
+    assert_eq!(type_seq(&mut e, "hoa "), "hoa ");
+
+    assert_eq!(e.feed_str("ko"), "ko");
+    e.clear();
+    let mut out = String::new();
+    e.transform_str("vn ko vieejt", &mut out);
+    assert_eq!(out, "Việt Nam không việt");
 }
 
 #[test]
-fn edge_single_char_tone_keys() {
-    // Single tone key chars should pass through as-is
-    let mut e = UltraFastViEngine::new();
-    assert_eq!(type_seq(&mut e, "s"), "s");
+fn reopen_last_word_for_editing() {
+    let mut e = UltraFastViEngine::new();
+    assert!(!e.reopen_last_word());
+
+    type_seq(&mut e, "hoa ");
+    assert!(e.reopen_last_word());
+    assert_eq!(e.rendered(), "hoa");
+    assert_eq!(e.feed('s'), "hoá");
+    // Already reopened
+    assert!(!e.reopen_last_word());
+
+    type_seq(&mut e, " vieejt ");
+    assert!(e.reopen_last_word());
+    assert_eq!(e.backspace(), "việ");
+
+    // A second delimiter or a clear forgets the word
+    type_seq(&mut e, " hoa  ");
+    assert!(!e.reopen_last_word());
+    type_seq(&mut e, "hoa ");
+    e.clear();
+    assert!(!e.reopen_last_word());
+}
 
+#[test]
+fn commit_flushes_without_delimiter() {
     let mut e = UltraFastViEngine::new();
-    assert_eq!(type_seq(&mut e, "f"), "f");
+    assert_eq!(e.commit(), "");
 
-    let mut e = UltraFastViEngine::new();
-    assert_eq!(type_seq(&mut e, "r"), "r");
+    type_seq(&mut e, "chaof");
+    assert_eq!(e.commit(), "chào");
+    assert_eq!(e.decision(), crate::Decision::Idle);
+    assert_eq!(e.commit(), "");
 
-    let mut e = UltraFastViEngine::new();
-    assert_eq!(type_seq(&mut e, "x"), "x");
+    // Cancelled tone stays cancelled
+    type_seq(&mut e, "ass");
+    assert_eq!(e.commit(), "as");
+    assert_eq!(type_seq(&mut e, "as"), "á");
+    assert_eq!(e.commit(), "á");
 
-    let mut e = UltraFastViEngine::new();
-    assert_eq!(type_seq(&mut e, "j"), "j");
+    type_seq(&mut e, "expression");
+    assert_eq!(e.commit(), "expression");
+}
 
-    let mut e = UltraFastViEngine::new();
-    assert_eq!(type_seq(&mut e, "z"), "z");
+#[test]
+fn preview_tracks_latest_feed() {
+    let mut e = UltraFastViEngine::new();
+    assert_eq!(e.preview(), "");
+    for (key, expected) in "vieejt".chars().zip(["v", "vi", "vie", "viê", "việ", "việt"]) {
+        let out = e.feed(key).to_string();
+        assert_eq!(e.preview(), out);
+        assert_eq!(e.preview(), expected);
+        assert_eq!(e.preview(), expected);
+    }
+    e.backspace();
+    assert_eq!(e.preview(), "việ");
+    e.feed(' ');
+    assert_eq!(e.preview(), "");
+    e.feed('a');
+    assert_eq!(e.preview(), "a");
+    e.clear();
+    assert_eq!(e.preview(), "");
 }
 
 #[test]
-fn edge_common_vietnamese_words() {
-    // Common words that exercise multiple features
-    let mut e = UltraFastViEngine::new();
-    assert_eq!(type_seq(&mut e, "xins"), "xín");
+fn transform_word_across_rayon_threads() {
+    use rayon::prelude::*;
+
+    let words = [
+        ("vieejt", InputMethod::Telex, "việt"),
+        ("nguowif", InputMethod::Telex, "người"),
+        ("d9u7o7ng2", InputMethod::Vni, "đường"),
+        ("to^i", InputMethod::Viqr, "tôi"),
+        ("expression", InputMethod::Telex, "expression"),
+    ];
+    let results: Vec<(String, &str)> = (0..10_000)
+        .into_par_iter()
+        .map(|i| {
+            let (raw, method, expected) = words[i % words.len()];
+            (crate::transform_word(raw, method), expected)
+        })
+        .collect();
+    for (got, expected) in results {
+        assert_eq!(got, expected);
+    }
+}
 
-    let mut e = UltraFastViEngine::new();
-    assert_eq!(type_seq(&mut e, "chaof"), "chào");
+#[test]
+fn cloned_engine_continues_independently() {
+    let mut e = UltraFastViEngine::builder().input_method(InputMethod::Vni).build();
+    type_seq(&mut e, "vie6");
+    let mut c = e.clone();
+    assert_eq!(c.feed('5'), "việ");
+    assert_eq!(e.feed('t'), "viêt");
+    assert_eq!(c.input_method(), InputMethod::Vni);
+}
 
-    let mut e = UltraFastViEngine::new();
-    assert_eq!(type_seq(&mut e, "ddeepj"), "đệp");
+#[test]
+fn save_and_restore_state() {
+    let mut e = UltraFastViEngine::new();
+    type_seq(&mut e, "huow");
+    let snapshot = e.save_state();
+    assert_eq!(snapshot.raw(), "huow");
+
+    type_seq(&mut e, "ng ddi");
+    assert_eq!(e.restore_state(snapshot.clone()), "hươ");
+    assert_eq!(e.feed('s'), "hướ");
+
+    // Cancelled tones and the input method come back too
+    let mut vni = UltraFastViEngine::builder().input_method(InputMethod::Vni).build();
+    type_seq(&mut vni, "a11");
+    let state = vni.save_state();
+    let mut fresh = UltraFastViEngine::new();
+    assert_eq!(fresh.restore_state(state), vni.rendered());
+    assert_eq!(fresh.input_method(), InputMethod::Vni);
+    assert_eq!(fresh.feed('b'), vni.feed('b'));
+}
 
-    let mut e = UltraFastViEngine::new();
-    assert_eq!(type_seq(&mut e, "nawm"), "năm");
+#[test]
+fn builder_configures_engine() {
+    let mut e = UltraFastViEngine::builder()
+        .input_method(InputMethod::Telex)
+        .tone_style(crate::ToneStyle::Traditional)
+        .delimiters(&['-'])
+        .restore_english_on_commit(false)
+        .build();
+    assert_eq!(e.tone_style(), crate::ToneStyle::Traditional);
+    assert_eq!(type_seq(&mut e, "hoaf-"), "hòa-");
+    assert_ne!(type_seq(&mut e, "expression "), "expression ");
+
+    let mut e = UltraFastViEngine::default();
+    e.feed_str("vieet");
+    let debug = format!("{e:?}");
+    assert!(debug.contains("raw: \"vieet\""), "{debug}");
+    assert!(debug.contains("input_method: Telex"), "{debug}");
+    assert!(debug.len() < 300, "{debug}");
+}
 
+#[test]
+fn pasted_precomposed_letters_keep_composing() {
     let mut e = UltraFastViEngine::new();
-    assert_eq!(type_seq(&mut e, "nawms"), "nắm");
+    assert_eq!(e.feed_str("việt"), "việt");
+    assert_eq!(e.feed('z'), "viêt");
+    assert_eq!(e.commit(), "viêt");
 
-    // không -> khoongf (ô + huyền = ồ)
-    let mut e = UltraFastViEngine::new();
-    assert_eq!(type_seq(&mut e, "khoongf"), "khồng");
+    assert_eq!(e.feed_str("việt "), "việt ");
+    assert_eq!(e.feed_str("ĐƯỜNG"), "ĐƯỜNG");
+    assert_eq!(e.feed('s'), "ĐƯỚNG");
+    e.clear();
 
-    // được -> dduowcj
-    let mut e = UltraFastViEngine::new();
-    assert_eq!(type_seq(&mut e, "dduowcj"), "được");
+    // Typed on after a pasted prefix
+    assert_eq!(e.feed_str("ngươif"), "người");
+    assert_eq!(e.feed_str(" "), "người ");
 
-    // người -> nguowif
+    let mut e = UltraFastViEngine::builder().input_method(InputMethod::Vni).build();
+    assert_eq!(e.feed_str("việt"), "việt");
+    assert_eq!(e.feed_str("0 "), "viêt ");
+
+    // Anything else is kept as typed
     let mut e = UltraFastViEngine::new();
-    assert_eq!(type_seq(&mut e, "nguowif"), "người");
+    assert_eq!(e.feed_str("aa€s"), "aa€s");
+    assert_eq!(e.feed_str(" "), "aa€s ");
+    assert_eq!(e.feed_str("as"), "á");
 }
 
+// The heapless raw buffer holds only 32 keys; see tests/heapless.rs
+#[cfg(not(feature = "heapless"))]
 #[test]
-fn free_style_modifier_bubbling() {
-    // ee modifier with vowel in between: neues -> nếu
-    let mut e = UltraFastViEngine::new();
-    assert_eq!(type_seq(&mut e, "neues"), "nếu");
+fn words_over_the_key_limit_are_kept_whole() {
+    let token = "xinchaoaaasssdddfffjjjrrrwwwooowwweeeaa";
+    assert!(token.len() > UltraFastViEngine::MAX_WORD_KEYS);
+    let mut e = UltraFastViEngine::new();
+    assert_eq!(e.feed_str(token), token);
+    assert_eq!(e.decision(), crate::Decision::Fallback);
+    assert_eq!(e.commit(), token);
+    assert_eq!(e.feed_str(&format!("{token} vieejt")), format!("{token} việt"));
+    assert_eq!(crate::transform(token, InputMethod::Telex), token);
+}
 
-    // aa modifier with vowel in between: naos -> nâó? No — naos: n,a,o -> nao + tone s
-    // Actually: nao with free-style aa: naoas -> n,a,o,a -> bubble a next to a -> n,a,a,o -> nâo + s -> nấo
-    let mut e = UltraFastViEngine::new();
-    assert_eq!(type_seq(&mut e, "naoas"), "nấo");
+#[cfg(not(feature = "heapless"))]
+#[test]
+fn smaller_engines_keep_shorter_words_as_typed() {
+    let mut e = UltraFastViEngine::<16, 48>::sized(crate::EngineConfig::default());
+    assert_eq!(e.feed_str("nghieeng "), "nghiêng ");
+    // 17 keys: past the limit of this engine, not of the default one
+    let token = "ddaaaaaaaaaaaaaas";
+    assert_eq!(e.feed_str(token), token);
+    assert_ne!(crate::transform(token, InputMethod::Telex), token);
+}
+
+// Inputs of the kinds fuzz/fuzz_targets/feed.rs throws at the engine
+#[test]
+fn feed_survives_arbitrary_keys() {
+    let inputs = [
+        "\u{0}\u{7}\t\r\n\u{7f}",
+        "a\u{301}\u{302}\u{31b}\u{323}s",
+        "đươờngg😀jf中\u{200b}x",
+        "ưươơooowwwaaaddd\u{7f}\u{7f}\u{7f}ss",
+        "vieejt\u{7f}nam1234567890zzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzz",
+        "Ẹ̈̃ÀẠaaaa\\^(+`?~",
+    ];
+    for method in [InputMethod::Telex, InputMethod::Vni] {
+        let config = crate::EngineConfig {
+            input_method: method,
+            ..Default::default()
+        };
+        for input in inputs {
+            let mut e = UltraFastViEngine::with_config(config.clone());
+            let mut small = UltraFastViEngine::<8, 12>::sized(config.clone());
+            for key in input.chars() {
+                e.feed(key);
+                small.feed(key);
+            }
+            e.commit();
+            small.commit();
+            crate::transform(input, method);
+        }
+    }
+}
+
+#[test]
+fn map_vowel_with_tone_covers_both_cases() {
+    use crate::tone::{map_vowel_with_tone, to_upper_vi};
+    let bases = ['a', 'ă', 'â', 'e', 'ê', 'i', 'o', 'ô', 'ơ', 'u', 'ư', 'y'];
+    for base in bases {
+        let forms: Vec<char> = (0..=5).map(|t| map_vowel_with_tone(base, t)).collect();
+        for (from, &toned) in forms.iter().enumerate() {
+            for (to, &expected) in forms.iter().enumerate() {
+                assert_eq!(map_vowel_with_tone(toned, to as u8), expected, "{toned} {to}");
+                let upper = to_upper_vi(toned);
+                assert_ne!(upper, toned);
+                assert_eq!(map_vowel_with_tone(upper, to as u8), to_upper_vi(expected), "{upper} {to}");
+            }
+            // Removing the tone gives the base back
+            assert_eq!(map_vowel_with_tone(to_upper_vi(toned), 0), to_upper_vi(base), "{from}");
+        }
+    }
+    assert_eq!(map_vowel_with_tone('A', 1), 'Á');
+    assert_eq!(map_vowel_with_tone('Ự', 0), 'Ư');
+    assert_eq!(map_vowel_with_tone('B', 1), 'B');
+    assert_eq!(map_vowel_with_tone('Đ', 1), 'Đ');
+}
+
+#[test]
+fn tone_utilities_round_trip() {
+    use crate::tone::{Tone, add_tone, base_vowel, is_vietnamese_vowel, strip_tone, tone_of};
+    let vowels = "aăâeêioôơuưyAĂÂEÊIOÔƠUƯY";
+    for c in vowels.chars() {
+        assert!(is_vietnamese_vowel(c));
+        assert_eq!(tone_of(c), None);
+        assert_eq!(strip_tone(c), c);
+        assert!("aeiouyAEIOUY".contains(base_vowel(c)), "{c}");
+        for tone in Tone::ALL {
+            let toned = add_tone(c, tone);
+            assert_ne!(toned, c);
+            assert_eq!(tone_of(toned), Some(tone));
+            assert_eq!(strip_tone(toned), strip_tone(c));
+            assert_eq!(base_vowel(toned), base_vowel(c));
+            assert_eq!(toned.is_uppercase(), c.is_uppercase());
+            assert!(is_vietnamese_vowel(toned));
+            for other in Tone::ALL {
+                assert_eq!(add_tone(toned, other), add_tone(c, other));
+            }
+        }
+    }
+    for c in ['b', 'đ', 'Đ', 'w', '1', ' ', '中'] {
+        assert!(!is_vietnamese_vowel(c));
+        assert_eq!(add_tone(c, Tone::Sac), c);
+        assert_eq!(strip_tone(c), c);
+        assert_eq!(tone_of(c), None);
+        assert_eq!(base_vowel(c), c);
+    }
+}
 
-    // oo modifier with vowel in between: noies -> nối
+#[test]
+fn nfd_output() {
     let mut e = UltraFastViEngine::new();
-    assert_eq!(type_seq(&mut e, "noios"), "nối");
+    assert_eq!(e.config().output_form, crate::OutputForm::Nfc);
+    assert_eq!(e.feed_str("vieejt"), "vi\u{1ec7}t");
+    e.clear();
+
+    let mut e = UltraFastViEngine::builder().output_form(crate::OutputForm::Nfd).build();
+    assert_eq!(e.feed_str("vieejt "), "vie\u{323}\u{302}t ");
+    assert_eq!(e.feed_str("nguwowfi "), "ngu\u{31b}o\u{31b}\u{300}i ");
+    assert_eq!(e.feed_str("quoocs "), "quo\u{302}\u{301}c ");
+    assert_eq!(e.feed_str("DDAWNGJ "), "\u{110}A\u{323}\u{306}NG ");
+    assert_eq!(e.feed_str("clear"), "clear");
+    assert_eq!(e.tone_variants(), Vec::<String>::new());
+    e.clear();
+    e.feed_str("ma");
+    assert_eq!(e.tone_variants()[5], "ma\u{323}");
 
-    // Free-style ee: tieengs -> tiếng
     let mut e = UltraFastViEngine::new();
-    assert_eq!(type_seq(&mut e, "tieengs"), "tiếng");
+    e.set_output_form(crate::OutputForm::Nfd);
+    let mut out = String::new();
+    e.transform_str("hoaf binhf", &mut out);
+    assert_eq!(out, "hoa\u{300} bi\u{300}nh");
+}
 
-    // Free-style with w: moiws -> mới
+#[test]
+fn nfd_input_folds_into_the_word() {
     let mut e = UltraFastViEngine::new();
-    assert_eq!(type_seq(&mut e, "moiws"), "mới");
+    for word in ["vie\u{323}\u{302}t", "vie\u{302}\u{323}t"] {
+        assert_eq!(e.feed_str(word), "việt");
+        assert_eq!(e.commit(), "việt");
+    }
+    assert_eq!(e.feed_str("hoa\u{300} "), "hoà ");
+    assert_eq!(e.feed_str("NGU\u{31b}O\u{31b}\u{300}I"), "NGƯỜI");
+    assert_eq!(e.feed('z'), "NGƯƠI");
+    e.clear();
+
+    let mut e = UltraFastViEngine::builder().input_method(InputMethod::Vni).build();
+    assert_eq!(e.feed_str("vie\u{323}\u{302}t "), "việt ");
 
-    // dd modifier with consonant in between is NOT expected (dd must be adjacent)
-    // dnd -> just dnd (d is first char = consonant, n, d = second d but first d is at pos 0)
+    // Other marks, or a mark with no letter to go on, are left untouched
     let mut e = UltraFastViEngine::new();
-    assert_eq!(type_seq(&mut e, "dand"), "đan");
+    assert_eq!(e.feed_str("noe\u{308}l "), "noe\u{308}l ");
+    assert_eq!(e.feed_str("\u{301}a"), "\u{301}a");
 }
 
 #[test]
-fn free_style_does_not_break_normal() {
-    // Normal adjacent modifiers still work
-    let mut e = UltraFastViEngine::new();
-    assert_eq!(type_seq(&mut e, "aas"), "ấ");
+fn remove_diacritics_folds_vietnamese_letters() {
+    use crate::fold::{remove_diacritics, remove_diacritics_into};
+
+    assert_eq!(remove_diacritics("ĐƯỜNG Nguyễn trãi, Quận 1"), "DUONG Nguyen trai, Quan 1");
+    assert_eq!(remove_diacritics("Ặ ặ Ơ ợ ỹ Ỵ"), "A a O o y Y");
+    // Latin letters shared with other languages fold too; the rest is kept
+    assert_eq!(remove_diacritics("café, Zoë, garçon"), "cafe, Zoë, garçon");
+    assert_eq!(remove_diacritics("phở 😀 ngon"), "pho 😀 ngon");
+
+    let mut out = String::from("> ");
+    remove_diacritics_into("Việt Nam", &mut out).unwrap();
+    assert_eq!(out, "> Viet Nam");
+}
 
+#[test]
+fn impossible_finals_stay_as_typed() {
     let mut e = UltraFastViEngine::new();
-    assert_eq!(type_seq(&mut e, "ees"), "ế");
+    for word in ["loadj", "filmr", "worldf", "modj", "havs"] {
+        assert_eq!(e.feed_str(word), word);
+        assert_eq!(e.commit(), word);
+    }
+    for (keys, word) in [("hoans", "hoán"), ("nghieeng", "nghiêng"), ("vieejt", "việt"), ("khoangr", "khoảng")] {
+        assert_eq!(e.feed_str(keys), word);
+        assert_eq!(e.commit(), word);
+    }
+}
 
+#[test]
+fn stop_finals_only_take_sac_and_nang() {
     let mut e = UltraFastViEngine::new();
-    assert_eq!(type_seq(&mut e, "oos"), "ố");
+    for (keys, word) in [("hocj", "học"), ("hocs", "hóc"), ("hocf", "hocf"), ("mapr", "mapr"), ("hofc", "hofc")] {
+        assert_eq!(e.feed_str(keys), word);
+        assert_eq!(e.commit(), word);
+    }
+    e.set_input_method(InputMethod::Vni);
+    for (keys, word) in [("hoc5", "học"), ("hoc2", "hoc2"), ("map3", "map3"), ("vie6t4", "vie6t4")] {
+        assert_eq!(e.feed_str(keys), word);
+        assert_eq!(e.commit(), word);
+    }
+
+    let mut e = UltraFastViEngine::builder().stop_final_tone(crate::StopFinalTone::Ignore).build();
+    assert_eq!(e.feed_str("hocf "), "hoc ");
+    assert_eq!(e.feed_str("vieejtx"), "viêt");
+    assert_eq!(e.feed('j'), "việt");
+}
 
+#[test]
+fn english_onsets_stay_as_typed() {
     let mut e = UltraFastViEngine::new();
-    assert_eq!(type_seq(&mut e, "dd"), "đ");
+    for word in ["strees", "threes", "swiftj", "scripts", "splashs", "shrimps", "whys", "smiles", "twitters"] {
+        assert_eq!(e.feed_str(word), word);
+        assert_eq!(e.commit(), word);
+    }
+    // The `w` shorthand still works after t and s
+    for (keys, word) in [("nghees", "nghế"), ("twf", "từ"), ("swar", "sửa"), ("tres", "tré")] {
+        assert_eq!(e.feed_str(keys), word);
+        assert_eq!(e.commit(), word);
+    }
+}
 
-    // Triple still toggles back
+#[test]
+fn tone_on_three_vowel_nuclei() {
+    let words = ["người", "chuyện", "khuỷu", "rượu", "ngoài", "khuýa", "yếu", "quyết", "khuấy"];
+    let telex = ["nguoiwf", "chuyeenj", "khuyur", "ruwowuj", "ngoaif", "khuyas", "yeeus", "quyeets", "khuaays"];
+    let vni = ["ngu7o7i2", "chuye6n5", "khuyu3", "ru7o7u5", "ngoai2", "khuya1", "ye6u1", "quye6t1", "khua6y1"];
+    for (method, keys) in [(InputMethod::Telex, telex), (InputMethod::Vni, vni)] {
+        let mut e = UltraFastViEngine::builder().input_method(method).build();
+        for (keys, word) in keys.into_iter().zip(words) {
+            assert_eq!(e.feed_str(keys), word, "{method} {keys}");
+            assert_eq!(e.commit(), word);
+        }
+    }
+}
+
+#[test]
+fn horn_after_uou_marks_uo() {
     let mut e = UltraFastViEngine::new();
-    assert_eq!(type_seq(&mut e, "aaa"), "a");
+    for (keys, word) in [("ruouw", "rươu"), ("ruouwj", "rượu"), ("Ruouwj", "Rượu"), ("huouw", "hươu")] {
+        assert_eq!(e.feed_str(keys), word);
+        assert_eq!(e.commit(), word);
+    }
+    assert_eq!(type_seq_vni("ruou75"), "rượu");
+}
 
+#[test]
+fn tone_on_uye_nucleus() {
+    let words = ["thuyền", "quyển", "chuyện", "khuyên", "khuyến", "nguyễn"];
+    let telex = ["thuyeenf", "quyeenr", "chuyeenj", "khuyeen", "khuyeens", "nguyeenx"];
+    let vni = ["thuye6n2", "quye6n3", "chuye6n5", "khuye6n", "khuye6n1", "nguye64n"];
+    for (method, keys) in [(InputMethod::Telex, telex), (InputMethod::Vni, vni)] {
+        let mut e = UltraFastViEngine::builder().input_method(method).build();
+        for (keys, word) in keys.into_iter().zip(words) {
+            assert_eq!(e.feed_str(keys), word, "{method} {keys}");
+            assert_eq!(e.commit(), word);
+        }
+    }
+    // On ê before its circumflex is typed, too
     let mut e = UltraFastViEngine::new();
-    assert_eq!(type_seq(&mut e, "eee"), "e");
+    assert_eq!(e.feed_str("thuyenf"), "thuyèn");
+    assert_eq!(e.feed('e'), "thuyền");
+}
+
+#[test]
+fn tone_on_uo_nuclei() {
+    let words = ["thuở", "quở", "buổi", "cuối", "chuỗi"];
+    let telex = ["thuowr", "quowr", "buoori", "cuoois", "chuooix"];
+    let vni = ["thuo73", "quo73", "buo6i3", "cuo6i1", "chuo6i4"];
+    for (method, keys) in [(InputMethod::Telex, telex), (InputMethod::Vni, vni)] {
+        let mut e = UltraFastViEngine::builder().input_method(method).build();
+        for (keys, word) in keys.into_iter().zip(words) {
+            assert_eq!(e.feed_str(keys), word, "{method} {keys}");
+            assert_eq!(e.commit(), word);
+        }
+    }
+}
+
+#[test]
+fn qu_glide_never_takes_the_tone() {
+    let words = ["quốc", "quần", "quặng", "quỳnh", "quyết", "quở", "quầy"];
+    let telex = ["quoocs", "quaanf", "quawngj", "quynhf", "quyeets", "quowr", "quaayf"];
+    let vni = ["quo6c1", "qua6n2", "qua8ng5", "quy2nh", "quye6t1", "quo73", "qua6y2"];
+    for (method, keys) in [(InputMethod::Telex, telex), (InputMethod::Vni, vni)] {
+        let mut e = UltraFastViEngine::builder().input_method(method).build();
+        for (keys, word) in keys.into_iter().zip(words) {
+            assert_eq!(e.feed_str(keys), word, "{method} {keys}");
+            assert_eq!(e.commit(), word);
+        }
+    }
+    let mut e = UltraFastViEngine::builder().tone_style(crate::ToneStyle::Traditional).build();
+    assert_eq!(e.feed_str("quys quas"), "quý quá");
+}
+
+#[test]
+fn gi_onset_or_nucleus() {
+    let words = ["gì", "gìn", "giữ", "giường", "giêng", "giặt", "giỏi"];
+    let telex = ["gif", "ginf", "giuwx", "giuwowngf", "gieeng", "giawtj", "gioir"];
+    let vni = ["gi2", "gi2n", "giu74", "giu7o7ng2", "gie6ng", "gia8t5", "gioi3"];
+    for (method, keys) in [(InputMethod::Telex, telex), (InputMethod::Vni, vni)] {
+        let mut e = UltraFastViEngine::builder().input_method(method).build();
+        for (keys, word) in keys.into_iter().zip(words) {
+            assert_eq!(e.feed_str(keys), word, "{method} {keys}");
+            assert_eq!(e.commit(), word);
+        }
+    }
+}
+
+#[test]
+fn input_method_parses_and_displays() {
+    for &method in InputMethod::ALL {
+        assert_eq!(format!("{method}").parse(), Ok(method));
+    }
+    assert_eq!("VNI".parse(), Ok(InputMethod::Vni));
+    assert_eq!("Telex".parse(), Ok(InputMethod::Telex));
+    assert_eq!(format!("{:>6}", InputMethod::Vni), "   vni");
+
+    let err = "vnix".parse::<InputMethod>().unwrap_err();
+    assert_eq!(err, crate::ParseInputMethodError);
+    assert_eq!(err.to_string(), "unsupported input method (use telex|vni|viqr|auto)");
+    assert!("".parse::<InputMethod>().is_err());
+}
+
+#[cfg(feature = "serde")]
+mod serde_round_trip {
+    use crate::{InputMethod, ToneStyle, UltraFastViEngine};
+
+    #[test]
+    fn input_method_and_style_names_are_stable() {
+        assert_eq!(serde_json::to_string(&InputMethod::Viqr).unwrap(), "\"viqr\"");
+        assert_eq!(serde_json::to_string(&ToneStyle::Traditional).unwrap(), "\"traditional\"");
+        for m in [InputMethod::Telex, InputMethod::Vni, InputMethod::Viqr] {
+            let json = serde_json::to_string(&m).unwrap();
+            assert_eq!(serde_json::from_str::<InputMethod>(&json).unwrap(), m);
+        }
+    }
+
+    #[test]
+    fn unknown_input_method_is_an_error() {
+        let err = serde_json::from_str::<InputMethod>("\"vnii\"").unwrap_err();
+        assert!(err.to_string().contains("unknown variant `vnii`"), "{err}");
+    }
+
+    #[test]
+    fn engine_config_round_trip() {
+        let mut e = UltraFastViEngine::builder()
+            .input_method(InputMethod::Viqr)
+            .delimiters(&[',', '-'])
+            .key_remap(crate::KeyRemap::AZERTY)
+            .build();
+        e.add_macro("vn", "Việt Nam");
+        let json = serde_json::to_string(e.config()).unwrap();
+        assert!(json.contains(r#""input_method":"viqr""#), "{json}");
+        assert!(json.contains(r#""delimiters":",-""#), "{json}");
+        assert!(json.contains(r#""key_remap":"AQQAWZZWaqqawzzw""#), "{json}");
+        let back: crate::EngineConfig = serde_json::from_str(&json).unwrap();
+        assert_eq!(&back, e.config());
+
+        // Missing fields keep their defaults
+        let partial: crate::EngineConfig = serde_json::from_str(r#"{"tone_style":"traditional"}"#).unwrap();
+        assert_eq!(partial.tone_style, ToneStyle::Traditional);
+        assert_eq!(partial.input_method, InputMethod::Telex);
+    }
+
+    #[test]
+    fn engine_state_round_trip() {
+        let mut e = UltraFastViEngine::builder().input_method(InputMethod::Vni).build();
+        e.feed_str("d9u7o7ng");
+        let json = serde_json::to_string(&e.save_state()).unwrap();
+        assert_eq!(json, r#"{"raw":"d9u7o7ng","raw_locked":false,"input_method":"vni"}"#);
+
+        let mut restored = UltraFastViEngine::new();
+        restored.restore_state(serde_json::from_str(&json).unwrap());
+        assert_eq!(restored.feed('2'), e.feed('2'));
+    }
+}
+
+#[test]
+fn config_changes_rerender_immediately() {
+    use crate::{EngineConfig, ToneStyle};
+
+    let mut e = UltraFastViEngine::with_config(EngineConfig::default());
+    assert_eq!(e.config(), &EngineConfig::default());
+    assert_eq!(type_seq(&mut e, "hoaf"), "hoà");
+
+    let mut config = e.config().clone();
+    config.tone_style = ToneStyle::Traditional;
+    assert_eq!(e.set_config(config.clone()), "hòa");
+
+    e.clear();
+    assert_eq!(type_seq(&mut e, "moiw"), "mơi");
+    config.w_bubbling = false;
+    assert_ne!(e.set_config(config.clone()), "mơi");
+
+    e.clear();
+    assert_eq!(type_seq(&mut e, "hoa2"), "hoa2");
+    config.input_method = InputMethod::Vni;
+    assert_eq!(e.set_config(config.clone()), "hòa");
+    assert_eq!(e.input_method(), InputMethod::Vni);
+
+    config.delimiters = crate::Delimiters::new(&['-']);
+    config.restore_english_on_commit = false;
+    e.set_config(config);
+    assert_eq!(type_seq(&mut e, "-a1,"), "hòa-á,");
+}
+
+#[cfg(feature = "wasm")]
+#[test]
+fn wasm_engine_deltas() {
+    use crate::wasm::{WasmDelta, WasmEngine};
+
+    fn apply(text: &mut String, WasmDelta { backspaces, insert }: WasmDelta) {
+        for _ in 0..backspaces {
+            text.pop();
+        }
+        text.push_str(&insert);
+    }
+
+    assert!(WasmEngine::new("dvorak").is_err());
+    let mut e = WasmEngine::new("telex").unwrap();
+    let mut text = String::new();
+    for key in ["t", "o", "o", "i", "Shift", " "] {
+        apply(&mut text, e.feed(key));
+    }
+    assert_eq!(text, "tôi ");
+
+    // The commit patches the preview the host shows, not the whole word
+    for c in "expression".chars() {
+        apply(&mut text, e.feed(c.encode_utf8(&mut [0; 4])));
+    }
+    assert_ne!(text, "tôi expression");
+    apply(&mut text, e.commit());
+    assert_eq!(text, "tôi expression");
+
+    e.set_method("vni").unwrap();
+    e.feed("a");
+    e.feed("1");
+    assert_eq!(e.commit(), WasmDelta { backspaces: 0, insert: String::new() });
+}
+
+#[cfg(feature = "ffi")]
+#[test]
+fn ffi_feed_through_c_abi() {
+    use crate::ffi::*;
+    use core::ffi::{CStr, c_char};
+
+    let engine = uvie_engine_new();
+    assert!(!engine.is_null());
+    let mut out = [0 as c_char; 16];
+    unsafe {
+        let mut len = 0;
+        for c in "vieejt".chars() {
+            len = uvie_engine_feed(engine, c as u32, out.as_mut_ptr(), out.len());
+        }
+        assert_eq!(CStr::from_ptr(out.as_ptr()).to_str().unwrap(), "việt");
+        assert_eq!(len, "việt".len());
+
+        // Too small: reports the size needed, the render can be fetched afterwards
+        let mut tiny = [0 as c_char; 4];
+        let needed = uvie_engine_feed(engine, 's' as u32, tiny.as_mut_ptr(), tiny.len());
+        assert_eq!(needed, "viết".len());
+        assert_eq!(tiny[0], 0);
+        assert_eq!(uvie_engine_rendered(engine, out.as_mut_ptr(), out.len()), needed);
+        assert_eq!(CStr::from_ptr(out.as_ptr()).to_str().unwrap(), "viết");
+
+        assert_eq!(uvie_engine_set_method(engine, 7), -1);
+        assert_eq!(uvie_engine_set_method(engine, UVIE_METHOD_VNI), 0);
+        uvie_engine_clear(engine);
+        uvie_engine_feed(engine, 'a' as u32, out.as_mut_ptr(), out.len());
+        uvie_engine_feed(engine, '1' as u32, out.as_mut_ptr(), out.len());
+        assert_eq!(CStr::from_ptr(out.as_ptr()).to_str().unwrap(), "á");
+        assert_eq!(uvie_engine_feed(engine, 0xD800, out.as_mut_ptr(), out.len()), "á".len());
+
+        assert_eq!(uvie_engine_feed(core::ptr::null_mut(), 'a' as u32, out.as_mut_ptr(), out.len()), 0);
+        uvie_engine_free(engine);
+        uvie_engine_free(core::ptr::null_mut());
+    }
+}
+
+#[test]
+fn transform_vi_iterator_matches_batch() {
+    use crate::TransformViExt;
+
+    for input in ["vieejt nam ", "tooi ddi hocj", "", "  (ddaay), ok?"] {
+        let lazy: String = input.chars().transform_vi(InputMethod::Telex).collect();
+        assert_eq!(lazy, crate::transform(input, InputMethod::Telex), "{input:?}");
+    }
+
+    // Nothing of a word is yielded before it is finished
+    let mut it = "vieejt nam".chars().transform_vi(InputMethod::Telex);
+    assert_eq!(it.by_ref().take(5).collect::<String>(), "việt ");
+    assert_eq!(it.collect::<String>(), "nam");
+}
+
+#[test]
+fn vi_writer_handles_awkward_chunks() {
+    use crate::ViWriter;
+    use std::io::Write;
+
+    let input = "Vieejt Nam – ddeejp, tooi ddi hocj".as_bytes();
+    let expected = crate::transform("Vieejt Nam – ddeejp, tooi ddi hocj", InputMethod::Telex);
+    // Every split point, including inside the 3-byte dash and inside words
+    for split in 0..=input.len() {
+        let mut w = ViWriter::new(Vec::new(), InputMethod::Telex);
+        w.write_all(&input[..split]).unwrap();
+        w.write_all(&input[split..]).unwrap();
+        let out = w.into_inner().unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), expected, "split at {split}");
+    }
+
+    // Byte by byte, with the word held back until a delimiter or flush
+    let mut w = ViWriter::new(Vec::new(), InputMethod::Telex);
+    for b in b"chaof ban" {
+        w.write_all(&[*b]).unwrap();
+    }
+    w.flush().unwrap();
+    assert_eq!(String::from_utf8(w.into_inner().unwrap()).unwrap(), "chào ban");
+
+    // Invalid bytes pass through and end the word
+    let mut w = ViWriter::new(Vec::new(), InputMethod::Telex);
+    w.write_all(b"aa\xffaa\xe1\x80").unwrap();
+    assert_eq!(w.into_inner().unwrap(), b"\xc3\xa2\xff\xc3\xa2\xe1\x80");
+    let mut w = ViWriter::new(Vec::new(), InputMethod::Telex);
+    w.write_all(b"\xe1\x80").unwrap();
+    w.write_all(b"aa").unwrap();
+    assert_eq!(w.into_inner().unwrap(), b"\xe1\x80\xc3\xa2");
+}
+
+#[test]
+fn feed_into_matches_feed() {
+    for (method, text) in [
+        (InputMethod::Telex, "Vieejt Nam, tooi ddi hocj. expression asss"),
+        (InputMethod::Vni, "Vie65t Nam d9u7o7ng2"),
+        (InputMethod::Viqr, "Vie^.t (ok.) to^i\\."),
+    ] {
+        let mut a = UltraFastViEngine::builder().input_method(method).build();
+        let mut b = a.clone();
+        a.add_macro("ok", "được");
+        b.add_macro("ok", "được");
+        for c in text.chars() {
+            let mut out = String::new();
+            b.feed_into(c, &mut out).unwrap();
+            assert_eq!(out, a.feed(c), "{text:?} at {c:?}");
+        }
+    }
 }
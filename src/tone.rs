@@ -6,6 +6,33 @@ pub fn is_vowel_unicode(c: char) -> bool {
     )
 }
 
+/// Uppercases a (possibly toned/modified) Vietnamese letter, e.g. `ệ` -> `Ệ`, `đ` -> `Đ`.
+#[inline]
+pub fn to_upper_vi(c: char) -> char {
+    // Vietnamese letters sit in blocks where the uppercase is a fixed offset away, so
+    // only other chars need the Unicode tables
+    match c as u32 {
+        0..=0x7f => c.to_ascii_uppercase(),
+        0xe0..=0xfe if c != '÷' => char::from_u32(c as u32 - 0x20).unwrap_or(c),
+        0x1ea1..=0x1ef9 if c as u32 & 1 == 1 => char::from_u32(c as u32 - 1).unwrap_or(c),
+        _ => match c {
+            'ă' => 'Ă',
+            'đ' => 'Đ',
+            'ĩ' => 'Ĩ',
+            'ũ' => 'Ũ',
+            'ơ' => 'Ơ',
+            'ư' => 'Ư',
+            _ => c.to_uppercase().next().unwrap_or(c),
+        },
+    }
+}
+
+/// Returns vowel `c` carrying `tone` (0 = none, 1-5 = sắc, huyền, hỏi, ngã, nặng).
+///
+/// `c` may already carry any tone: the result is always rebuilt from its untoned
+/// base, so re-toning a previously rendered letter never stacks or keeps stale marks.
+/// Uppercase vowels stay uppercase (`('Â', 1)` → `Ấ`); letters that are not vowels are
+/// returned unchanged.
 pub fn map_vowel_with_tone(c: char, tone: u8) -> char {
     let base_id: Option<usize> = match c {
         'a' | 'á' | 'à' | 'ả' | 'ã' | 'ạ' => Some(0),
@@ -24,6 +51,14 @@ pub fn map_vowel_with_tone(c: char, tone: u8) -> char {
     };
 
     let Some(id) = base_id else {
+        // Uppercase vowels go through their lowercase row of the same table
+        if c.is_uppercase()
+            && let Some(lower) = c.to_lowercase().next()
+            && lower != c
+            && tone_vowel_index(lower).is_some()
+        {
+            return to_upper_vi(map_vowel_with_tone(lower, tone));
+        }
         return c;
     };
 
@@ -31,6 +66,339 @@ pub fn map_vowel_with_tone(c: char, tone: u8) -> char {
     TONE_VOWELS[id][t]
 }
 
+/// One of the five marked tones; the unmarked tone (ngang) is `None` in [`tone_of`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Tone {
+    /// Acute: `á`
+    Sac = 1,
+    /// Grave: `à`
+    Huyen = 2,
+    /// Hook above: `ả`
+    Hoi = 3,
+    /// Tilde: `ã`
+    Nga = 4,
+    /// Dot below: `ạ`
+    Nang = 5,
+}
+
+impl Tone {
+    pub const ALL: [Tone; 5] = [Tone::Sac, Tone::Huyen, Tone::Hoi, Tone::Nga, Tone::Nang];
+}
+
+/// `c` carrying `tone` in place of any tone it had; non-vowels are returned unchanged.
+///
+/// ```
+/// use uvie::tone::{Tone, add_tone};
+/// assert_eq!(add_tone('ơ', Tone::Huyen), 'ờ');
+/// assert_eq!(add_tone('Á', Tone::Nang), 'Ạ');
+/// ```
+pub fn add_tone(c: char, tone: Tone) -> char {
+    map_vowel_with_tone(c, tone as u8)
+}
+
+/// `c` without its tone, keeping breve, circumflex and horn.
+///
+/// ```
+/// use uvie::tone::strip_tone;
+/// assert_eq!(strip_tone('ệ'), 'ê');
+/// assert_eq!(strip_tone('Ự'), 'Ư');
+/// ```
+pub fn strip_tone(c: char) -> char {
+    map_vowel_with_tone(c, 0)
+}
+
+/// The tone `c` carries, if it is a vowel with one.
+///
+/// ```
+/// use uvie::tone::{Tone, tone_of};
+/// assert_eq!(tone_of('ẫ'), Some(Tone::Nga));
+/// assert_eq!(tone_of('â'), None);
+/// ```
+pub fn tone_of(c: char) -> Option<Tone> {
+    let (_, tone) = tone_vowel_index(to_lower_vi(c))?;
+    Tone::ALL.get((tone as usize).checked_sub(1)?).copied()
+}
+
+/// The plain Latin vowel under `c`, without tone, breve, circumflex or horn, in the
+/// case of `c`; other chars are returned unchanged.
+///
+/// ```
+/// use uvie::tone::base_vowel;
+/// assert_eq!(base_vowel('ớ'), 'o');
+/// assert_eq!(base_vowel('Ặ'), 'A');
+/// ```
+pub fn base_vowel(c: char) -> char {
+    let Some((row, _)) = tone_vowel_index(to_lower_vi(c)) else {
+        return c;
+    };
+    let base = match row {
+        0..=2 => 'a',
+        3 | 4 => 'e',
+        5 => 'i',
+        6..=8 => 'o',
+        9 | 10 => 'u',
+        _ => 'y',
+    };
+    if c.is_uppercase() { base.to_ascii_uppercase() } else { base }
+}
+
+/// Whether `c` is one of the 12 Vietnamese vowels, in either case and with any tone.
+///
+/// ```
+/// use uvie::tone::is_vietnamese_vowel;
+/// assert!(is_vietnamese_vowel('Ở'));
+/// assert!(!is_vietnamese_vowel('đ'));
+/// ```
+pub fn is_vietnamese_vowel(c: char) -> bool {
+    tone_vowel_index(to_lower_vi(c)).is_some()
+}
+
+/// Writes `c` decomposed (NFD) if it is a vowel with diacritics, otherwise as is.
+pub(crate) fn write_decomposed<W: core::fmt::Write>(c: char, out: &mut W) -> core::fmt::Result {
+    let Some((row, tone)) = tone_vowel_index(to_lower_vi(c)) else {
+        return out.write_char(c);
+    };
+    out.write_char(base_vowel(c))?;
+    let modifier = match row {
+        1 => Some('\u{306}'),
+        2 | 4 | 7 => Some('\u{302}'),
+        8 | 10 => Some('\u{31b}'),
+        _ => None,
+    };
+    let mark = match tone {
+        1 => Some('\u{301}'),
+        2 => Some('\u{300}'),
+        3 => Some('\u{309}'),
+        4 => Some('\u{303}'),
+        5 => Some('\u{323}'),
+        _ => None,
+    };
+    // Canonical order sorts by combining class: horn (216) < dot below (220) < the
+    // marks above (230), so only a dot below precedes its modifier (`ệ` = e U+0323 U+0302)
+    let (first, second) = match (modifier, mark) {
+        (Some(m), Some('\u{323}')) if m != '\u{31b}' => (mark, modifier),
+        _ => (modifier, mark),
+    };
+    for c in [first, second].into_iter().flatten() {
+        out.write_char(c)?;
+    }
+    Ok(())
+}
+
+#[cfg(feature = "std")]
+pub(crate) fn to_nfd(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() * 2);
+    for c in s.chars() {
+        // Writing to a String cannot fail
+        let _ = write_decomposed(c, &mut out);
+    }
+    out
+}
+
+#[inline]
+pub(crate) fn to_lower_vi(c: char) -> char {
+    if c.is_ascii() {
+        c.to_ascii_lowercase()
+    } else {
+        c.to_lowercase().next().unwrap_or(c)
+    }
+}
+
+/// Where the tone mark goes in a syllable.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum ToneStyle {
+    /// "New style": open `oa`, `oe`, `uy` carry the tone on the second vowel (hoà, thuỷ).
+    #[default]
+    Modern,
+    /// "Old style": open `oa`, `oe`, `uy` carry the tone on the first vowel (hòa, thủy).
+    Traditional,
+}
+
+/// Picks the slot of `chars` that carries the tone, given the lowercase resolved
+/// letters and a bitmask of their vowel slots. Returns `None` when there is no vowel.
+pub(crate) fn tone_position(chars: &[char], mask: u32, style: ToneStyle) -> Option<usize> {
+    // The mask is built from the same slice, so no bit may point past its end.
+    debug_assert!(chars.len() >= 32 || mask >> chars.len() == 0);
+    // The u of qu is a glide and the i of gi part of the onset, so neither takes the tone
+    // when another vowel follows (quốc, quyết, giữa, giường); gì and gìn keep it on i
+    let mask = match chars {
+        ['q' | 'Q', 'u' | 'U', ..] | ['g' | 'G', 'i' | 'I', ..] if mask & 0b10 != 0 && mask.count_ones() > 1 => {
+            mask & !0b10
+        }
+        _ => mask,
+    };
+    let count = mask.count_ones();
+    if count == 0 {
+        return None;
+    }
+
+    let target_pos = match count {
+        1 => mask.trailing_zeros() as usize,
+        2 => {
+            let first = mask.trailing_zeros() as usize;
+            let second = (mask & !(1 << first)).trailing_zeros() as usize;
+
+            let f = chars.get(first).copied().unwrap_or('\0');
+            let sc = chars.get(second).copied().unwrap_or('\0');
+
+            // Special case: ui/ưi (e.g. "túi", "gửi") place tone on the first vowel.
+            // Exception: in "qu" prefix, 'u' is a glide, so tone belongs to the following vowel.
+            let mut prefer_first = (f == 'u' || f == 'ư') && sc == 'i';
+
+            // Modified/circumflex vowels paired with a plain vowel: tone on the modified vowel.
+            // e.g. ơi(mới), ôi(tối), êu(nếu), âu(đầu), ây(đấy), âo(cháo/nấo)
+            // Exception: ươ pair — tone goes on ơ (second), not ư.
+            let f_is_modified = matches!(f, 'ơ' | 'ô' | 'ê' | 'â' | 'ă');
+            let sc_is_plain = matches!(sc, 'a' | 'e' | 'i' | 'o' | 'u' | 'y');
+            if f_is_modified && sc_is_plain {
+                prefer_first = true;
+            }
+
+            let has_coda = (second + 1) < chars.len();
+
+            // Traditional style keeps the tone on the first vowel of open oa/oe/uy (hòa, thủy)
+            let traditional_first = style == ToneStyle::Traditional
+                && !has_coda
+                && matches!((f, sc), ('o', 'a') | ('o', 'e') | ('u', 'y'));
+
+            // Standard open pairs that often prefer tone on the first vowel.
+            let is_open_pair = (f == 'i' && (sc == 'a' || sc == 'u'))
+                || (f == 'u' && (sc == 'a' || sc == 'e'))
+                || (f == 'ư' && (sc == 'a' || sc == 'u'))
+                || (f == 'a'
+                    && (sc == 'o' || sc == 'e' || sc == 'i' || sc == 'u' || sc == 'y'))
+                || (f == 'e' && (sc == 'o' || sc == 'u'))
+                || (f == 'o' && sc == 'i')
+                || (f == 'â' && (sc == 'y' || sc == 'u'));
+
+            if prefer_first || traditional_first {
+                first
+            } else if is_open_pair {
+                if has_coda { second } else { first }
+            } else {
+                second
+            }
+        }
+        // Three or more vowels: a modified vowel takes the tone, the second one of ươ
+        // (người, rượu, chuyện, yếu, khuấy); otherwise the middle vowel of oai, oay, oeo, uya, uyu (ngoài, khuỷu).
+        // uyê keeps its tone on ê even before the circumflex is typed (thuyenf → thuyèn)
+        _ => {
+            let vowels = || (0..chars.len()).filter(|&k| mask & (1 << k) != 0);
+            match vowels().rfind(|&k| matches!(chars[k], 'ă' | 'â' | 'ê' | 'ô' | 'ơ' | 'ư')) {
+                Some(k) => k,
+                None => match (vowels().nth(1), vowels().nth(2)) {
+                    (Some(y), Some(e)) if chars[y] == 'y' && chars[e] == 'e' => e,
+                    (middle, _) => middle.unwrap_or(0),
+                },
+            }
+        }
+    };
+
+    debug_assert!(target_pos < chars.len());
+    Some(target_pos)
+}
+
+/// A composed word split into lowercase, untoned letters, ready for tone placement.
+#[cfg(feature = "std")]
+pub(crate) struct BaseWord {
+    pub chars: [char; 32],
+    pub len: usize,
+    pub vowel_mask: u32,
+    pub upper_mask: u32,
+    /// Tone carried by the word (0 = none) and the slot carrying it.
+    pub tone: u8,
+    pub toned_at: Option<usize>,
+}
+
+#[cfg(feature = "std")]
+impl BaseWord {
+    /// Returns `None` for words longer than 32 letters or carrying more than one tone.
+    pub(crate) fn parse(word: &str) -> Option<Self> {
+        let mut w = BaseWord {
+            chars: ['\0'; 32],
+            len: 0,
+            vowel_mask: 0,
+            upper_mask: 0,
+            tone: 0,
+            toned_at: None,
+        };
+        for c in word.chars() {
+            if w.len == w.chars.len() {
+                return None;
+            }
+            let lower = if c.is_uppercase() {
+                w.upper_mask |= 1 << w.len;
+                c.to_lowercase().next().unwrap_or(c)
+            } else {
+                c
+            };
+            w.chars[w.len] = match tone_vowel_index(lower) {
+                Some((row, t)) => {
+                    if t != 0 {
+                        if w.toned_at.is_some() {
+                            return None;
+                        }
+                        w.tone = t;
+                        w.toned_at = Some(w.len);
+                    }
+                    w.vowel_mask |= 1 << w.len;
+                    tone_row_base(row)
+                }
+                None => lower,
+            };
+            w.len += 1;
+        }
+        Some(w)
+    }
+
+    #[inline]
+    pub(crate) fn letters(&self) -> &[char] {
+        &self.chars[..self.len]
+    }
+
+    pub(crate) fn tone_position(&self, style: ToneStyle) -> Option<usize> {
+        tone_position(self.letters(), self.vowel_mask, style)
+    }
+
+    /// True for syllables ending in a stop (c, ch, p, t), which only take sắc or nặng.
+    pub(crate) fn has_stop_coda(&self) -> bool {
+        let letters = self.letters();
+        let Some(&last) = letters.last() else {
+            return false;
+        };
+        let last_is_vowel = self.vowel_mask & (1 << (self.len - 1)) != 0;
+        !last_is_vowel
+            && self.vowel_mask != 0
+            && (matches!(last, 'c' | 'p' | 't') || letters.ends_with(&['c', 'h']))
+    }
+
+    /// The word with `tone` placed on slot `target`, in its original case.
+    pub(crate) fn with_tone(&self, tone: u8, target: Option<usize>) -> impl Iterator<Item = char> + '_ {
+        self.letters().iter().enumerate().map(move |(k, &c)| {
+            let c = if Some(k) == target { map_vowel_with_tone(c, tone) } else { c };
+            if self.upper_mask & (1 << k) != 0 { to_upper_vi(c) } else { c }
+        })
+    }
+}
+
+/// Looks up a lowercase vowel in the tone table, returning its row and tone id (0 = no tone).
+pub(crate) fn tone_vowel_index(c: char) -> Option<(usize, u8)> {
+    for (row, forms) in TONE_VOWELS.iter().enumerate() {
+        if let Some(tone) = forms.iter().position(|&f| f == c) {
+            return Some((row, tone as u8));
+        }
+    }
+    None
+}
+
+/// Untoned base form of a tone table row (`a`, `ă`, `â`, ...).
+#[inline(always)]
+pub(crate) fn tone_row_base(row: usize) -> char {
+    TONE_VOWELS[row][0]
+}
+
 const TONE_VOWELS: [[char; 6]; 12] = [
     ['a', 'á', 'à', 'ả', 'ã', 'ạ'],
     ['ă', 'ắ', 'ằ', 'ẳ', 'ẵ', 'ặ'],
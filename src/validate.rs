@@ -0,0 +1,195 @@
+//! Structural check of composed Vietnamese syllables, for spellcheckers and for the
+//! engine's commit-time fallback to English.
+
+use crate::tone::{is_vowel_unicode, tone_row_base, tone_vowel_index};
+
+/// Whether `word` is shaped like a Vietnamese syllable: a legal onset, vowel group and
+/// final consonant that fit together, spelled by the usual `c`/`k`, `g`/`gh`, `ng`/`ngh`
+/// rules, and a tone the final allows (`p`, `t`, `c`, `ch` only take sắc or nặng).
+///
+/// Any case is accepted. It checks the shape only, so `khoèo` passes though no word
+/// is spelled like that.
+///
+/// ```
+/// use uvie::validate::is_valid_syllable;
+/// assert!(is_valid_syllable("nghiêng"));
+/// assert!(is_valid_syllable("Quốc"));
+/// assert!(!is_valid_syllable("clear"));
+/// assert!(!is_valid_syllable("đứcx"));
+/// assert!(!is_valid_syllable("hòc"));
+/// ```
+pub fn is_valid_syllable(word: &str) -> bool {
+    !word.is_empty() && word.chars().all(char::is_alphabetic) && is_valid_letters(word.chars(), true)
+}
+
+/// Whether a final consonant can follow the vowel group.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Final {
+    Optional,
+    Required,
+    Never,
+}
+
+const ONSETS: [&str; 28] = [
+    "", "b", "c", "ch", "d", "đ", "g", "gh", "gi", "h", "k", "kh", "l", "m", "n", "ng", "ngh", "nh", "p", "ph", "qu",
+    "r", "s", "t", "th", "tr", "v", "x",
+];
+
+const CODAS: [&str; 9] = ["", "c", "ch", "m", "n", "ng", "nh", "p", "t"];
+
+// Vowel groups after the onset, so without the `u` of `qu` or the `i` of `gi`
+const NUCLEI: [(&str, Final); 55] = [
+    ("a", Final::Optional),
+    ("ă", Final::Required),
+    ("â", Final::Required),
+    ("e", Final::Optional),
+    ("ê", Final::Optional),
+    ("i", Final::Optional),
+    ("o", Final::Optional),
+    ("ô", Final::Optional),
+    ("ơ", Final::Optional),
+    ("u", Final::Optional),
+    ("ư", Final::Optional),
+    ("y", Final::Optional),
+    ("ai", Final::Never),
+    ("ao", Final::Never),
+    ("au", Final::Never),
+    ("ay", Final::Never),
+    ("âu", Final::Never),
+    ("ây", Final::Never),
+    ("eo", Final::Never),
+    ("êu", Final::Never),
+    ("ia", Final::Never),
+    ("iu", Final::Never),
+    ("oi", Final::Never),
+    ("ôi", Final::Never),
+    ("ơi", Final::Never),
+    ("ua", Final::Never),
+    ("ui", Final::Never),
+    ("ưa", Final::Never),
+    ("ưi", Final::Never),
+    ("ưu", Final::Never),
+    ("uơ", Final::Never),
+    ("oa", Final::Optional),
+    ("oe", Final::Optional),
+    ("uê", Final::Optional),
+    ("uy", Final::Optional),
+    ("iê", Final::Required),
+    ("yê", Final::Required),
+    ("uô", Final::Required),
+    ("ươ", Final::Required),
+    ("oă", Final::Required),
+    ("uâ", Final::Required),
+    ("oo", Final::Required),
+    ("uyê", Final::Required),
+    ("iêu", Final::Never),
+    ("yêu", Final::Never),
+    ("oai", Final::Never),
+    ("oay", Final::Never),
+    ("oao", Final::Never),
+    ("oeo", Final::Never),
+    ("uây", Final::Never),
+    ("uôi", Final::Never),
+    ("ươi", Final::Never),
+    ("ươu", Final::Never),
+    ("uya", Final::Never),
+    ("uyu", Final::Never),
+];
+
+/// Whether `letters` (lowercase) are a final consonant Vietnamese allows, or none.
+pub(crate) fn is_valid_coda(letters: impl Iterator<Item = char> + Clone) -> bool {
+    CODAS.iter().any(|c| c.chars().eq(letters.clone()))
+}
+
+/// [`is_valid_syllable`] on the letters of `word`; non-letters (escaped VIQR marks,
+/// digits) are skipped. Unless `strict`, only the onset, final and a group of at most
+/// three vowels are checked, so unfinished words (`viêt`) and chat spellings (`kó`) pass.
+pub(crate) fn is_valid_letters(word: impl Iterator<Item = char>, strict: bool) -> bool {
+    parts(word, strict).is_some()
+}
+
+/// How a syllable splits up, in letters, with its tone (0-5).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Parts {
+    pub onset: usize,
+    pub nucleus: usize,
+    pub coda: usize,
+    pub tone: u8,
+}
+
+/// Splits the letters of `word` into onset, vowel group and final if they pass
+/// [`is_valid_letters`]. `qu` and `gi` before a vowel count as onsets.
+pub(crate) fn parts(word: impl Iterator<Item = char>, strict: bool) -> Option<Parts> {
+    // `nghiêng` is the longest syllable
+    let mut letters = ['\0'; 7];
+    let mut len = 0usize;
+    let mut tone = 0u8;
+    for c in word.filter(|c| c.is_alphabetic()) {
+        let c = c.to_lowercase().next().unwrap_or(c);
+        let c = match tone_vowel_index(c) {
+            Some((row, t)) => {
+                if t != 0 {
+                    if tone != 0 {
+                        return None;
+                    }
+                    tone = t;
+                }
+                tone_row_base(row)
+            }
+            None => c,
+        };
+        if len == letters.len() {
+            return None;
+        }
+        letters[len] = c;
+        len += 1;
+    }
+    let letters = &letters[..len];
+
+    let first_vowel = letters.iter().position(|&c| is_vowel_unicode(c))?;
+    // `qu` and `gi` (before another vowel) take their vowel into the onset
+    let onset_len = match letters {
+        ['q', 'u', next, ..] | ['g', 'i', next, ..] if is_vowel_unicode(*next) => 2,
+        _ => first_vowel,
+    };
+    let (onset, rest) = letters.split_at(onset_len);
+    let nucleus_len = rest.iter().take_while(|&&c| is_vowel_unicode(c)).count();
+    let (nucleus, coda) = rest.split_at(nucleus_len);
+
+    if !ONSETS.iter().any(|o| o.chars().eq(onset.iter().copied()))
+        || !is_valid_coda(coda.iter().copied())
+    {
+        return None;
+    }
+    let found = Parts {
+        onset: onset.len(),
+        nucleus: nucleus.len(),
+        coda: coda.len(),
+        tone,
+    };
+    if !strict {
+        return (nucleus_len <= 3).then_some(found);
+    }
+    let &(_, fin) = NUCLEI.iter().find(|(n, _)| n.chars().eq(nucleus.iter().copied()))?;
+    match fin {
+        Final::Required if coda.is_empty() => return None,
+        Final::Never if !coda.is_empty() => return None,
+        _ => {}
+    }
+
+    // Spelling: `k`, `gh` and `ngh` before e ê i y, `c`, `g` and `ng` elsewhere
+    let front = matches!(nucleus.first(), Some('e' | 'ê' | 'i' | 'y'));
+    let spelled = match onset {
+        ['c'] | ['g'] | ['n', 'g'] => !front || onset == ['g'] && nucleus.first() == Some(&'i'),
+        ['k'] | ['g', 'h'] | ['n', 'g', 'h'] => front,
+        _ => true,
+    };
+    // `ch` and `nh` finals follow a, ê, i or y (`anh`, `ếch`, `tính`, `huỳnh`)
+    let fits = match coda {
+        ['c', 'h'] | ['n', 'h'] => matches!(nucleus.last(), Some('a' | 'ê' | 'i' | 'y')),
+        _ => true,
+    };
+    // Stop finals take only sắc or nặng
+    let toned = !matches!(coda, ['p'] | ['t'] | ['c'] | ['c', 'h']) || matches!(tone, 1 | 5);
+    (spelled && fits && toned).then_some(found)
+}
@@ -0,0 +1,143 @@
+//! Test vectors: files of `method<TAB>keys<TAB>expected` lines, with an optional
+//! `<TAB>comment`, checked against the engine, e.g. `vni<TAB>hoa2<TAB>hoà<TAB>tone on the o`.
+//!
+//! Blank lines and lines starting with `#` are skipped. The older `keys<TAB>expected` form
+//! is still read: a trailing `@telex`, `@vni`, `@auto` or `@viqr` token selects the input
+//! method of its line (Telex otherwise).
+
+use std::fmt;
+use std::io::{self, BufRead};
+
+use crate::{InputMethod, UltraFastViEngine};
+
+/// A vector line the engine got wrong, or could not read.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Failure {
+    /// 1-based line number in the vector file.
+    pub line: usize,
+    pub method: InputMethod,
+    pub input: String,
+    pub expected: String,
+    /// The line's comment column, if it has one.
+    pub comment: Option<String>,
+    pub kind: FailureKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FailureKind {
+    /// Feeding the keys one at a time and committing gave this instead.
+    Word(String),
+    /// [`transform`](crate::transform) gave this instead.
+    Batch(String),
+    /// The line is not `method<TAB>keys<TAB>expected[<TAB>comment]` or
+    /// `keys<TAB>expected [@method]`.
+    Malformed(&'static str),
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            FailureKind::Word(actual) => write!(
+                f,
+                "line {}: {} {:?} typed as {:?}, expected {:?}",
+                self.line, self.method, self.input, actual, self.expected
+            )?,
+            FailureKind::Batch(actual) => write!(
+                f,
+                "line {}: {} {:?} transformed to {:?}, expected {:?}",
+                self.line, self.method, self.input, actual, self.expected
+            )?,
+            FailureKind::Malformed(reason) => return write!(f, "line {}: {reason}", self.line),
+        }
+        match &self.comment {
+            Some(comment) => write!(f, " ({comment})"),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Checks every vector read from `reader` through both the keystroke and the batch path
+/// with a fresh engine, returning the failures in line order.
+pub fn run_vectors<R: BufRead>(reader: R) -> io::Result<Vec<Failure>> {
+    let mut failures = Vec::new();
+    let mut engine = UltraFastViEngine::new();
+    for (i, line) in reader.lines().enumerate() {
+        let line = line?;
+        let number = i + 1;
+        let line = line.trim_end_matches('\r');
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let malformed = |reason| Failure {
+            line: number,
+            method: InputMethod::Telex,
+            input: line.to_owned(),
+            expected: String::new(),
+            comment: None,
+            kind: FailureKind::Malformed(reason),
+        };
+        let (method, input, expected, comment) = match parse_line(line) {
+            Ok(parsed) => parsed,
+            Err(reason) => {
+                failures.push(malformed(reason));
+                continue;
+            }
+        };
+        let failure = |kind| Failure {
+            line: number,
+            method,
+            input: input.to_owned(),
+            expected: expected.to_owned(),
+            comment: comment.map(str::to_owned),
+            kind,
+        };
+
+        engine.clear();
+        engine.set_input_method(method);
+        let mut typed = String::new();
+        for key in input.chars() {
+            engine.feed(key);
+            if engine.preview().is_empty() {
+                typed.push_str(engine.rendered());
+            }
+        }
+        typed.push_str(engine.commit());
+        if typed != expected {
+            failures.push(failure(FailureKind::Word(typed)));
+        }
+
+        let transformed = crate::transform(input, method);
+        if transformed != expected {
+            failures.push(failure(FailureKind::Batch(transformed)));
+        }
+    }
+    Ok(failures)
+}
+
+/// Splits a vector line into its method, keys, expected output and comment.
+fn parse_line(line: &str) -> Result<(InputMethod, &str, &str, Option<&str>), &'static str> {
+    let mut fields = line.split('\t');
+    let columns = (fields.next(), fields.next(), fields.next(), fields.next(), fields.next());
+    match columns {
+        (Some(method), Some(input), Some(expected), comment, None) if !expected.starts_with('@') => {
+            let method = method.parse().map_err(|_| "unknown method, use telex|vni|viqr|auto")?;
+            Ok((method, input, expected, comment.filter(|c| !c.trim().is_empty())))
+        }
+        (_, _, _, Some(_), _) => Err("expected method<TAB>keys<TAB>expected[<TAB>comment]"),
+        _ => parse_legacy_line(line),
+    }
+}
+
+/// Reads the older `keys<TAB>expected [@method]` form.
+fn parse_legacy_line(line: &str) -> Result<(InputMethod, &str, &str, Option<&str>), &'static str> {
+    let (line, method) = match line.rsplit_once(['\t', ' ']) {
+        Some((rest, token)) if token.starts_with('@') => match token[1..].parse() {
+            Ok(method) => (rest.trim_end(), method),
+            Err(_) => return Err("unknown method, use @telex|@vni|@viqr|@auto"),
+        },
+        _ => (line, InputMethod::Telex),
+    };
+    let (input, expected) = line.split_once('\t').ok_or("expected method<TAB>keys<TAB>expected")?;
+    Ok((method, input, expected, None))
+}
@@ -0,0 +1,81 @@
+//! `wasm-bindgen` bindings for browser text fields (`wasm` feature).
+
+use wasm_bindgen::prelude::*;
+
+use crate::{InputMethod, UltraFastViEngine};
+
+/// Edit to apply to the text field: delete `backspaces` chars before the caret, then
+/// type `insert`.
+#[wasm_bindgen(getter_with_clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WasmDelta {
+    pub backspaces: usize,
+    pub insert: String,
+}
+
+#[wasm_bindgen]
+pub struct WasmEngine {
+    engine: UltraFastViEngine,
+}
+
+#[wasm_bindgen]
+impl WasmEngine {
+    /// `method` is `"telex"`, `"vni"`, `"viqr"` or `"auto"`.
+    #[wasm_bindgen(constructor)]
+    pub fn new(method: &str) -> Result<WasmEngine, String> {
+        Ok(Self {
+            engine: UltraFastViEngine::builder().input_method(parse_method(method)?).build(),
+        })
+    }
+
+    /// Feeds one key (a single-char `KeyboardEvent.key`); other keys are ignored and
+    /// return an empty delta.
+    pub fn feed(&mut self, key: &str) -> WasmDelta {
+        let mut chars = key.chars();
+        let (Some(c), None) = (chars.next(), chars.next()) else {
+            return WasmDelta {
+                backspaces: 0,
+                insert: String::new(),
+            };
+        };
+        let delta = self.engine.feed_delta(c);
+        WasmDelta {
+            backspaces: delta.backspaces,
+            insert: delta.insert.to_owned(),
+        }
+    }
+
+    /// Finalizes the word being composed; the delta turns the preview shown for it
+    /// into the committed word (`expression` typed in Telex is restored as typed).
+    pub fn commit(&mut self) -> WasmDelta {
+        let shown = self.engine.preview().to_owned();
+        diff(&shown, self.engine.commit())
+    }
+
+    pub fn clear(&mut self) {
+        self.engine.clear();
+    }
+
+    #[wasm_bindgen(js_name = setMethod)]
+    pub fn set_method(&mut self, method: &str) -> Result<(), String> {
+        self.engine.set_input_method(parse_method(method)?);
+        Ok(())
+    }
+}
+
+/// The edit turning `prev` into `next`, keeping their common prefix.
+fn diff(prev: &str, next: &str) -> WasmDelta {
+    let prefix = prev
+        .char_indices()
+        .zip(next.chars())
+        .find(|&((_, pc), nc)| pc != nc)
+        .map_or(prev.len().min(next.len()), |((i, _), _)| i);
+    WasmDelta {
+        backspaces: prev[prefix..].chars().count(),
+        insert: next[prefix..].to_owned(),
+    }
+}
+
+fn parse_method(method: &str) -> Result<InputMethod, String> {
+    method.parse().map_err(|err| format!("{method:?}: {err}"))
+}
@@ -0,0 +1,161 @@
+use std::io::{self, BufRead, Write};
+
+use crate::{InputMethod, UltraFastViEngine};
+
+/// Converts text as it is written through it, like [`transform`](crate::transform).
+///
+/// The word being composed is held back until a non-word char or [`flush`](Write::flush);
+/// dropping the writer flushes too. UTF-8 sequences split across writes are reassembled
+/// and invalid bytes are passed through unchanged.
+///
+/// ```no_run
+/// use std::io;
+/// use uvie::{InputMethod, ViWriter};
+///
+/// io::copy(&mut io::stdin(), &mut ViWriter::new(io::stdout(), InputMethod::Telex))?;
+/// # Ok::<(), io::Error>(())
+/// ```
+pub struct ViWriter<W: Write> {
+    // Only `None` once `into_inner` has taken it.
+    inner: Option<W>,
+    engine: UltraFastViEngine,
+    // Leading bytes of a UTF-8 sequence cut off by the end of a write.
+    partial: [u8; 4],
+    partial_len: usize,
+    out: String,
+}
+
+impl<W: Write> ViWriter<W> {
+    pub fn new(inner: W, method: InputMethod) -> Self {
+        Self {
+            inner: Some(inner),
+            engine: UltraFastViEngine::builder().input_method(method).build(),
+            partial: [0; 4],
+            partial_len: 0,
+            out: String::new(),
+        }
+    }
+
+    /// Flushes, writes any incomplete trailing UTF-8 bytes as they are, and returns the
+    /// inner writer.
+    pub fn into_inner(mut self) -> io::Result<W> {
+        self.flush()?;
+        let mut inner = self.inner.take().expect("inner writer present until into_inner");
+        inner.write_all(&self.partial[..self.partial_len])?;
+        Ok(inner)
+    }
+
+    fn inner(&mut self) -> &mut W {
+        self.inner.as_mut().expect("inner writer present until into_inner")
+    }
+
+    fn convert(&mut self, text: &str) {
+        for c in text.chars() {
+            self.engine.transform_push(c, &mut self.out);
+        }
+    }
+
+    fn write_out(&mut self) -> io::Result<()> {
+        let out = std::mem::take(&mut self.out);
+        let res = self.inner().write_all(out.as_bytes());
+        self.out = out;
+        self.out.clear();
+        res
+    }
+}
+
+impl<W: Write> Write for ViWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut rest = buf;
+
+        // Complete a sequence left over from the previous write
+        while self.partial_len > 0 && !rest.is_empty() {
+            self.partial[self.partial_len] = rest[0];
+            let (bytes, n) = (self.partial, self.partial_len + 1);
+            match std::str::from_utf8(&bytes[..n]) {
+                Ok(s) => {
+                    self.partial_len = 0;
+                    rest = &rest[1..];
+                    self.convert(s);
+                }
+                Err(e) if e.error_len().is_some() => {
+                    // Not a continuation byte: pass the cut-off bytes through and read
+                    // this one afresh
+                    self.partial_len = 0;
+                    self.engine.transform_finish(&mut self.out);
+                    self.write_out()?;
+                    self.inner().write_all(&bytes[..n - 1])?;
+                }
+                Err(_) => {
+                    self.partial_len = n;
+                    rest = &rest[1..];
+                }
+            }
+        }
+
+        while !rest.is_empty() {
+            match std::str::from_utf8(rest) {
+                Ok(s) => {
+                    self.convert(s);
+                    rest = &[];
+                }
+                Err(e) => {
+                    let (valid, tail) = rest.split_at(e.valid_up_to());
+                    self.convert(std::str::from_utf8(valid).unwrap_or_default());
+                    match e.error_len() {
+                        Some(n) => {
+                            self.engine.transform_finish(&mut self.out);
+                            self.write_out()?;
+                            self.inner().write_all(&tail[..n])?;
+                            rest = &tail[n..];
+                        }
+                        None => {
+                            self.partial[..tail.len()].copy_from_slice(tail);
+                            self.partial_len = tail.len();
+                            rest = &[];
+                        }
+                    }
+                }
+            }
+        }
+
+        self.write_out()?;
+        Ok(buf.len())
+    }
+
+    /// Ends the word being composed, so the next write starts a new one.
+    fn flush(&mut self) -> io::Result<()> {
+        self.engine.transform_finish(&mut self.out);
+        self.write_out()?;
+        self.inner().flush()
+    }
+}
+
+impl<W: Write> Drop for ViWriter<W> {
+    fn drop(&mut self) {
+        if self.inner.is_some() {
+            let _ = self.flush();
+        }
+    }
+}
+
+/// Converts a whole document line by line, streaming from `input` to `output`.
+///
+/// Newlines and punctuation end words and the last word is flushed at the end. Fails
+/// with [`io::ErrorKind::InvalidData`], naming the line, if `input` is not UTF-8.
+pub fn convert<R: BufRead, W: Write>(mut input: R, output: W, method: InputMethod) -> io::Result<()> {
+    let mut writer = ViWriter::new(output, method);
+    let mut line = String::new();
+    for number in 1.. {
+        line.clear();
+        let n = input.read_line(&mut line).map_err(|err| match err.kind() {
+            io::ErrorKind::InvalidData => io::Error::new(io::ErrorKind::InvalidData, format!("line {number}: invalid UTF-8")),
+            _ => err,
+        })?;
+        if n == 0 {
+            break;
+        }
+        writer.write_all(line.as_bytes())?;
+    }
+    writer.into_inner()?.flush()
+}
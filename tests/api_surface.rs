@@ -0,0 +1,50 @@
+//! Names every item of the intended public API, so that removing or renaming one
+//! fails to build. Compiling is the test.
+
+#![allow(unused_imports)]
+
+use uvie::{
+    Decision, Delimiters, Delta, EngineBuilder, EngineConfig, EngineError, EngineState, FeedResult, InputMethod,
+    InputResult, Key, KeyRemap, KeystrokeEffect, OutputForm, ParseInputMethodError, StopFinalTone, TelexVariant,
+    ToneStyle, UltraFastViEngine, ValidationLevel, YOrthography,
+};
+#[cfg(feature = "std")]
+use uvie::{TransformViExt, ViWriter, convert, retone_text, run_vectors, transform, transform_word};
+
+use uvie::english::looks_english;
+use uvie::fold::remove_diacritics_into;
+use uvie::modes::{IS_MODIFIER, IS_TONE_KEY, IS_VOWEL, Mode, ModeBuilder, ResolverFn};
+use uvie::syllable::{Syllable, parse};
+use uvie::tone::{
+    Tone, add_tone, base_vowel, is_vietnamese_vowel, is_vowel_unicode, map_vowel_with_tone, strip_tone, to_upper_vi,
+    tone_of,
+};
+use uvie::validate::is_valid_syllable;
+
+#[cfg(feature = "std")]
+use uvie::hints::key_sequences_for;
+#[cfg(feature = "alloc")]
+use uvie::fold::remove_diacritics;
+#[cfg(feature = "alloc")]
+use uvie::ime::{ImeAction, Session};
+#[cfg(feature = "std")]
+use uvie::iter::TransformVi;
+#[cfg(feature = "alloc")]
+use uvie::reverse::{to_telex, to_vni};
+#[cfg(feature = "std")]
+use uvie::vectors::{Failure, FailureKind};
+
+#[cfg(feature = "ffi")]
+use uvie::ffi::{uvie_engine_clear, uvie_engine_feed, uvie_engine_free, uvie_engine_new, uvie_engine_rendered, uvie_engine_set_method};
+#[cfg(feature = "python")]
+use uvie::python::{PyViEngine, register};
+#[cfg(feature = "wasm")]
+use uvie::wasm::{WasmDelta, WasmEngine};
+
+mod prelude {
+    use uvie::prelude::{
+        EngineBuilder, EngineConfig, FeedResult, InputMethod, Key, OutputForm, TelexVariant, ToneStyle, UltraFastViEngine,
+    };
+    #[cfg(feature = "std")]
+    use uvie::prelude::{TransformViExt, transform};
+}
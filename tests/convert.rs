@@ -0,0 +1,42 @@
+#![cfg(feature = "std")]
+
+use std::io::{Cursor, ErrorKind};
+
+use uvie::{InputMethod, convert};
+
+fn convert_str(input: &[u8], method: InputMethod) -> std::io::Result<String> {
+    let mut out = Vec::new();
+    convert(Cursor::new(input), &mut out, method)?;
+    Ok(String::from_utf8(out).unwrap())
+}
+
+#[test]
+fn converts_document_line_by_line() {
+    let input = "Vieejt Nam\ntooi ddi hocj.\n\n(xin chaof), bajn!\nddaay";
+    assert_eq!(
+        convert_str(input.as_bytes(), InputMethod::Telex).unwrap(),
+        "Việt Nam\ntôi đi học.\n\n(xin chào), bạn!\nđây"
+    );
+}
+
+#[test]
+fn converts_vni_and_keeps_crlf() {
+    assert_eq!(
+        convert_str(b"Vie65t Nam\r\nd9u7o7ng2\r\n", InputMethod::Vni).unwrap(),
+        "Việt Nam\r\nđường\r\n"
+    );
+}
+
+#[test]
+fn large_input_streams_through() {
+    let input = "tooi ddi hocj\n".repeat(10_000);
+    let out = convert_str(input.as_bytes(), InputMethod::Telex).unwrap();
+    assert_eq!(out, "tôi đi học\n".repeat(10_000));
+}
+
+#[test]
+fn invalid_utf8_is_reported_with_its_line() {
+    let err = convert_str(b"vieejt\nnam \xff\n", InputMethod::Telex).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+    assert!(err.to_string().contains("line 2"), "{err}");
+}
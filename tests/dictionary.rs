@@ -0,0 +1,33 @@
+use uvie::UltraFastViEngine;
+
+#[cfg(feature = "dictionary")]
+const WORDS: &[&str] = &["hán", "hảo", "nam", "việt", "xin"];
+
+#[cfg(feature = "dictionary")]
+#[test]
+fn listed_words_are_converted() {
+    let mut e = UltraFastViEngine::builder().dictionary(WORDS).build();
+    assert_eq!(e.feed_str("haor hans "), "hảo hán ");
+    assert_eq!(e.feed_str("Vieetj Nam "), "Việt Nam ");
+}
+
+#[cfg(feature = "dictionary")]
+#[test]
+fn unlisted_words_are_committed_as_typed() {
+    let mut e = UltraFastViEngine::new();
+    e.set_dictionary(WORDS);
+    assert_eq!(e.feed_str("hax "), "hax ");
+    assert_eq!(e.feed_str("tooi "), "tooi ");
+    // Plain words need no entry
+    assert_eq!(e.feed_str("hello "), "hello ");
+
+    e.clear_dictionary();
+    assert_eq!(e.feed_str("hax tooi "), "hã tôi ");
+}
+
+#[cfg(not(feature = "dictionary"))]
+#[test]
+fn every_syllable_is_converted_without_the_feature() {
+    let mut e = UltraFastViEngine::new();
+    assert_eq!(e.feed_str("hax tooi "), "hã tôi ");
+}
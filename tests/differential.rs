@@ -0,0 +1,88 @@
+//! Compares uvie with the `vi` crate on Telex and VNI keys, to surface tone placement
+//! and validation differences. Slow and expected to turn up known differences, so it
+//! only runs on request:
+//!
+//! ```text
+//! cargo test --test differential -- --ignored
+//! ```
+//!
+//! Keys where uvie differs on purpose are listed in `tests/data/differential/allow.tsv`.
+#![cfg(feature = "std")]
+
+use uvie::InputMethod;
+
+const SYLLABLES: &str = include_str!("data/syllables.txt");
+const ALLOW: &str = include_str!("data/differential/allow.tsv");
+
+/// Free-style and English keys the syllable list does not cover.
+const EXTRA: &[(InputMethod, &str)] = &[
+    (InputMethod::Telex, "tuwf"),
+    (InputMethod::Telex, "nguowif"),
+    (InputMethod::Telex, "dduowngf"),
+    (InputMethod::Telex, "vieetj"),
+    (InputMethod::Telex, "hoaf"),
+    (InputMethod::Telex, "quoocs"),
+    (InputMethod::Telex, "giuwax"),
+    (InputMethod::Telex, "cass"),
+    (InputMethod::Telex, "expression"),
+    (InputMethod::Telex, "search"),
+    (InputMethod::Telex, "xooong"),
+    (InputMethod::Telex, "\\vieejt"),
+    (InputMethod::Vni, "ngu7o72i"),
+    (InputMethod::Vni, "truong72"),
+    (InputMethod::Vni, "d9uong72"),
+    (InputMethod::Vni, "hoa2"),
+    (InputMethod::Vni, "file1"),
+    (InputMethod::Vni, "utf8"),
+];
+
+fn vi_transform(method: InputMethod, keys: &str) -> String {
+    let definition = match method {
+        InputMethod::Vni => &vi::VNI,
+        _ => &vi::TELEX,
+    };
+    let mut out = String::new();
+    vi::methods::transform_buffer(definition, keys.chars(), &mut out);
+    out
+}
+
+fn allowed() -> Vec<(InputMethod, &'static str)> {
+    ALLOW
+        .lines()
+        .filter(|line| !line.trim().is_empty() && !line.starts_with('#'))
+        .map(|line| {
+            let mut fields = line.split('\t');
+            let method = fields.next().unwrap().parse().unwrap_or_else(|err| panic!("{line:?}: {err}"));
+            let keys = fields.next().unwrap_or_else(|| panic!("{line:?}: expected method<TAB>keys"));
+            (method, keys)
+        })
+        .collect()
+}
+
+#[test]
+#[ignore = "compares against the vi crate; run with --ignored"]
+fn matches_vi() {
+    let mut cases: Vec<(InputMethod, String)> = Vec::new();
+    for word in SYLLABLES.lines().filter(|line| !line.starts_with('#')).flat_map(str::split_whitespace) {
+        cases.push((InputMethod::Telex, uvie::reverse::to_telex(word)));
+        cases.push((InputMethod::Vni, uvie::reverse::to_vni(word)));
+    }
+    cases.extend(EXTRA.iter().map(|&(method, keys)| (method, keys.to_owned())));
+
+    let allowed = allowed();
+    let mut diff = String::new();
+    let mut mismatches = 0;
+    for (method, keys) in &cases {
+        let ours = uvie::transform(keys, *method);
+        let theirs = vi_transform(*method, keys);
+        if ours != theirs && !allowed.contains(&(*method, keys.as_str())) {
+            mismatches += 1;
+            diff.push_str(&format!("{:<6} {keys:<12} ours {ours:<12} vi {theirs}\n", method.name()));
+        }
+    }
+    assert!(
+        mismatches == 0,
+        "{mismatches} of {} cases differ from vi (allow intended ones in tests/data/differential/allow.tsv):\n{diff}",
+        cases.len()
+    );
+}
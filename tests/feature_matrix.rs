@@ -0,0 +1,49 @@
+//! The engine in each buffer configuration. Every test target builds in all of them,
+//! skipping the tests of APIs a configuration leaves out:
+//!
+//! ```text
+//! cargo test                                           # std
+//! cargo test --no-default-features --features alloc    # no_std + alloc
+//! cargo test --no-default-features --features heapless # no_std, fixed buffers
+//! ```
+
+use uvie::{InputMethod, UltraFastViEngine};
+
+fn type_word(e: &mut UltraFastViEngine, keys: &str) -> bool {
+    e.clear();
+    keys.chars().for_each(|c| {
+        e.feed(c);
+    });
+    e.commit() == "việt"
+}
+
+#[test]
+fn composes_in_every_configuration() {
+    let mut e = UltraFastViEngine::new();
+    assert!(type_word(&mut e, "vieejt"));
+    e.set_input_method(InputMethod::Vni);
+    assert!(type_word(&mut e, "vie65t"));
+}
+
+#[cfg(not(feature = "heapless"))]
+#[test]
+fn growable_buffers_keep_long_words() {
+    let mut e = UltraFastViEngine::new();
+    let token = "x".repeat(200);
+    token.chars().for_each(|c| {
+        e.feed(c);
+    });
+    assert_eq!(e.commit(), token);
+    assert_eq!(e.last_error(), None);
+}
+
+#[cfg(feature = "heapless")]
+#[test]
+fn fixed_buffers_report_overflow() {
+    let mut e = UltraFastViEngine::new();
+    "x".repeat(200).chars().for_each(|c| {
+        e.feed(c);
+    });
+    assert_eq!(e.commit().len(), 32);
+    assert_eq!(e.last_error(), Some(uvie::EngineError::RawOverflow));
+}
@@ -0,0 +1,91 @@
+//! `feed_into` must not allocate once the engine exists, nor `feed` once its buffers
+//! have grown to fit the longest word.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::fmt::{self, Write};
+
+use uvie::{InputMethod, UltraFastViEngine};
+
+struct CountingAlloc;
+
+thread_local! {
+    // Per thread, so tests running alongside don't count against each other
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+fn allocations() -> usize {
+    ALLOCATIONS.with(Cell::get)
+}
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let _ = ALLOCATIONS.try_with(|n| n.set(n.get() + 1));
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+/// Fixed-capacity display buffer, as an embedded integrator would have.
+struct Display {
+    buf: [u8; 64],
+    len: usize,
+}
+
+impl Write for Display {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let end = self.len + s.len();
+        self.buf.get_mut(self.len..end).ok_or(fmt::Error)?.copy_from_slice(s.as_bytes());
+        self.len = end;
+        Ok(())
+    }
+}
+
+#[test]
+fn feed_into_does_not_allocate() {
+    for method in [InputMethod::Telex, InputMethod::Vni, InputMethod::Viqr] {
+        let mut engine = UltraFastViEngine::builder().input_method(method).build();
+        let mut display = Display { buf: [0; 64], len: 0 };
+
+        let before = allocations();
+        for c in "Vieejt Nam, d9u7o7ng2 to^i. nguowif expression ".chars() {
+            display.len = 0;
+            engine.feed_into(c, &mut display).unwrap();
+        }
+        assert_eq!(allocations(), before, "{method:?}");
+    }
+}
+
+#[test]
+fn feed_does_not_allocate_after_warm_up() {
+    let text = [
+        (InputMethod::Telex, "Tooi ddang gox Tieengs Vieejt, nguowif Vieejt Nam khoong thichs expression. Hoaf bifnh! "),
+        (InputMethod::Vni, "To6i d9ang go4 Tie6ng1 Vie6t5, ngu7o7i2 Vie6t5 Nam kho6ng thich1 expression. Hoa2 bi2nh! "),
+        (InputMethod::Viqr, "To^i dd-ang go~ Tie^'ng Vie^.t, ngu+o+`i Vie^.t Nam. "),
+        (InputMethod::Auto, "Tooi ddang go4 Tie6ng1 Vieejt "),
+    ];
+    for (method, text) in text {
+        let mut engine = UltraFastViEngine::builder().input_method(method).build();
+        // The first pass may grow the buffers to the longest word
+        text.chars().for_each(|c| {
+            engine.feed(c);
+        });
+
+        let before = allocations();
+        for _ in 0..5 {
+            for c in text.chars() {
+                engine.feed(c);
+                engine.preview();
+            }
+            engine.backspace();
+            engine.commit();
+        }
+        assert_eq!(allocations(), before, "{method:?}");
+    }
+}
@@ -0,0 +1,35 @@
+//! Runs every `tests/data/*.tsv` file of `method<TAB>keys<TAB>expected[<TAB>comment]`
+//! lines through a fresh engine, reporting all failures at once.
+//!
+//! Tricky words go in those files rather than in Rust; see `uvie::vectors` for the format.
+#![cfg(feature = "std")]
+
+use std::fs::{self, File};
+use std::io::BufReader;
+use std::path::Path;
+
+use uvie::run_vectors;
+
+#[test]
+fn golden_files_pass() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/data");
+    let mut files: Vec<_> = fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "tsv"))
+        .collect();
+    files.sort();
+    assert!(!files.is_empty(), "no .tsv files in {}", dir.display());
+
+    let mut report = String::new();
+    let mut count = 0;
+    for path in &files {
+        let failures = run_vectors(BufReader::new(File::open(path).unwrap())).unwrap();
+        let name = path.file_name().unwrap().to_string_lossy();
+        for failure in &failures {
+            report += &format!("tests/data/{name}:{failure}\n");
+        }
+        count += failures.len();
+    }
+    assert!(count == 0, "{count} golden failure(s):\n{report}");
+}
@@ -0,0 +1,81 @@
+//! Overflow reporting of the fixed-size buffers: `cargo test --features heapless`.
+#![cfg(feature = "heapless")]
+
+use uvie::{EngineConfig, EngineError, FeedResult, UltraFastViEngine};
+
+#[test]
+fn raw_overflow_is_reported() {
+    let mut e = UltraFastViEngine::new();
+    let token = "b".repeat(40);
+    for c in token.chars() {
+        e.feed(c);
+    }
+    assert_eq!(e.last_error(), Some(EngineError::RawOverflow));
+    // The keys that fit are kept as typed
+    assert_eq!(e.commit(), &token[..32]);
+
+    e.clear();
+    assert_eq!(e.last_error(), None);
+    assert_eq!(e.feed_str("vieejt"), "việt");
+    assert_eq!(e.last_error(), None);
+}
+
+#[test]
+fn out_overflow_is_reported() {
+    let mut e = UltraFastViEngine::new();
+    let sentence = "tooi ddi hocj ".repeat(20);
+    e.feed_str(&sentence);
+    assert_eq!(e.last_error(), Some(EngineError::OutOverflow));
+
+    e.clear();
+    e.feed_str("tooi ddi hocj ");
+    assert_eq!(e.last_error(), None);
+}
+
+#[test]
+fn smaller_buffers_overflow_sooner() {
+    let token = "b".repeat(17);
+    let mut small = UltraFastViEngine::<16, 48>::sized(EngineConfig::default());
+    small.feed_str(&token);
+    assert_eq!(small.last_error(), Some(EngineError::RawOverflow));
+    let mut e = UltraFastViEngine::new();
+    e.feed_str(&token);
+    assert_eq!(e.last_error(), None);
+
+    let sentence = "tooi ddi hocj ".repeat(4);
+    small.clear();
+    small.feed_str(&sentence);
+    assert_eq!(small.last_error(), Some(EngineError::OutOverflow));
+    e.feed_str(&sentence);
+    assert_eq!(e.last_error(), None);
+
+    small.clear();
+    assert_eq!(small.feed_str("nghieeng"), "nghiêng");
+    assert_eq!(small.last_error(), None);
+}
+
+#[test]
+fn committed_word_survives_a_full_out_buffer() {
+    // `bô` fills the buffer, so the space is dropped from the commit
+    let mut e = UltraFastViEngine::<16, 3>::sized(EngineConfig::default());
+    assert_eq!(e.feed_str("boo"), "bô");
+    assert_eq!(e.feed_event(' '), FeedResult::Committed { word: "bô", delimiter: ' ' });
+    assert_eq!(e.last_error(), Some(EngineError::OutOverflow));
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn transform_reports_raw_overflow() {
+    let input = "a".repeat(40) + "bcdef";
+    let mut e = UltraFastViEngine::new();
+    let mut out = String::new();
+    e.transform_str(&input, &mut out);
+    assert_eq!(e.last_error(), Some(EngineError::RawOverflow));
+    // The keys that fit are kept as typed
+    assert_eq!(out, &input[..32]);
+
+    out.clear();
+    e.transform_str("vieejt", &mut out);
+    assert_eq!(out, "việt");
+    assert_eq!(e.last_error(), None);
+}
@@ -0,0 +1,83 @@
+#![cfg(feature = "alloc")]
+
+use uvie::ime::ImeAction::{self, Commit, CommitAndForward, Forward, UpdatePreedit};
+use uvie::ime::Session;
+use uvie::{InputMethod, Key};
+
+fn run(session: &mut Session, table: &[(Key, ImeAction<'_>)]) {
+    for (i, (key, expected)) in table.iter().enumerate() {
+        assert_eq!(&session.key_event(*key), expected, "event {i}: {key:?}");
+    }
+}
+
+#[test]
+fn types_a_sentence() {
+    let c = Key::Char;
+    let mut session = Session::new(InputMethod::Telex);
+    run(
+        &mut session,
+        &[
+            (c('x'), UpdatePreedit("x")),
+            (c('i'), UpdatePreedit("xi")),
+            (c('n'), UpdatePreedit("xin")),
+            (c(' '), Commit("xin ".into())),
+            (c('c'), UpdatePreedit("c")),
+            (c('h'), UpdatePreedit("ch")),
+            (c('a'), UpdatePreedit("cha")),
+            (c('o'), UpdatePreedit("chao")),
+            (c('f'), UpdatePreedit("chào")),
+            (c(' '), Commit("chào ".into())),
+            (c('b'), UpdatePreedit("b")),
+            (c('a'), UpdatePreedit("ba")),
+            (c('n'), UpdatePreedit("ban")),
+            (c('j'), UpdatePreedit("bạn")),
+            (c('!'), Commit("bạn!".into())),
+        ],
+    );
+    assert_eq!(session.preedit(), "");
+}
+
+#[test]
+fn editing_keys() {
+    let c = Key::Char;
+    let mut session = Session::new(InputMethod::Telex);
+    run(
+        &mut session,
+        &[
+            // Backspace edits the preedit, then reaches the application
+            (c('a'), UpdatePreedit("a")),
+            (c('s'), UpdatePreedit("á")),
+            (Key::Backspace, UpdatePreedit("a")),
+            (Key::Backspace, UpdatePreedit("")),
+            (Key::Backspace, Forward(Key::Backspace)),
+            // Escape drops the preedit and is swallowed, unless there is none
+            (c('d'), UpdatePreedit("d")),
+            (c('d'), UpdatePreedit("đ")),
+            (Key::Escape, UpdatePreedit("")),
+            (Key::Escape, Forward(Key::Escape)),
+            // Enter, Tab and arrows commit and are forwarded
+            (c('o'), UpdatePreedit("o")),
+            (c('o'), UpdatePreedit("ô")),
+            (Key::Enter, CommitAndForward("ô".into(), Key::Enter)),
+            (Key::Enter, Forward(Key::Enter)),
+            (c('e'), UpdatePreedit("e")),
+            (Key::Tab, CommitAndForward("e".into(), Key::Tab)),
+            (c('u'), UpdatePreedit("u")),
+            (Key::Other, CommitAndForward("u".into(), Key::Other)),
+            // Telex digits are forwarded, ending the word
+            (c('1'), Forward(c('1'))),
+            (c('b'), UpdatePreedit("b")),
+            (c('5'), CommitAndForward("b".into(), c('5'))),
+            (c('.'), Forward(c('.'))),
+        ],
+    );
+}
+
+#[test]
+fn vni_digits_are_composed() {
+    let c = Key::Char;
+    let mut session = Session::new(InputMethod::Vni);
+    run(&mut session, &[(c('a'), UpdatePreedit("a")), (c('6'), UpdatePreedit("â")), (c('1'), UpdatePreedit("ấ"))]);
+    assert_eq!(session.focus_out().as_deref(), Some("ấ"));
+    assert_eq!(session.focus_out(), None);
+}
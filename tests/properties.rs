@@ -0,0 +1,94 @@
+//! Invariants that hold for any word of up to 32 Telex or VNI keys, checked on
+//! generated key sequences.
+#![cfg(feature = "alloc")]
+
+use proptest::prelude::*;
+use uvie::validate::is_valid_syllable;
+use uvie::{InputMethod, UltraFastViEngine, reverse};
+
+/// Most bytes a key adds to a rendered word: a precomposed Vietnamese letter is 3
+/// bytes in UTF-8 and every key renders at most one letter.
+const MAX_BYTES_PER_KEY: usize = 3;
+
+/// Keys of one word, weighted towards the ones that compose.
+fn word() -> impl Strategy<Value = (InputMethod, String)> {
+    prop_oneof![
+        "[aeiouyAEOUdDwWsfrxjzngtchmqpkl]{1,32}".prop_map(|keys| (InputMethod::Telex, keys)),
+        "[aeiouyAEOUdDngtchmqpkl0-9]{1,32}".prop_map(|keys| (InputMethod::Vni, keys)),
+    ]
+}
+
+/// Keys shaped like a syllable: onset, vowels, coda and tone, most of them valid.
+fn syllable() -> impl Strategy<Value = (InputMethod, String)> {
+    const ONSET: &str = "(ngh|ng|nh|ch|tr|th|kh|ph|gi|qu|[bcdghklmnprstvx])?";
+    const NUCLEUS: &str = "(a|e|i|o|u|y|ai|ao|au|ay|eo|eu|ia|iu|oa|oe|oi|ua|ue|ui|uo|uy|ye|ieu|oai|oay|uoi|uya|uye|yeu)";
+    const CODA: &str = "(ng|nh|ch|[cmnpt])?";
+    prop_oneof![
+        keys(format!("(dd)?{ONSET}{NUCLEUS}[aeow]?{CODA}[sfrxj]?")).prop_map(|keys| (InputMethod::Telex, keys)),
+        keys(format!("(d9)?{ONSET}{NUCLEUS}[678]?{CODA}[1-5]?")).prop_map(|keys| (InputMethod::Vni, keys)),
+    ]
+}
+
+fn keys(pattern: String) -> impl Strategy<Value = String> {
+    proptest::string::string_regex(&pattern).expect("valid key pattern")
+}
+
+fn engine(method: InputMethod) -> UltraFastViEngine {
+    UltraFastViEngine::builder().input_method(method).build()
+}
+
+proptest! {
+    // Shrunk failures belong in src/tests.rs, not in a regressions file
+    #![proptest_config(ProptestConfig { failure_persistence: None, max_global_rejects: 1 << 16, ..ProptestConfig::default() })]
+
+    #[test]
+    fn output_stays_within_bytes_per_key((method, keys) in word()) {
+        let mut e = engine(method);
+        for (n, key) in keys.chars().enumerate() {
+            let out = e.feed(key);
+            prop_assert!(out.len() <= (n + 1) * MAX_BYTES_PER_KEY, "{keys:?} rendered {out:?}");
+        }
+        let word = e.commit();
+        prop_assert!(word.len() <= keys.len() * MAX_BYTES_PER_KEY, "{keys:?} committed {word:?}");
+    }
+
+    #[test]
+    fn reverse_keys_type_the_word_again((method, keys) in syllable()) {
+        let mut e = engine(method);
+        e.feed_str(&keys);
+        let word = e.commit().to_owned();
+        prop_assume!(is_valid_syllable(&word));
+
+        let again = match method {
+            InputMethod::Vni => reverse::to_vni(&word),
+            _ => reverse::to_telex(&word),
+        };
+        e.feed_str(&keys);
+        e.feed(' ');
+        e.feed_str(&again);
+        prop_assert_eq!(e.commit(), word.as_str(), "{:?} typed again as {:?}", keys, again);
+    }
+
+    #[test]
+    fn render_depends_only_on_the_keys((method, keys) in word(), (_, before) in word()) {
+        let mut fresh = engine(method);
+        let mut used = engine(method);
+        used.feed_str(&before);
+        used.feed(' ');
+        for key in keys.chars() {
+            prop_assert_eq!(fresh.feed(key), used.feed(key));
+        }
+        prop_assert_eq!(fresh.commit(), used.commit());
+    }
+
+    #[test]
+    fn clear_resets_the_engine((method, keys) in word(), (_, before) in word()) {
+        let mut fresh = engine(method);
+        let mut cleared = engine(method);
+        cleared.feed_str(&before);
+        cleared.clear();
+        prop_assert_eq!(fresh.feed_str(&keys), cleared.feed_str(&keys));
+        prop_assert_eq!(fresh.decision(), cleared.decision());
+        prop_assert_eq!(fresh.commit(), cleared.commit());
+    }
+}
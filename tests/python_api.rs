@@ -0,0 +1,67 @@
+#![cfg(feature = "python")]
+
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyModule};
+
+fn with_uvie(code: &std::ffi::CStr) {
+    pyo3::prepare_freethreaded_python();
+    Python::with_gil(|py| {
+        let module = PyModule::new(py, "uvie").unwrap();
+        uvie::python::register(&module).unwrap();
+        let globals = PyDict::new(py);
+        globals.set_item("uvie", module).unwrap();
+        if let Err(err) = py.run(code, Some(&globals), None) {
+            err.print(py);
+            panic!("python code failed");
+        }
+    });
+}
+
+#[test]
+fn engine_class() {
+    with_uvie(
+        c"
+e = uvie.ViEngine()
+assert e.feed('a') == 'a'
+assert e.feed('a') == 'â'
+assert e.commit() == 'â'
+assert e.feed_str('xin chaof') == 'xin chào'
+e.clear()
+e.set_method('vni')
+assert e.feed_str('d9u7o7ng2') == 'đường'
+try:
+    e.set_method('dvorak')
+    raise AssertionError('unknown method accepted')
+except ValueError:
+    pass
+",
+    );
+}
+
+#[test]
+fn module_transform() {
+    with_uvie(
+        c"
+assert uvie.transform('Vieejt Nam') == 'Việt Nam'
+assert uvie.transform('to^i', method='viqr') == 'tôi'
+",
+    );
+}
+
+#[test]
+fn engine_shared_between_python_threads() {
+    with_uvie(
+        c"
+import threading
+e = uvie.ViEngine('telex')
+def work():
+    for _ in range(200):
+        e.feed_str('tooi ')
+threads = [threading.Thread(target=work) for _ in range(4)]
+for t in threads: t.start()
+for t in threads: t.join()
+e.clear()
+assert e.feed_str('ddi') == 'đi'
+",
+    );
+}
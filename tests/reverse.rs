@@ -0,0 +1,54 @@
+#![cfg(feature = "std")]
+
+use uvie::{InputMethod, reverse, transform};
+
+fn syllables() -> impl Iterator<Item = &'static str> {
+    include_str!("data/syllables.txt")
+        .lines()
+        .filter(|line| !line.starts_with('#'))
+        .flat_map(str::split_whitespace)
+}
+
+fn failures(method: InputMethod, keys: fn(&str) -> String) -> Vec<String> {
+    let mut failures = Vec::new();
+    for word in syllables() {
+        for word in [word.to_owned(), word.to_uppercase()] {
+            let typed = keys(&word);
+            let back = transform(&typed, method);
+            if back != word {
+                failures.push(format!("{word} → {typed} → {back}"));
+            }
+        }
+    }
+    failures
+}
+
+#[test]
+fn telex_keys_round_trip() {
+    assert!(syllables().count() >= 300);
+    let failures = failures(InputMethod::Telex, reverse::to_telex);
+    assert!(failures.is_empty(), "{failures:#?}");
+}
+
+#[test]
+fn telex_keys() {
+    assert_eq!(reverse::to_telex("người"), "nguwowif");
+    assert_eq!(reverse::to_telex("đặc"), "ddawcj");
+    assert_eq!(reverse::to_telex("Quốc, gì?"), "Quoocs, gif?");
+    assert_eq!(reverse::to_telex("café 42 😀"), "cafes 42 😀");
+    assert_eq!(reverse::to_telex("xoong coóc"), "xooong cooocs");
+}
+
+#[test]
+fn vni_keys_round_trip() {
+    let failures = failures(InputMethod::Vni, reverse::to_vni);
+    assert!(failures.is_empty(), "{failures:#?}");
+}
+
+#[test]
+fn vni_keys() {
+    assert_eq!(reverse::to_vni("người"), "ngu7o7i2");
+    assert_eq!(reverse::to_vni("đặc ăn"), "d9a8c5 a8n");
+    // Digits in a word would be read as keys
+    assert_eq!(reverse::to_vni("b52 mp3 2024"), "b52 mp3 2024");
+}
@@ -0,0 +1,86 @@
+use uvie::syllable::{Syllable, parse};
+use uvie::tone::Tone::{self, *};
+
+// Word, then its onset, glide, nucleus, coda and tone
+type Case = (&'static str, &'static str, Option<char>, &'static str, &'static str, Option<Tone>);
+
+#[rustfmt::skip]
+const CASES: &[Case] = &[
+    // Every onset
+    ("ăn", "", None, "ă", "n", None),
+    ("ước", "", None, "ướ", "c", Some(Sac)),
+    ("bạn", "b", None, "ạ", "n", Some(Nang)),
+    ("cá", "c", None, "á", "", Some(Sac)),
+    ("chữ", "ch", None, "ữ", "", Some(Nga)),
+    ("dạy", "d", None, "ạy", "", Some(Nang)),
+    ("đường", "đ", None, "ườ", "ng", Some(Huyen)),
+    ("gà", "g", None, "à", "", Some(Huyen)),
+    ("gì", "g", None, "ì", "", Some(Huyen)),
+    ("ghế", "gh", None, "ế", "", Some(Sac)),
+    ("giữa", "gi", None, "ữa", "", Some(Nga)),
+    ("giếng", "gi", None, "ế", "ng", Some(Sac)),
+    ("hỏi", "h", None, "ỏi", "", Some(Hoi)),
+    ("kem", "k", None, "e", "m", None),
+    ("khuya", "kh", Some('u'), "ya", "", None),
+    ("lửa", "l", None, "ửa", "", Some(Hoi)),
+    ("mười", "m", None, "ười", "", Some(Huyen)),
+    ("năm", "n", None, "ă", "m", None),
+    ("ngoài", "ng", Some('o'), "ài", "", Some(Huyen)),
+    ("nghiêng", "ngh", None, "iê", "ng", None),
+    ("nhanh", "nh", None, "a", "nh", None),
+    ("pin", "p", None, "i", "n", None),
+    ("phở", "ph", None, "ở", "", Some(Hoi)),
+    ("quốc", "q", Some('u'), "ố", "c", Some(Sac)),
+    ("quyết", "q", Some('u'), "yế", "t", Some(Sac)),
+    ("quà", "q", Some('u'), "à", "", Some(Huyen)),
+    ("rượu", "r", None, "ượu", "", Some(Nang)),
+    ("sách", "s", None, "á", "ch", Some(Sac)),
+    ("tuần", "t", Some('u'), "ầ", "n", Some(Huyen)),
+    ("thuở", "th", Some('u'), "ở", "", Some(Hoi)),
+    ("trường", "tr", None, "ườ", "ng", Some(Huyen)),
+    ("việt", "v", None, "iệ", "t", Some(Nang)),
+    ("xoong", "x", None, "oo", "ng", None),
+    // Every final
+    ("lúc", "l", None, "ú", "c", Some(Sac)),
+    ("ếch", "", None, "ế", "ch", Some(Sac)),
+    ("cơm", "c", None, "ơ", "m", None),
+    ("hoàn", "h", Some('o'), "à", "n", Some(Huyen)),
+    ("hoàng", "h", Some('o'), "à", "ng", Some(Huyen)),
+    ("tính", "t", None, "í", "nh", Some(Sac)),
+    ("họp", "h", None, "ọ", "p", Some(Nang)),
+    ("mất", "m", None, "ấ", "t", Some(Sac)),
+    // Glides and vowel groups
+    ("hoà", "h", Some('o'), "à", "", Some(Huyen)),
+    ("hòa", "h", Some('ò'), "a", "", Some(Huyen)),
+    ("khoẻ", "kh", Some('o'), "ẻ", "", Some(Hoi)),
+    ("hoặc", "h", Some('o'), "ặ", "c", Some(Nang)),
+    ("ngoằn", "ng", Some('o'), "ằ", "n", Some(Huyen)),
+    ("huệ", "h", Some('u'), "ệ", "", Some(Nang)),
+    ("huỳnh", "h", Some('u'), "ỳ", "nh", Some(Huyen)),
+    ("chuyện", "ch", Some('u'), "yệ", "n", Some(Nang)),
+    ("khuỷu", "kh", Some('u'), "ỷu", "", Some(Hoi)),
+    ("khuây", "kh", Some('u'), "ây", "", None),
+    ("muốn", "m", None, "uố", "n", Some(Sac)),
+    ("của", "c", None, "ủa", "", Some(Hoi)),
+    ("yêu", "", None, "yêu", "", None),
+    ("ngoéo", "ng", Some('o'), "éo", "", Some(Sac)),
+    // Case is kept
+    ("Nghiêng", "Ngh", None, "iê", "ng", None),
+    ("QUỐC", "Q", Some('U'), "Ố", "C", Some(Sac)),
+];
+
+#[test]
+fn parses_syllables() {
+    assert!(CASES.len() >= 50);
+    for &(word, onset, glide, nucleus, coda, tone) in CASES {
+        let expected = Syllable { onset, glide, nucleus, coda, tone };
+        assert_eq!(parse(word), Some(expected), "{word}");
+    }
+}
+
+#[test]
+fn rejects_non_syllables() {
+    for word in ["", "clear", "bzzt", "đứcx", "hòc", "việt nam", "b52", "ă"] {
+        assert_eq!(parse(word), None, "{word}");
+    }
+}
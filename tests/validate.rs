@@ -0,0 +1,45 @@
+use uvie::validate::is_valid_syllable;
+
+const VALID: &[&str] = &[
+    "a", "à", "ở", "y", "ý", "em", "anh", "ông", "ước", "yêu", "yếu", "uống",
+    "ba", "bác", "cá", "các", "chị", "chuyện", "của", "cuộc", "dạ", "đường", "đức",
+    "gà", "gì", "gìn", "ghế", "ghi", "gia", "giữ", "giữa", "giếng", "giường", "giúp",
+    "hoà", "hoạ", "hoàng", "hoặc", "huỳnh", "huých", "khoẻ", "khoét", "khuya", "khuỷu", "khuyên", "kìa", "kem", "kết",
+    "lửa", "muốn", "mười", "nghiêng", "nghĩ", "nghe", "ngoài", "ngoằn", "ngoéo", "ngủ", "người", "nhanh", "như",
+    "phở", "quà", "quạ", "quân", "quyết", "quốc", "quýt", "quên",
+    "rượu", "sách", "tết", "thuở", "tuần", "tuyết", "trường", "vịt", "việt", "xoong", "xuân",
+    "NGHIÊNG", "Quốc", "ĐẶC",
+];
+
+const INVALID: &[&str] = &[
+    // Not letters of a syllable
+    "", "việt nam", "b52", "đức!",
+    // Onsets
+    "clear", "bzzt", "string", "free", "wow", "jam", "fan", "qa",
+    // Vowel groups
+    "hoaa", "aeo", "uo", "ưo", "ieu", "oă", "ie",
+    // Finals
+    "đứcx", "hav", "tisk", "world", "chs", "anhh",
+    // Groups that need a final, or take none
+    "ă", "â", "tiê", "muô", "ngươ", "aim", "mưan", "yêun",
+    // Spelling
+    "ka", "ce", "ci", "ge", "ghà", "nge", "nghà", "kuá",
+    // ch and nh after other vowels
+    "och", "unh", "ơnh",
+    // Tones on stop finals
+    "hòc", "mảp", "bãt", "bat", "chiềc", "sàch",
+    // Two tones
+    "hóà",
+];
+
+#[test]
+fn valid_syllables() {
+    let rejected: Vec<_> = VALID.iter().filter(|w| !is_valid_syllable(w)).collect();
+    assert!(rejected.is_empty(), "{rejected:?}");
+}
+
+#[test]
+fn invalid_syllables() {
+    let accepted: Vec<_> = INVALID.iter().filter(|w| is_valid_syllable(w)).collect();
+    assert!(accepted.is_empty(), "{accepted:?}");
+}
@@ -0,0 +1,30 @@
+#![cfg(feature = "std")]
+
+use std::io::Cursor;
+
+use uvie::run_vectors;
+use uvie::vectors::FailureKind;
+
+// tests/golden.rs runs the files in tests/data
+
+#[test]
+fn reports_mismatches_with_line_numbers() {
+    let vectors = "# comment\n\nvieejt\tviệt\nvieejt\tviet\na6\tâ @vni\nnope\nas\tá\t@foo\n\
+                   vni\ta6\tâ\ntelex\tas\ta\tno tone\nfoo\tas\tá\ntelex\tas\tá\tone\ttoo many\n";
+    let failures = run_vectors(Cursor::new(vectors)).unwrap();
+    let summary: Vec<_> = failures.iter().map(|f| (f.line, &f.kind)).collect();
+    assert_eq!(
+        summary,
+        [
+            (4, &FailureKind::Word("việt".into())),
+            (4, &FailureKind::Batch("việt".into())),
+            (6, &FailureKind::Malformed("expected method<TAB>keys<TAB>expected")),
+            (7, &FailureKind::Malformed("unknown method, use @telex|@vni|@viqr|@auto")),
+            (9, &FailureKind::Word("á".into())),
+            (9, &FailureKind::Batch("á".into())),
+            (10, &FailureKind::Malformed("unknown method, use telex|vni|viqr|auto")),
+            (11, &FailureKind::Malformed("expected method<TAB>keys<TAB>expected[<TAB>comment]")),
+        ]
+    );
+    assert_eq!(failures[4].to_string(), "line 9: telex \"as\" typed as \"á\", expected \"a\" (no tone)");
+}
@@ -0,0 +1,38 @@
+//! Runs headless with `wasm-pack test --node --features wasm`.
+#![cfg(all(feature = "wasm", target_arch = "wasm32"))]
+
+use uvie::wasm::{WasmDelta, WasmEngine};
+use wasm_bindgen_test::wasm_bindgen_test;
+
+fn apply(text: &mut String, delta: WasmDelta) {
+    for _ in 0..delta.backspaces {
+        text.pop();
+    }
+    text.push_str(&delta.insert);
+}
+
+fn type_keys(e: &mut WasmEngine, keys: &str) -> String {
+    let mut text = String::new();
+    for c in keys.chars() {
+        apply(&mut text, e.feed(c.encode_utf8(&mut [0; 4])));
+    }
+    text
+}
+
+#[wasm_bindgen_test]
+fn telex_basics() {
+    let mut e = WasmEngine::new("telex").unwrap();
+    assert_eq!(type_keys(&mut e, "aa"), "â");
+    e.clear();
+    assert_eq!(type_keys(&mut e, "phoos "), "phố ");
+    assert_eq!(e.commit().insert, "");
+}
+
+#[wasm_bindgen_test]
+fn commit_patches_the_preview() {
+    let mut e = WasmEngine::new("telex").unwrap();
+    let mut text = type_keys(&mut e, "expression");
+    assert_ne!(text, "expression");
+    apply(&mut text, e.commit());
+    assert_eq!(text, "expression");
+}